@@ -0,0 +1,439 @@
+use crate::sampling::Distribution;
+use crate::{v, Canvas, Color, Tuple, BLACK, F, PI};
+
+/// What a ray sees when it doesn't hit anything.
+pub enum Background {
+    Solid(Color),
+    /// Blends from `bottom` when looking straight down to `top` when looking
+    /// straight up.
+    Gradient {
+        bottom: Color,
+        top: Color,
+    },
+    /// An image wrapped around the scene, which the path tracer also uses as
+    /// a light source.
+    Image(EnvironmentMap),
+}
+
+impl Background {
+    pub fn color_at(&self, direction: Tuple) -> Color {
+        let direction = direction.normalize();
+
+        match self {
+            Self::Solid(color) => *color,
+            Self::Gradient { bottom, top } => {
+                let fraction = (direction.y + 1.0) / 2.0;
+
+                *bottom + (*top - *bottom) * fraction
+            }
+            Self::Image(map) => map.color_at(direction),
+        }
+    }
+
+    /// The precomputed diffuse light arriving at a surface facing `normal`,
+    /// if the background is an environment map with an irradiance map.
+    #[must_use]
+    pub fn irradiance_at(&self, normal: Tuple) -> Option<Color> {
+        match self {
+            Self::Image(map) => map.irradiance_at(normal),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self::Solid(BLACK)
+    }
+}
+
+// longest side of the downsampled image used to compute irradiance maps
+const IRRADIANCE_SOURCE_WIDTH: usize = 64;
+
+/// An equirectangular (latitude/longitude) image, with its center straight
+/// ahead along -z.
+///
+/// Directions are sampled in proportion to the brightness of the image so
+/// that small bright areas, like the lamps of a studio, light the scene
+/// without much noise.
+pub struct EnvironmentMap {
+    image: Canvas,
+    rows: Distribution,
+    columns: Vec<Distribution>,
+    irradiance: Option<Canvas>,
+}
+
+impl EnvironmentMap {
+    /// # Panics
+    ///
+    /// Panics if the image is empty.
+    #[must_use]
+    pub fn new(image: Canvas) -> Self {
+        let (width, height) = (image.width, image.height);
+
+        assert!(width > 0 && height > 0, "the environment map is empty");
+        let mut columns = Vec::with_capacity(height);
+        let mut row_weights = Vec::with_capacity(height);
+
+        for y in 0..height {
+            // rows near the poles cover less of the sphere
+            let sin_theta = (PI * (y as F + 0.5) / height as F).sin();
+
+            let weights = (0..width)
+                .map(|x| image.pixel_at(x, y).luminance().max(0.0) * sin_theta)
+                .collect::<Vec<_>>();
+
+            let row = Distribution::new(&weights);
+            row_weights.push(row.total());
+            columns.push(row);
+        }
+
+        Self {
+            image,
+            rows: Distribution::new(&row_weights),
+            columns,
+            irradiance: None,
+        }
+    }
+
+    /// Precomputes a `width` by `height` map of the light a diffuse surface
+    /// receives from the environment, for use by the Whitted integrator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map would be empty.
+    #[must_use]
+    pub fn irradiance(mut self, width: usize, height: usize) -> Self {
+        assert!(width > 0 && height > 0, "the irradiance map is empty");
+
+        // the irradiance is very smooth, so a small copy of the image does
+        let source = downsample(
+            &self.image,
+            self.image.width.min(IRRADIANCE_SOURCE_WIDTH),
+            self.image.height.min(IRRADIANCE_SOURCE_WIDTH / 2),
+        );
+
+        let texel_angle = 2.0 * PI * PI / (source.width * source.height) as F;
+        let texels = iproduct(source.width, source.height)
+            .map(|(x, y)| {
+                let (direction, sin_theta) = texel_direction(x, y, source.width, source.height);
+                (direction, source.pixel_at(x, y) * (texel_angle * sin_theta))
+            })
+            .collect::<Vec<_>>();
+
+        let mut irradiance = Canvas::new(width, height);
+
+        for (x, y) in iproduct(width, height) {
+            let (normal, _) = texel_direction(x, y, width, height);
+
+            // the cosine weighted average of the light over the hemisphere
+            let sum = texels
+                .iter()
+                .map(|&(direction, light)| light * normal.dot(direction).max(0.0))
+                .sum::<Color>();
+
+            irradiance.write_pixel(x, y, sum * (1.0 / PI));
+        }
+
+        self.irradiance = Some(irradiance);
+
+        self
+    }
+
+    pub fn color_at(&self, direction: Tuple) -> Color {
+        let (x, y) = texel(&self.image, direction);
+
+        self.image.pixel_at(x, y)
+    }
+
+    #[must_use]
+    pub fn irradiance_at(&self, normal: Tuple) -> Option<Color> {
+        self.irradiance
+            .as_ref()
+            .map(|map| bilinear(map, normal.normalize()))
+    }
+
+    /// Picks a direction with a probability proportional to how much light
+    /// comes from it, returning it with its probability density per unit
+    /// solid angle.
+    #[must_use]
+    pub fn sample(&self, u1: F, u2: F) -> Option<(Tuple, F)> {
+        let (width, height) = (self.image.width as F, self.image.height as F);
+
+        let (y, ry) = self.rows.sample(u1);
+        let (x, rx) = self.columns[y].sample(u2);
+
+        let theta = PI * (y as F + ry) / height;
+        let phi = 2.0 * PI * ((x as F + rx) / width - 0.5);
+        let sin_theta = theta.sin();
+
+        if sin_theta <= 0.0 {
+            return None;
+        }
+
+        let direction = v(sin_theta * phi.sin(), theta.cos(), -sin_theta * phi.cos());
+
+        // convert the probability of the texel into a density over the
+        // solid angle it covers
+        let probability = self.rows.probability(y) * self.columns[y].probability(x);
+        let pdf = probability * width * height / (2.0 * PI * PI * sin_theta);
+
+        Some((direction, pdf))
+    }
+}
+
+impl From<Canvas> for EnvironmentMap {
+    fn from(image: Canvas) -> Self {
+        Self::new(image)
+    }
+}
+
+fn iproduct(width: usize, height: usize) -> impl Iterator<Item = (usize, usize)> {
+    (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+}
+
+// maps a unit direction to the texture coordinates of a latitude/longitude
+// image; u wraps around the y axis and v runs from the bottom to the top
+fn equirectangular(direction: Tuple) -> (F, F) {
+    let u = 0.5 + direction.x.atan2(-direction.z) / (2.0 * PI);
+    let v = 0.5 + direction.y.clamp(-1.0, 1.0).asin() / PI;
+
+    (u, v)
+}
+
+// the texel of an equirectangular image that a direction falls into
+fn texel(image: &Canvas, direction: Tuple) -> (usize, usize) {
+    let (u, v) = equirectangular(direction);
+
+    let x = (u * image.width as F) as usize;
+    let y = ((1.0 - v) * image.height as F) as usize;
+
+    (x.min(image.width - 1), y.min(image.height - 1))
+}
+
+// the direction through the center of a texel, and the sine of its angle
+// from the y axis
+fn texel_direction(x: usize, y: usize, width: usize, height: usize) -> (Tuple, F) {
+    let theta = PI * (y as F + 0.5) / height as F;
+    let phi = 2.0 * PI * ((x as F + 0.5) / width as F - 0.5);
+
+    let direction = v(
+        theta.sin() * phi.sin(),
+        theta.cos(),
+        -theta.sin() * phi.cos(),
+    );
+
+    (direction, theta.sin())
+}
+
+fn downsample(image: &Canvas, width: usize, height: usize) -> Canvas {
+    let mut sums = vec![(BLACK, 0_usize); width * height];
+
+    for (x, y) in iproduct(image.width, image.height) {
+        let i = y * height / image.height * width + x * width / image.width;
+        sums[i].0 = sums[i].0 + image.pixel_at(x, y);
+        sums[i].1 += 1;
+    }
+
+    let mut canvas = Canvas::new(width, height);
+
+    for (i, (sum, count)) in sums.into_iter().enumerate() {
+        canvas.write_pixel(i % width, i / width, sum * (1.0 / count.max(1) as F));
+    }
+
+    canvas
+}
+
+// smoothly interpolates between the texel centers of an equirectangular
+// image, wrapping around horizontally
+fn bilinear(image: &Canvas, direction: Tuple) -> Color {
+    let (u, v) = equirectangular(direction);
+
+    let x = u * image.width as F - 0.5;
+    let y = ((1.0 - v) * image.height as F - 0.5).clamp(0.0, (image.height - 1) as F);
+
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+
+    let column = |x: F| (x as isize).rem_euclid(image.width as isize) as usize;
+    let row = |y: F| (y as usize).min(image.height - 1);
+
+    let top = image.pixel_at(column(x0), row(y0)) * (1.0 - fx)
+        + image.pixel_at(column(x0 + 1.0), row(y0)) * fx;
+    let bottom = image.pixel_at(column(x0), row(y0 + 1.0)) * (1.0 - fx)
+        + image.pixel_at(column(x0 + 1.0), row(y0 + 1.0)) * fx;
+
+    top * (1.0 - fy) + bottom * fy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn the_default_background_is_black() {
+        let b = Background::default();
+
+        assert_eq!(b.color_at(v(0, 0, 1)), BLACK);
+    }
+
+    #[test]
+    fn a_solid_background_is_the_same_everywhere() {
+        let b = Background::Solid(color(0.2, 0.4, 0.6));
+
+        assert_eq!(b.color_at(v(1, 0, 0)), color(0.2, 0.4, 0.6));
+        assert_eq!(b.color_at(v(0, -1, 0)), color(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn a_gradient_blends_vertically() {
+        let b = Background::Gradient {
+            bottom: WHITE,
+            top: color(0, 0, 1),
+        };
+
+        assert_fuzzy_eq!(b.color_at(v(0, -1, 0)), WHITE);
+        assert_fuzzy_eq!(b.color_at(v(0, 1, 0)), color(0, 0, 1));
+        assert_fuzzy_eq!(b.color_at(v(0, 0, -1)), color(0.5, 0.5, 1));
+        assert_fuzzy_eq!(b.color_at(v(0, 0, -5)), color(0.5, 0.5, 1));
+    }
+
+    #[test]
+    fn mapping_directions_onto_an_equirectangular_image() {
+        for (direction, u, v) in [
+            (v(0, 0, -1), 0.5, 0.5),
+            (v(1, 0, 0), 0.75, 0.5),
+            (v(-1, 0, 0), 0.25, 0.5),
+            (v(0, 0, 1), 1.0, 0.5),
+        ] {
+            let (mu, mv) = equirectangular(direction);
+
+            assert_fuzzy_eq!(mu, u);
+            assert_fuzzy_eq!(mv, v);
+        }
+
+        // u is arbitrary at the poles
+        assert_fuzzy_eq!(equirectangular(v(0, 1, 0)).1, 1.0);
+        assert_fuzzy_eq!(equirectangular(v(0, -1, 0)).1, 0.0);
+    }
+
+    #[test]
+    fn an_image_background_is_sampled_by_direction() {
+        let mut canvas = Canvas::new(4, 3);
+        for x in 0..4 {
+            canvas.write_pixel(x, 0, color(0, 0, 1));
+            canvas.write_pixel(x, 2, color(0, 1, 0));
+        }
+        canvas.write_pixel(2, 1, color(1, 0, 0));
+
+        let b = Background::Image(canvas.into());
+
+        assert_eq!(b.color_at(v(0, 0, -1)), color(1, 0, 0));
+        assert_eq!(b.color_at(v(0, 1, 0)), color(0, 0, 1));
+        assert_eq!(b.color_at(v(0, -1, 0)), color(0, 1, 0));
+    }
+
+    fn close(a: Color, b: Color) -> bool {
+        (a - b).max_component().abs() < 0.01 && (b - a).max_component().abs() < 0.01
+    }
+
+    #[test]
+    fn a_uniform_environment_gives_uniform_irradiance() {
+        let map = EnvironmentMap::new(Canvas::new_with_color(16, 8, color(0.5, 0.5, 0.5)))
+            .irradiance(8, 4);
+
+        for normal in [v(0, 1, 0), v(0, -1, 0), v(1, 0, 0), v(0.3, 0.2, -0.9)] {
+            let e = map.irradiance_at(normal).unwrap();
+
+            assert!(close(e, color(0.5, 0.5, 0.5)), "{e:?}");
+        }
+    }
+
+    #[test]
+    fn irradiance_comes_from_the_side_facing_the_light() {
+        // the top half of the environment is bright
+        let mut canvas = Canvas::new(64, 32);
+        for (x, y) in iproduct(64, 16) {
+            canvas.write_pixel(x, y, WHITE);
+        }
+
+        let map = EnvironmentMap::new(canvas).irradiance(32, 16);
+        let up = map.irradiance_at(v(0, 1, 0)).unwrap();
+        let down = map.irradiance_at(v(0, -1, 0)).unwrap();
+        let side = map.irradiance_at(v(1, 0, 0)).unwrap();
+
+        assert!(close(up, WHITE), "{up:?}");
+        assert!(close(down, BLACK), "{down:?}");
+        assert!(close(side, color(0.5, 0.5, 0.5)), "{side:?}");
+    }
+
+    #[test]
+    fn there_is_no_irradiance_unless_it_is_precomputed() {
+        let map = EnvironmentMap::new(Canvas::new_with_color(4, 2, WHITE));
+
+        assert_eq!(map.irradiance_at(v(0, 1, 0)), None);
+        assert_eq!(Background::default().irradiance_at(v(0, 1, 0)), None);
+    }
+
+    #[test]
+    fn an_environment_map_needs_at_least_a_pixel() {
+        for (width, height) in [(0, 0), (4, 0), (0, 2)] {
+            assert!(
+                std::panic::catch_unwind(|| EnvironmentMap::new(Canvas::new(width, height)))
+                    .is_err()
+            );
+        }
+
+        let map = EnvironmentMap::new(Canvas::new(1, 1));
+
+        assert!(std::panic::catch_unwind(|| map.irradiance(0, 8)).is_err());
+    }
+
+    #[test]
+    fn sampling_prefers_the_bright_parts_of_the_environment() {
+        let mut canvas = Canvas::new_with_color(8, 4, color(0.01, 0.01, 0.01));
+        canvas.write_pixel(6, 1, color(100, 100, 100));
+
+        let map = EnvironmentMap::new(canvas);
+        let mut rng = Rng::new(0);
+
+        let hits = (0..1000)
+            .filter_map(|_| map.sample(rng.next_f(), rng.next_f()))
+            .filter(|&(d, _)| texel(&map.image, d) == (6, 1))
+            .count();
+
+        assert!(hits > 950);
+    }
+
+    #[test]
+    fn samples_of_the_environment_are_unbiased() {
+        let mut canvas = Canvas::new_with_color(8, 4, color(0.2, 0.2, 0.2));
+        canvas.write_pixel(2, 1, color(5, 5, 5));
+        canvas.write_pixel(5, 3, color(1, 1, 1));
+
+        let map = EnvironmentMap::new(canvas);
+        let mut rng = Rng::new(1);
+
+        // estimate the total light arriving at a point from all directions
+        let n = 20000;
+        let estimate = (0..n)
+            .filter_map(|_| map.sample(rng.next_f(), rng.next_f()))
+            .map(|(d, pdf)| map.color_at(d) * (1.0 / pdf))
+            .sum::<Color>()
+            * (1.0 / n as F);
+
+        let exact = iproduct(8, 4)
+            .map(|(x, y)| {
+                // the exact solid angle of a texel
+                let top = PI * y as F / 4.0;
+                let bottom = PI * (y + 1) as F / 4.0;
+                let area = 2.0 * PI / 8.0 * (top.cos() - bottom.cos());
+
+                map.image.pixel_at(x, y) * area
+            })
+            .sum::<Color>();
+
+        assert!((estimate.red - exact.red).abs() / exact.red < 0.02);
+    }
+}
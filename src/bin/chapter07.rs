@@ -13,7 +13,7 @@ fn main() {
                 .rotate_y(-PI / 4.0)
                 .translate(0, 0, 5),
         )
-        .material(floor.props().material);
+        .material(floor.props().material.clone());
 
     let right_wall = Sphere::default()
         .transform(
@@ -22,7 +22,7 @@ fn main() {
                 .rotate_y(PI / 4.0)
                 .translate(0, 0, 5),
         )
-        .material(floor.props().material);
+        .material(floor.props().material.clone());
 
     let middle = Sphere::default()
         .transform(Matrix::translation(-0.5, 1, 0.5))
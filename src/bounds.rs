@@ -0,0 +1,221 @@
+use crate::{pt, Matrix, Ray, Tuple, F, INFINITY};
+
+/// An axis-aligned box around a shape, from the corner with the smallest
+/// coordinates to the one with the largest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min: Tuple,
+    pub max: Tuple,
+}
+
+impl BoundingBox {
+    #[must_use]
+    pub fn new(min: Tuple, max: Tuple) -> Self {
+        Self { min, max }
+    }
+
+    /// A box around everything, for shapes that never end.
+    #[must_use]
+    pub fn infinite() -> Self {
+        Self::new(
+            pt(-INFINITY, -INFINITY, -INFINITY),
+            pt(INFINITY, INFINITY, INFINITY),
+        )
+    }
+
+    #[must_use]
+    pub fn is_finite(&self) -> bool {
+        [self.min, self.max]
+            .iter()
+            .all(|p| p.x.is_finite() && p.y.is_finite() && p.z.is_finite())
+    }
+
+    pub fn add_point(&mut self, point: Tuple) {
+        self.min = pt(
+            self.min.x.min(point.x),
+            self.min.y.min(point.y),
+            self.min.z.min(point.z),
+        );
+        self.max = pt(
+            self.max.x.max(point.x),
+            self.max.y.max(point.y),
+            self.max.z.max(point.z),
+        );
+    }
+
+    pub fn add_box(&mut self, other: BoundingBox) {
+        self.add_point(other.min);
+        self.add_point(other.max);
+    }
+
+    #[must_use]
+    pub fn contains_point(&self, point: Tuple) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x)
+            && (self.min.y..=self.max.y).contains(&point.y)
+            && (self.min.z..=self.max.z).contains(&point.z)
+    }
+
+    #[must_use]
+    pub fn contains_box(&self, other: BoundingBox) -> bool {
+        self.contains_point(other.min) && self.contains_point(other.max)
+    }
+
+    /// The box around this one after `transform` is applied to it.
+    #[must_use]
+    pub fn transform(&self, transform: Matrix<4>) -> Self {
+        // infinities turn into NaNs when multiplied by zero
+        if !self.is_finite() {
+            return Self::infinite();
+        }
+
+        let (min, max) = (self.min, self.max);
+        let mut bounds = Self::default();
+
+        for corner in [
+            pt(min.x, min.y, min.z),
+            pt(min.x, min.y, max.z),
+            pt(min.x, max.y, min.z),
+            pt(min.x, max.y, max.z),
+            pt(max.x, min.y, min.z),
+            pt(max.x, min.y, max.z),
+            pt(max.x, max.y, min.z),
+            pt(max.x, max.y, max.z),
+        ] {
+            bounds.add_point(transform * corner);
+        }
+
+        bounds
+    }
+
+    /// Whether `ray` passes through the box.
+    #[must_use]
+    pub fn intersects(&self, ray: Ray) -> bool {
+        self.intersection_range(ray).is_some()
+    }
+
+    /// Where `ray` enters and leaves the box, found with the same slab test
+    /// as for cubes.
+    #[must_use]
+    pub fn intersection_range(&self, ray: Ray) -> Option<(F, F)> {
+        let check_axis = |origin: F, direction: F, min: F, max: F| {
+            let tmin = (min - origin) / direction;
+            let tmax = (max - origin) / direction;
+
+            if tmin > tmax {
+                (tmax, tmin)
+            } else {
+                (tmin, tmax)
+            }
+        };
+
+        let (xtmin, xtmax) = check_axis(ray.origin.x, ray.direction.x, self.min.x, self.max.x);
+        let (ytmin, ytmax) = check_axis(ray.origin.y, ray.direction.y, self.min.y, self.max.y);
+        let (ztmin, ztmax) = check_axis(ray.origin.z, ray.direction.z, self.min.z, self.max.z);
+
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+
+        (tmin <= tmax).then_some((tmin, tmax))
+    }
+}
+
+/// An empty box, which grows to fit whatever is added to it.
+impl Default for BoundingBox {
+    fn default() -> Self {
+        Self::new(
+            pt(INFINITY, INFINITY, INFINITY),
+            pt(-INFINITY, -INFINITY, -INFINITY),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn adding_points_to_an_empty_bounding_box() {
+        let mut bounds = BoundingBox::default();
+
+        bounds.add_point(pt(-5, 2, 0));
+        bounds.add_point(pt(7, 0, -3));
+
+        assert_eq!(bounds.min, pt(-5, 0, -3));
+        assert_eq!(bounds.max, pt(7, 2, 0));
+    }
+
+    #[test]
+    fn adding_one_bounding_box_to_another() {
+        let mut bounds = BoundingBox::new(pt(-5, -2, 0), pt(7, 4, 4));
+
+        bounds.add_box(BoundingBox::new(pt(8, -7, -2), pt(14, 2, 8)));
+
+        assert_eq!(bounds.min, pt(-5, -7, -2));
+        assert_eq!(bounds.max, pt(14, 4, 8));
+    }
+
+    #[test]
+    fn checking_whether_a_box_contains_a_point_or_a_box() {
+        let bounds = BoundingBox::new(pt(5, -2, 0), pt(11, 4, 7));
+
+        assert!(bounds.contains_point(pt(5, -2, 0)));
+        assert!(bounds.contains_point(pt(8, 1, 3)));
+        assert!(!bounds.contains_point(pt(3, 0, 3)));
+        assert!(!bounds.contains_point(pt(8, 1, 8)));
+
+        assert!(bounds.contains_box(BoundingBox::new(pt(6, -1, 1), pt(10, 3, 6))));
+        assert!(!bounds.contains_box(BoundingBox::new(pt(4, -3, -1), pt(10, 3, 6))));
+    }
+
+    #[test]
+    fn transforming_a_bounding_box() {
+        let bounds = BoundingBox::new(pt(-1, -1, -1), pt(1, 1, 1));
+
+        let transformed =
+            bounds.transform(Matrix::rotation_x(PI / 4.0) * Matrix::rotation_y(PI / 4.0));
+
+        let h = F::sqrt(2.0);
+        assert_fuzzy_eq!(transformed.min, pt(-h, -1.0 - h / 2.0, -1.0 - h / 2.0));
+        assert_fuzzy_eq!(transformed.max, pt(h, 1.0 + h / 2.0, 1.0 + h / 2.0));
+    }
+
+    #[test]
+    fn transforming_an_infinite_bounding_box() {
+        let bounds = BoundingBox::infinite().transform(Matrix::translation(1, 2, 3));
+
+        assert_eq!(bounds, BoundingBox::infinite());
+    }
+
+    #[test]
+    fn intersecting_a_ray_with_a_bounding_box() {
+        let bounds = BoundingBox::new(pt(5, -2, 0), pt(11, 4, 7));
+
+        for (origin, direction, hit) in [
+            (pt(15, 1, 2), v(-1, 0, 0), true),
+            (pt(8, 6, 5), v(0, -1, 0), true),
+            (pt(8, 2, 12), v(0, 0, -1), true),
+            (pt(8, 1, 3.5), v(0, 0, 1), true),
+            (pt(9, -1, -8), v(2, 4, 6), false),
+            (pt(18, 3, 5), v(-2, 0, -4), false),
+            (pt(8, 3, 4), v(0, 0, 1), true),
+        ] {
+            let r = ray(origin, direction.normalize());
+
+            assert_eq!(bounds.intersects(r), hit);
+        }
+    }
+
+    #[test]
+    fn where_a_ray_enters_and_leaves_a_bounding_box() {
+        let bounds = BoundingBox::new(pt(-1, -1, -1), pt(1, 1, 1));
+
+        let range = bounds.intersection_range(ray(pt(0, 0, -5), v(0, 0, 1)));
+
+        assert_eq!(range, Some((4.0, 6.0)));
+        assert_eq!(
+            bounds.intersection_range(ray(pt(0, 2, -5), v(0, 0, 1))),
+            None
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{pt, v, view_transform, Canvas, Matrix, Ray, World, F, PI, REFLECTION_DEPTH};
+use crate::{
+    pt, v, view_transform, Canvas, Color, Integrator, Matrix, Ray, RenderSettings, Rng, World, F,
+    PI,
+};
 use itertools::iproduct;
 use rayon::prelude::*;
 
@@ -11,6 +14,8 @@ pub struct Camera {
     half_height: F,
     pixel_size: F,
     transform: Matrix<4>,
+    integrator: Integrator,
+    settings: RenderSettings,
 }
 impl Default for Camera {
     fn default() -> Self {
@@ -50,6 +55,8 @@ impl Camera {
             half_height,
             pixel_size,
             transform: Matrix::identity(),
+            integrator: Integrator::default(),
+            settings: RenderSettings::default(),
         }
     }
 
@@ -60,10 +67,29 @@ impl Camera {
         self
     }
 
+    #[must_use]
+    pub fn integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+
+        self
+    }
+
+    #[must_use]
+    pub fn settings(mut self, settings: RenderSettings) -> Self {
+        self.settings = settings;
+
+        self
+    }
+
     fn ray_for_pixel(&self, x: usize, y: usize) -> Ray {
-        // the offset from the edge of the canvas to the pixel's center
-        let offset_x = (0.5 + x as F) * self.pixel_size;
-        let offset_y = (0.5 + y as F) * self.pixel_size;
+        self.ray_through_pixel(x, y, 0.5, 0.5)
+    }
+
+    // `dx` and `dy` are the position inside the pixel, from 0 to 1
+    fn ray_through_pixel(&self, x: usize, y: usize, dx: F, dy: F) -> Ray {
+        // the offset from the edge of the canvas to the point in the pixel
+        let offset_x = (dx + x as F) * self.pixel_size;
+        let offset_y = (dy + y as F) * self.pixel_size;
 
         // the untransformed coordinates of the pixel in world space.
         // (remember that the camera looks toward -z, so +x is to the *left*.)
@@ -86,12 +112,7 @@ impl Camera {
 
         let pixels = iproduct!(0..canvas.width, 0..canvas.height)
             .par_bridge()
-            .map(|(x, y)| {
-                let ray = self.ray_for_pixel(x, y);
-                let color = world.color_at(ray, REFLECTION_DEPTH);
-
-                (x, y, color)
-            })
+            .map(|(x, y)| (x, y, self.color_for_pixel(world, x, y)))
             .collect::<Vec<_>>();
 
         for (x, y, color) in pixels {
@@ -100,6 +121,25 @@ impl Camera {
 
         canvas
     }
+
+    fn color_for_pixel(&self, world: &World, x: usize, y: usize) -> Color {
+        match self.integrator {
+            Integrator::Whitted => world.color_at(self.ray_for_pixel(x, y), self.settings.budget()),
+            Integrator::PathTracer { samples, max_depth } => {
+                let mut rng = Rng::new((y * self.hsize + x) as u64);
+
+                let sum = (0..samples)
+                    .map(|_| {
+                        // jitter the rays inside the pixel for antialiasing
+                        let ray = self.ray_through_pixel(x, y, rng.next_f(), rng.next_f());
+                        world.trace_path(ray, max_depth, &mut rng)
+                    })
+                    .sum::<Color>();
+
+                sum * (1.0 / samples.max(1) as F)
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -177,4 +217,45 @@ mod tests {
 
         assert_fuzzy_eq!(image.pixel_at(5, 5), color(0.38066, 0.47583, 0.2855));
     }
+
+    #[test]
+    fn the_default_camera_uses_the_whitted_integrator() {
+        let c = Camera::new(160, 120, PI / 2.0);
+
+        assert_eq!(c.integrator, Integrator::Whitted);
+    }
+
+    #[test]
+    fn rendering_a_world_with_a_path_tracer() {
+        let w = World::default();
+        let from = pt(0, 0, -5);
+        let to = pt(0, 0, 0);
+        let up = v(0, 1, 0);
+
+        let c = Camera::new(11, 11, PI / 2.0)
+            .transform(view_transform(from, to, up))
+            .integrator(Integrator::path_tracer(4));
+
+        let image = c.render(&w);
+
+        // the center pixel only sees the outer sphere, lit without ambient
+        let center = image.pixel_at(5, 5);
+        assert!(center.green > center.red && center.red > center.blue);
+        assert_eq!(image.pixel_at(0, 0), BLACK);
+    }
+
+    #[test]
+    fn path_traced_renders_are_reproducible() {
+        let w = World::default();
+        let c = Camera::new(5, 5, PI / 2.0)
+            .transform(view_transform(pt(0, 0, -5), pt(0, 0, 0), v(0, 1, 0)))
+            .integrator(Integrator::path_tracer(2));
+
+        let a = c.render(&w);
+        let b = c.render(&w);
+
+        for (x, y) in iproduct!(0..5, 0..5) {
+            assert_eq!(a.pixel_at(x, y), b.pixel_at(x, y));
+        }
+    }
 }
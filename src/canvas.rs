@@ -1,7 +1,9 @@
 use crate::{FuzzyEq, F};
-use std::io::{Result as IoResult, Write};
+use std::fmt;
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult, Write};
 use std::iter::Sum;
 use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
 
 pub fn color<R: Into<F>, G: Into<F>, B: Into<F>>(r: R, g: G, b: B) -> Color {
     Color::new(r.into(), g.into(), b.into())
@@ -40,6 +42,17 @@ impl Color {
         )
     }
 
+    /// The perceived brightness of the color.
+    #[must_use]
+    pub fn luminance(self) -> F {
+        0.2126 * self.red + 0.7152 * self.green + 0.0722 * self.blue
+    }
+
+    #[must_use]
+    pub fn max_component(self) -> F {
+        self.red.max(self.green).max(self.blue)
+    }
+
     #[must_use]
     pub fn to_u8(self) -> (u8, u8, u8) {
         let c = self.clip(0.0, 1.0);
@@ -111,6 +124,7 @@ impl FuzzyEq<Self> for Color {
 }
 
 #[must_use]
+#[derive(Clone, PartialEq)]
 pub struct Canvas {
     pub width: usize,
     pub height: usize,
@@ -118,6 +132,15 @@ pub struct Canvas {
     pixels: Vec<Color>,
 }
 
+impl fmt::Debug for Canvas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Canvas")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Canvas {
     pub fn new(width: usize, height: usize) -> Self {
         Self::new_with_color(width, height, BLACK)
@@ -175,6 +198,228 @@ impl Canvas {
         self.write_ppm_header(&mut guard)?;
         self.write_ppm_data(&mut guard)
     }
+
+    /// Reads a plain (`P3`) or binary (`P6`) PPM image.
+    pub fn from_ppm(reader: &mut impl Read) -> IoResult<Self> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+
+        let mut tokens = Tokens::new(&bytes);
+
+        let binary = match tokens.next()? {
+            b"P3" => false,
+            b"P6" => true,
+            _ => return Err(invalid_data("not a PPM file")),
+        };
+
+        let (width, height) = tokens.dimensions()?;
+        let max = tokens.parse::<u16>()?;
+
+        if max == 0 {
+            return Err(invalid_data("the maximum color value must be positive"));
+        }
+
+        let scale = F::from(max);
+        let count = samples(width, height, 3)?;
+
+        // every value takes up at least a byte, so there's no point making
+        // room for more of them than there is data left
+        if count > tokens.remaining() {
+            return Err(truncated());
+        }
+
+        let mut values = Vec::with_capacity(count);
+
+        if binary {
+            let size = if max < 256 { 1 } else { 2 };
+            let data = tokens.binary(count * size)?;
+
+            values.extend(data.chunks_exact(size).map(|c| match c {
+                [b] => F::from(*b),
+                [hi, lo] => F::from(u16::from_be_bytes([*hi, *lo])),
+                _ => unreachable!(),
+            }));
+        } else {
+            for _ in 0..count {
+                values.push(F::from(tokens.parse::<u16>()?));
+            }
+        }
+
+        let pixels = values
+            .chunks_exact(3)
+            .map(|c| Color::new(c[0] / scale, c[1] / scale, c[2] / scale))
+            .collect();
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Reads a PFM image, which stores unclamped floating point colors and
+    /// is the usual format for high dynamic range environment maps.
+    pub fn from_pfm(reader: &mut impl Read) -> IoResult<Self> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+
+        let mut tokens = Tokens::new(&bytes);
+
+        let channels = match tokens.next()? {
+            b"PF" => 3,
+            b"Pf" => 1,
+            _ => return Err(invalid_data("not a PFM file")),
+        };
+
+        let (width, height) = tokens.dimensions()?;
+
+        // the sign of the scale gives the byte order
+        let little_endian = tokens.parse::<f32>()? < 0.0;
+
+        let len = samples(width, height, channels)?
+            .checked_mul(4)
+            .ok_or_else(|| invalid_data("the image is too large"))?;
+        let data = tokens.binary(len)?;
+        let values = data
+            .chunks_exact(4)
+            .map(|c| {
+                let b = [c[0], c[1], c[2], c[3]];
+                F::from(if little_endian {
+                    f32::from_le_bytes(b)
+                } else {
+                    f32::from_be_bytes(b)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut canvas = Self::new(width, height);
+
+        // rows are stored from the bottom of the image to the top
+        for (i, c) in values.chunks_exact(channels).enumerate() {
+            let color = match c {
+                [r, g, b] => Color::new(*r, *g, *b),
+                [l] => Color::new(*l, *l, *l),
+                _ => unreachable!(),
+            };
+
+            canvas.write_pixel(i % width, height - 1 - i / width, color);
+        }
+
+        Ok(canvas)
+    }
+
+    /// The pixel at texture coordinates `u` and `v`, which run from 0 to 1
+    /// with `v` pointing up.
+    pub fn color_at_uv(&self, u: F, v: F) -> Color {
+        let v = 1.0 - v;
+
+        let x = (u * (self.width - 1) as F)
+            .round()
+            .clamp(0.0, (self.width - 1) as F);
+        let y = (v * (self.height - 1) as F)
+            .round()
+            .clamp(0.0, (self.height - 1) as F);
+
+        self.pixel_at(x as usize, y as usize)
+    }
+}
+
+fn invalid_data(message: &str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, message)
+}
+
+fn truncated() -> IoError {
+    IoError::new(ErrorKind::UnexpectedEof, "the image is truncated")
+}
+
+// the number of values stored for an image, which has to fit in memory
+fn samples(width: usize, height: usize, channels: usize) -> IoResult<usize> {
+    width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(channels))
+        .ok_or_else(|| invalid_data("the image is too large"))
+}
+
+// splits the header of netpbm style images into whitespace separated tokens,
+// skipping `#` comments
+struct Tokens<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&b) = self.bytes.get(self.position) {
+            if b == b'#' {
+                while self.bytes.get(self.position).is_some_and(|&b| b != b'\n') {
+                    self.position += 1;
+                }
+            } else if b.is_ascii_whitespace() {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next(&mut self) -> IoResult<&'a [u8]> {
+        self.skip_whitespace();
+
+        let start = self.position;
+        while self
+            .bytes
+            .get(self.position)
+            .is_some_and(|b| !b.is_ascii_whitespace())
+        {
+            self.position += 1;
+        }
+
+        if start == self.position {
+            return Err(truncated());
+        }
+
+        Ok(&self.bytes[start..self.position])
+    }
+
+    fn parse<T: FromStr>(&mut self) -> IoResult<T> {
+        let token = self.next()?;
+
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|t| t.parse().ok())
+            .ok_or_else(|| invalid_data("malformed number in image"))
+    }
+
+    fn dimensions(&mut self) -> IoResult<(usize, usize)> {
+        let width = self.parse::<usize>()?;
+        let height = self.parse::<usize>()?;
+
+        if width == 0 || height == 0 {
+            return Err(invalid_data("the image must be at least a pixel in size"));
+        }
+
+        Ok((width, height))
+    }
+
+    // the number of bytes after the current token
+    fn remaining(&self) -> usize {
+        self.bytes.len().saturating_sub(self.position)
+    }
+
+    // the binary data starts after the single whitespace byte that ends the
+    // header
+    fn binary(&mut self, len: usize) -> IoResult<&'a [u8]> {
+        let start = self.position + 1;
+
+        start
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(start..end))
+            .ok_or_else(truncated)
+    }
 }
 
 struct MaxWidthWriter<'a, T: Write> {
@@ -349,6 +594,205 @@ P3
         assert_eq!(lines, expected);
     }
 
+    #[test]
+    fn reading_a_file_with_the_wrong_magic_number() {
+        let ppm = "P32\n1 1\n255\n0 0 0\n";
+
+        let err = Canvas::from_ppm(&mut ppm.as_bytes()).err().unwrap();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reading_a_ppm_returns_a_canvas_of_the_right_size() {
+        let ppm = "\
+P3
+10 2
+255
+0 0 0  0 0 0  0 0 0  0 0 0  0 0 0
+0 0 0  0 0 0  0 0 0  0 0 0  0 0 0
+0 0 0  0 0 0  0 0 0  0 0 0  0 0 0
+0 0 0  0 0 0  0 0 0  0 0 0  0 0 0
+";
+
+        let canvas = Canvas::from_ppm(&mut ppm.as_bytes()).unwrap();
+
+        assert_eq!(canvas.width, 10);
+        assert_eq!(canvas.height, 2);
+    }
+
+    #[test]
+    fn reading_pixel_data_from_a_ppm_file() {
+        let ppm = "\
+P3
+4 3
+255
+255 127 0  0 127 255  127 255 0  255 255 255
+0 0 0  255 0 0  0 255 0  0 0 255
+255 255 0  0 255 255  255 0 255  127 127 127
+";
+
+        let canvas = Canvas::from_ppm(&mut ppm.as_bytes()).unwrap();
+
+        for (x, y, expected) in [
+            (0, 0, color(1, 0.49804, 0)),
+            (1, 0, color(0, 0.49804, 1)),
+            (2, 0, color(0.49804, 1, 0)),
+            (3, 0, color(1, 1, 1)),
+            (0, 1, color(0, 0, 0)),
+            (1, 1, color(1, 0, 0)),
+            (2, 1, color(0, 1, 0)),
+            (3, 1, color(0, 0, 1)),
+            (0, 2, color(1, 1, 0)),
+            (1, 2, color(0, 1, 1)),
+            (2, 2, color(1, 0, 1)),
+            (3, 2, color(0.49804, 0.49804, 0.49804)),
+        ] {
+            assert_fuzzy_eq!(canvas.pixel_at(x, y), expected);
+        }
+    }
+
+    #[test]
+    fn ppm_parsing_ignores_comment_lines() {
+        let ppm = "\
+P3
+# this is a comment
+2 1
+# this, too
+255
+# another comment
+255 255 255
+# oh, no, comments in the pixel data!
+255 0 255
+";
+
+        let canvas = Canvas::from_ppm(&mut ppm.as_bytes()).unwrap();
+
+        assert_fuzzy_eq!(canvas.pixel_at(0, 0), color(1, 1, 1));
+        assert_fuzzy_eq!(canvas.pixel_at(1, 0), color(1, 0, 1));
+    }
+
+    #[test]
+    fn ppm_parsing_allows_an_rgb_triple_to_span_lines() {
+        let ppm = "\
+P3
+1 1
+255
+51
+153
+
+204
+";
+
+        let canvas = Canvas::from_ppm(&mut ppm.as_bytes()).unwrap();
+
+        assert_fuzzy_eq!(canvas.pixel_at(0, 0), color(0.2, 0.6, 0.8));
+    }
+
+    #[test]
+    fn ppm_parsing_respects_the_scale_setting() {
+        let ppm = "\
+P3
+2 2
+100
+100 100 100  50 50 50
+75 50 25  0 0 0
+";
+
+        let canvas = Canvas::from_ppm(&mut ppm.as_bytes()).unwrap();
+
+        assert_fuzzy_eq!(canvas.pixel_at(0, 1), color(0.75, 0.5, 0.25));
+    }
+
+    #[test]
+    fn reading_a_binary_ppm() {
+        let mut ppm = b"P6\n2 1\n255\n".to_vec();
+        ppm.extend([255, 0, 51, 0, 255, 102]);
+
+        let canvas = Canvas::from_ppm(&mut ppm.as_slice()).unwrap();
+
+        assert_fuzzy_eq!(canvas.pixel_at(0, 0), color(1, 0, 0.2));
+        assert_fuzzy_eq!(canvas.pixel_at(1, 0), color(0, 1, 0.4));
+    }
+
+    #[test]
+    fn reading_a_truncated_ppm() {
+        let ppm = "P3\n2 1\n255\n255 255 255\n0 0\n";
+
+        let err = Canvas::from_ppm(&mut ppm.as_bytes()).err().unwrap();
+
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn reading_a_ppm_larger_than_its_data() {
+        let ppm = "P3\n100000 100000\n255\n0 0 0\n";
+
+        let err = Canvas::from_ppm(&mut ppm.as_bytes()).err().unwrap();
+
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn reading_a_ppm_too_large_to_address() {
+        let ppm = format!("P6\n{} 2\n255\n", usize::MAX);
+
+        let err = Canvas::from_ppm(&mut ppm.as_bytes()).err().unwrap();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reading_a_ppm_without_pixels() {
+        for ppm in ["P3\n0 2\n255\n", "P6\n2 0\n255\n"] {
+            let err = Canvas::from_ppm(&mut ppm.as_bytes()).err().unwrap();
+
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn reading_a_pfm() {
+        let mut pfm = b"PF\n1 2\n-1.0\n".to_vec();
+        for value in [0.5_f32, 0.25, 4.0, 1.0, 2.0, 3.0] {
+            pfm.extend(value.to_le_bytes());
+        }
+
+        let canvas = Canvas::from_pfm(&mut pfm.as_slice()).unwrap();
+
+        // the bottom row comes first and the values aren't clamped
+        assert_fuzzy_eq!(canvas.pixel_at(0, 1), color(0.5, 0.25, 4));
+        assert_fuzzy_eq!(canvas.pixel_at(0, 0), color(1, 2, 3));
+    }
+
+    #[test]
+    fn reading_a_truncated_pfm() {
+        let mut pfm = b"PF\n1 1\n1.0\n".to_vec();
+        pfm.extend(1.0_f32.to_be_bytes());
+
+        let err = Canvas::from_pfm(&mut pfm.as_slice()).err().unwrap();
+
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn reading_a_pfm_without_pixels() {
+        let pfm = b"PF\n0 0\n-1.0\n";
+
+        let err = Canvas::from_pfm(&mut pfm.as_slice()).err().unwrap();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reading_a_pfm_too_large_to_address() {
+        let pfm = format!("Pf\n{} 1\n-1.0\n", usize::MAX / 2);
+
+        let err = Canvas::from_pfm(&mut pfm.as_bytes()).err().unwrap();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
     #[test]
     fn ppm_end_newline() {
         let c = Canvas::new(10, 2);
@@ -0,0 +1,426 @@
+use crate::sampling::{cosine_hemisphere, ggx_half_vector, glossy_direction};
+use crate::{
+    color, Background, Brdf, Color, Comps, Intersections, Material, Ray, Rng, Shape, Tuple, World,
+    BLACK, INFINITY, PI, WHITE,
+};
+
+/// How `Camera::render` turns camera rays into colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Integrator {
+    /// Recursive Whitted-style ray tracing through `World::color_at`.
+    #[default]
+    Whitted,
+    /// Monte Carlo path tracing, averaging `samples` paths per pixel that
+    /// are at most `max_depth` bounces long.
+    PathTracer { samples: usize, max_depth: u8 },
+}
+
+impl Integrator {
+    #[must_use]
+    pub fn path_tracer(samples: usize) -> Self {
+        Self::PathTracer {
+            samples,
+            max_depth: 16,
+        }
+    }
+}
+
+// paths shorter than this are never terminated by russian roulette
+const MIN_BOUNCES: u8 = 3;
+
+impl World {
+    /// Estimates the light arriving along `ray` by following a single random
+    /// path through the scene.
+    ///
+    /// Surfaces are split into the same diffuse, reflected and refracted
+    /// terms that `World::shade_hit` adds together; one of them is picked at
+    /// random on every bounce. Diffuse bounces sample the point lights
+    /// directly and continue in a cosine-weighted direction, which is where
+    /// indirect light and color bleeding come from. Cook-Torrance surfaces
+    /// may continue around the mirror direction instead, spread out by their
+    /// roughness, and metals only ever do. The ambient term is
+    /// ignored since indirect light replaces it, while emissive surfaces
+    /// light up everything their bounces reach. Environment maps are sampled
+    /// directly as well, by brightness.
+    pub fn trace_path(&self, ray: Ray, max_depth: u8, rng: &mut Rng) -> Color {
+        let mut radiance = BLACK;
+        let mut throughput = WHITE;
+        let mut ray = ray;
+
+        // set when the light of the environment has already been sampled
+        // directly at the last bounce
+        let mut sampled_environment = false;
+
+        // the object the path is currently travelling through
+        let mut medium: Option<&dyn Shape> = None;
+
+        for depth in 0..max_depth {
+            let xs = self.intersect(ray);
+            let Some(hit) = xs.hit() else {
+                if !sampled_environment {
+                    let absorbed =
+                        medium.map_or(WHITE, |o| o.props().material.transmission(INFINITY));
+                    radiance =
+                        radiance + throughput * absorbed * self.background.color_at(ray.direction);
+                }
+                break;
+            };
+
+            if let Some(object) = medium {
+                throughput = throughput * object.props().material.transmission(hit.t);
+            }
+
+            let comps = hit.prepare_computations(ray, &xs);
+            let material = &comps.object.props().material;
+
+            // emissive surfaces are only found by hitting them, so their
+            // light is never counted twice by the next event estimation
+            radiance = radiance + throughput * material.emissive;
+
+            // weight the reflected and refracted terms like shade_hit does
+            let (reflected, refracted) = if material.reflective > 0.0 && material.transparency > 0.0
+            {
+                let reflectance = comps.schlick();
+                (
+                    material.reflective * reflectance,
+                    material.transparency * (1.0 - reflectance),
+                )
+            } else if material.reflective > 0.0 && material.fresnel {
+                (material.reflective * comps.schlick(), 0.0)
+            } else {
+                (material.reflective, material.transparency)
+            };
+
+            // fresnel reflections take their light away from the surface
+            let surface = if material.fresnel && material.transparency == 0.0 {
+                1.0 - reflected
+            } else {
+                1.0
+            };
+
+            // choose one of the terms in proportion to its weight, scaling
+            // the throughput by the inverse of the probability of choosing it
+            let total = surface + reflected + refracted;
+            let choice = rng.next_f() * total;
+
+            if choice < surface {
+                let color = material.color_at(comps.object, comps.over_point);
+
+                // next event estimation: sample every light directly
+                for &light in &self.lights {
+                    let visibility = self.shadow_attenuation(light, comps.over_point);
+
+                    if visibility == BLACK {
+                        continue;
+                    }
+
+                    let lightv = (light.position - comps.over_point).normalize();
+                    let direct = material.direct_light(
+                        color,
+                        lightv,
+                        light.intensity * visibility,
+                        comps.eyev,
+                        comps.normalv,
+                    );
+
+                    radiance = radiance + throughput * direct * total;
+                }
+
+                // sample the environment in proportion to its brightness
+                if let Background::Image(map) = &self.background {
+                    if let Some((lightv, pdf)) = map.sample(rng.next_f(), rng.next_f()) {
+                        let visibility = self.transmittance(comps.over_point, lightv, INFINITY);
+
+                        if visibility != BLACK {
+                            // direct_light includes the cosine term and the
+                            // diffuse albedo but not the 1/pi of the BRDF
+                            let direct = material.direct_light(
+                                color,
+                                lightv,
+                                map.color_at(lightv) * visibility,
+                                comps.eyev,
+                                comps.normalv,
+                            );
+
+                            radiance = radiance + throughput * direct * (total / (PI * pdf));
+                        }
+                    }
+                }
+
+                let Some((direction, weight)) = surface_bounce(material, color, &comps, rng) else {
+                    break;
+                };
+                throughput = throughput * weight * total;
+                ray = Ray::new(comps.over_point, direction);
+                medium = comps.from_object;
+                sampled_environment = matches!(self.background, Background::Image(_));
+            } else if choice < surface + reflected {
+                sampled_environment = false;
+                throughput = throughput * total;
+                let direction = glossy_direction(comps.reflectv, comps.normalv, material.blur, rng);
+                ray = Ray::new(comps.over_point, direction);
+                medium = comps.from_object;
+            } else {
+                sampled_environment = false;
+                throughput = throughput * total;
+
+                // with dispersion, follow a single channel picked at random
+                let direction = match comps.dispersed_indices() {
+                    Some(indices) => {
+                        let channel = ((rng.next_f() * 3.0) as usize).min(2);
+                        let (n1, n2) = indices[channel];
+                        let mut mask = [0.0; 3];
+                        mask[channel] = 3.0;
+
+                        throughput = throughput * color(mask[0], mask[1], mask[2]);
+                        comps.refracted_direction_between(n1, n2)
+                    }
+                    None => comps.refracted_direction(),
+                };
+
+                (ray, medium) = match direction {
+                    Some(direction) => {
+                        let direction =
+                            glossy_direction(direction, comps.normalv, material.blur, rng);
+                        (Ray::new(comps.under_point, direction), comps.to_object)
+                    }
+                    // total internal reflection sends everything back
+                    None => (
+                        Ray::new(comps.over_point, comps.reflectv),
+                        comps.from_object,
+                    ),
+                };
+            }
+
+            // russian roulette: terminate dim paths early, boosting the
+            // survivors so that the estimate stays unbiased
+            if depth >= MIN_BOUNCES {
+                let survival = throughput.max_component().min(0.95);
+
+                if rng.next_f() >= survival {
+                    break;
+                }
+
+                throughput = throughput * (1.0 / survival);
+            }
+        }
+
+        radiance
+    }
+}
+
+// Picks the direction of a bounce off the surface along with the weight it
+// gives the path, or `None` when the path ends there.
+fn surface_bounce(
+    material: &Material,
+    color: Color,
+    comps: &Comps,
+    rng: &mut Rng,
+) -> Option<(Tuple, Color)> {
+    let n_dot_v = comps.eyev.dot(comps.normalv);
+    let diffuse = material.diffuse_albedo(color, comps.eyev, comps.normalv);
+
+    // Phong's highlights only ever come from the lights, while Cook-Torrance
+    // reflects the light of everything around it as well
+    let specular = match material.brdf {
+        Brdf::Phong => BLACK,
+        Brdf::CookTorrance => material.schlick_reflectance(color, n_dot_v.max(0.0)),
+    };
+
+    // follow each lobe in proportion to roughly how much light it reflects
+    let weights = diffuse.max_component() + specular.max_component();
+    if weights <= 0.0 {
+        return None;
+    }
+    let p_specular = specular.max_component() / weights;
+
+    if p_specular == 0.0 || rng.next_f() >= p_specular {
+        // with cosine-weighted sampling the cosine term and the pdf cancel
+        // out, leaving the diffuse albedo as the path weight
+        let direction = cosine_hemisphere(comps.normalv, rng.next_f(), rng.next_f());
+
+        return Some((direction, diffuse * (1.0 / (1.0 - p_specular))));
+    }
+
+    // mirror the eye around a microfacet picked from the GGX distribution,
+    // which is what spreads the reflection out on rough surfaces
+    let halfway = ggx_half_vector(
+        comps.normalv,
+        material.ggx_alpha(),
+        rng.next_f(),
+        rng.next_f(),
+    );
+    let direction = -comps.eyev.reflect(halfway);
+
+    let n_dot_l = direction.dot(comps.normalv);
+    let n_dot_h = halfway.dot(comps.normalv);
+    let v_dot_h = comps.eyev.dot(halfway);
+
+    if n_dot_l <= 0.0 || n_dot_v <= 0.0 || v_dot_h <= 0.0 {
+        return None;
+    }
+
+    // the distribution cancels out against the density of the sample
+    let fresnel = material.schlick_reflectance(color, v_dot_h);
+    let g = material.smith_geometry(n_dot_l, n_dot_v);
+
+    Some((
+        direction,
+        fresnel * (g * v_dot_h / (n_dot_v * n_dot_h * p_specular)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn the_default_integrator_is_whitted() {
+        assert_eq!(Integrator::default(), Integrator::Whitted);
+    }
+
+    #[test]
+    fn a_path_that_misses_everything_is_black() {
+        let w = World::default();
+        let r = ray(pt(0, 0, -5), v(0, 1, 0));
+        let mut rng = Rng::new(0);
+
+        assert_eq!(w.trace_path(r, 5, &mut rng), BLACK);
+    }
+
+    #[test]
+    fn a_path_has_no_ambient_term() {
+        // the outer sphere of the default world is convex and the inner one
+        // sits inside it, so every diffuse bounce escapes to the void and only
+        // the direct light remains
+        let w = World::default();
+        let r = ray(pt(0, 0, -5), v(0, 0, 1));
+
+        for seed in 0..10 {
+            let mut rng = Rng::new(seed);
+            let c = w.trace_path(r, 5, &mut rng);
+
+            assert_fuzzy_eq!(c, color(0.30066, 0.37583, 0.2255));
+        }
+    }
+
+    #[test]
+    fn diffuse_bounces_bleed_color_onto_nearby_surfaces() {
+        let floor = Plane::default();
+        let wall = Plane::default()
+            .transform(Matrix::translation(0, 0, 1) * Matrix::rotation_x(PI / 2.0))
+            .material(Material::default().rgb(1, 0, 0).specular(0));
+
+        let w = World {
+            objects: vec![floor.into(), wall.into()],
+            lights: vec![point_light(pt(0, 5, -5), color(1, 1, 1))],
+            ..Default::default()
+        };
+
+        let r = ray(pt(0, 1, -1), v(0, -1, 0));
+        let mut rng = Rng::new(1);
+        let c = (0..500)
+            .map(|_| w.trace_path(r, 5, &mut rng))
+            .sum::<Color>();
+
+        assert!(c.red > c.blue);
+        assert!(c.red > c.green);
+    }
+
+    #[test]
+    fn emissive_objects_light_the_scene() {
+        let floor = Plane::default();
+        let ceiling = Plane::default()
+            .transform(Matrix::translation(0, 2, 0))
+            .material(Material::default().emissive(color(1, 1, 1)));
+
+        let w = World {
+            objects: vec![floor.into(), ceiling.into()],
+            lights: vec![],
+            ..Default::default()
+        };
+
+        let r = ray(pt(0, 1, -1), v(0, -1, 0));
+        let mut rng = Rng::new(4);
+        let c = (0..200)
+            .map(|_| w.trace_path(r, 2, &mut rng))
+            .sum::<Color>()
+            * (1.0 / 200.0);
+
+        // every diffuse bounce off the floor sees the glowing ceiling
+        assert_fuzzy_eq!(c, color(0.9, 0.9, 0.9));
+    }
+
+    #[test]
+    fn metals_get_no_diffuse_bounces() {
+        // a lamp above and in front of a smooth ball, which diffuse bounces
+        // find but the reflection straight back towards the eye never does
+        let render = |metallic: F| {
+            let ball = Sphere::default().material(
+                Material::default()
+                    .brdf(Brdf::CookTorrance)
+                    .metallic(metallic)
+                    .roughness(0),
+            );
+            let lamp = Sphere::default()
+                .transform(Matrix::translation(0, 3, -3))
+                .material(Material::default().emissive(color(1, 1, 1)));
+
+            let w = World {
+                objects: vec![ball.into(), lamp.into()],
+                lights: vec![],
+                ..Default::default()
+            };
+
+            let r = ray(pt(0, 0, -5), v(0, 0, 1));
+            let mut rng = Rng::new(8);
+
+            (0..200)
+                .map(|_| w.trace_path(r, 2, &mut rng))
+                .sum::<Color>()
+        };
+
+        assert_eq!(render(1.0), BLACK);
+        assert!(render(0.0).red > 0.0);
+    }
+
+    #[test]
+    fn the_background_lights_the_scene() {
+        let floor = Plane::default();
+
+        let w = World {
+            objects: vec![floor.into()],
+            lights: vec![],
+            background: Background::Solid(color(0.5, 0.5, 0.5)),
+        };
+
+        let r = ray(pt(0, 1, -1), v(0, -1, 0));
+        let mut rng = Rng::new(5);
+        let c = (0..200)
+            .map(|_| w.trace_path(r, 2, &mut rng))
+            .sum::<Color>()
+            * (1.0 / 200.0);
+
+        assert_fuzzy_eq!(c, color(0.45, 0.45, 0.45));
+    }
+
+    #[test]
+    fn an_environment_map_lights_the_scene() {
+        let floor = Plane::default().material(Material::default().specular(0));
+        let environment = Canvas::new_with_color(8, 4, color(0.5, 0.5, 0.5));
+
+        let w = World {
+            objects: vec![floor.into()],
+            lights: vec![],
+            background: Background::Image(environment.into()),
+        };
+
+        let r = ray(pt(0, 1, -1), v(0, -1, 0));
+        let mut rng = Rng::new(6);
+        let n = 2000;
+        let c = (0..n).map(|_| w.trace_path(r, 2, &mut rng)).sum::<Color>() * (1.0 / n as F);
+
+        assert!((c.red - 0.45).abs() < 0.02, "{c:?}");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::material::WAVELENGTHS;
 use crate::{Ray, Shape, Tuple, EPSILON, F};
 
 pub struct Comps<'shape> {
@@ -12,6 +13,10 @@ pub struct Comps<'shape> {
     pub reflectv: Tuple,
     pub n1: F,
     pub n2: F,
+    /// The object the ray travels through before the hit, if any.
+    pub from_object: Option<&'shape dyn Shape>,
+    /// The object a refracted ray travels through after the hit, if any.
+    pub to_object: Option<&'shape dyn Shape>,
 }
 
 impl<'shape> Comps<'shape> {
@@ -40,48 +45,135 @@ impl<'shape> Comps<'shape> {
 
         r0 + (1.0 - r0) * (1.0 - cos).powi(5)
     }
+
+    /// The direction of the ray refracted at this intersection, or `None`
+    /// under total internal reflection.
+    #[must_use]
+    pub fn refracted_direction(&self) -> Option<Tuple> {
+        self.refracted_direction_between(self.n1, self.n2)
+    }
+
+    /// Like `Comps::refracted_direction` but with the given refractive
+    /// indices on either side of the surface.
+    #[must_use]
+    pub fn refracted_direction_between(&self, n1: F, n2: F) -> Option<Tuple> {
+        // find the ratio of the first index of refraction to the second
+        // inverted from the definition of Snell's Law
+        let n_ratio = n1 / n2;
+
+        // cos(theta_i) is the same as the dot product of the two vectors
+        let cos_i = self.eyev.dot(self.normalv);
+
+        // find sin(theta_t) ^ 2 via trigonometric identity
+        let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
+        if sin2_t > 1.0 {
+            return None;
+        }
+
+        // cos(theta_t) via trigonometric identity
+        let cos_t = (1.0 - sin2_t).sqrt();
+
+        // compute the direction of the refracted ray
+        Some(self.normalv * (n_ratio * cos_i - cos_t) - self.eyev * n_ratio)
+    }
+
+    /// The indices on either side of the surface for the red, green and
+    /// blue channels, or `None` when neither side disperses light.
+    #[must_use]
+    pub fn dispersed_indices(&self) -> Option<[(F, F); 3]> {
+        let dispersive =
+            |o: Option<&dyn Shape>| o.is_some_and(|o| o.props().material.dispersion.is_some());
+
+        if !dispersive(self.from_object) && !dispersive(self.to_object) {
+            return None;
+        }
+
+        let index = |o: Option<&dyn Shape>, wavelength| {
+            o.map_or(1.0, |o| o.props().material.refractive_index_at(wavelength))
+        };
+
+        Some(WAVELENGTHS.map(|w| (index(self.from_object, w), index(self.to_object, w))))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Intersection<'shape> {
     pub t: F,
     pub object: &'shape dyn Shape,
+    /// The index of the triangle of a mesh that was hit, and the barycentric
+    /// weights `u` and `v` of its second and third corners at the hit.
+    pub triangle: Option<usize>,
+    pub u: F,
+    pub v: F,
 }
 
 impl<'shape> Intersection<'shape> {
     pub fn new(t: F, object: &'shape dyn Shape) -> Intersection<'shape> {
-        Self { t, object }
+        Self {
+            t,
+            object,
+            triangle: None,
+            u: 0.0,
+            v: 0.0,
+        }
+    }
+
+    /// An intersection with `triangle` of a mesh, at the point given by its
+    /// barycentric weights `u` and `v`.
+    pub fn with_uv(
+        t: F,
+        object: &'shape dyn Shape,
+        triangle: usize,
+        u: F,
+        v: F,
+    ) -> Intersection<'shape> {
+        Self {
+            t,
+            object,
+            triangle: Some(triangle),
+            u,
+            v,
+        }
     }
 
     #[must_use]
-    pub fn prepare_computations(&self, ray: Ray, intersections: &[Intersection]) -> Comps {
+    pub fn prepare_computations(
+        &self,
+        ray: Ray,
+        intersections: &[Intersection<'shape>],
+    ) -> Comps<'shape> {
         let t = self.t;
         let object = self.object;
 
         let point = ray.position(t);
         let eyev = -ray.direction;
 
-        let mut normalv = object.normal_at(point);
+        let mut geometric_normal = object.normal_at_hit(point, self);
+        let mut normalv = match &object.props().material.perturbation {
+            Some(perturbation) => perturbation.perturb(object, point, geometric_normal),
+            None => geometric_normal,
+        };
         let mut inside = false;
 
-        if normalv.dot(eyev) < 0.0 {
+        if geometric_normal.dot(eyev) < 0.0 {
             inside = true;
+            geometric_normal = -geometric_normal;
             normalv = -normalv;
         }
 
-        let over_point = point + normalv * EPSILON;
-        let under_point = point - normalv * EPSILON;
+        // offset along the true surface normal, since a perturbed one could
+        // point below the surface and cause acne
+        let over_point = point + geometric_normal * EPSILON;
+        let under_point = point - geometric_normal * EPSILON;
         let reflectv = ray.direction.reflect(normalv);
 
-        let mut n1 = 1.0;
-        let mut n2 = 1.0;
+        let mut from_object = None;
+        let mut to_object = None;
         let mut containers = Vec::<&dyn Shape>::new();
 
         for i in intersections {
             if i == self {
-                if let Some(o) = containers.last() {
-                    n1 = o.props().material.refractive_index;
-                }
+                from_object = containers.last().copied();
             }
 
             if let Some(index) = containers.iter().position(|&o| o == i.object) {
@@ -91,13 +183,17 @@ impl<'shape> Intersection<'shape> {
             }
 
             if i == self {
-                if let Some(o) = containers.last() {
-                    n2 = o.props().material.refractive_index;
-                }
+                to_object = containers.last().copied();
                 break;
             }
         }
 
+        // a dispersive material only knows its index for some wavelength, so
+        // use the one in the middle of the spectrum
+        let index = |o: &dyn Shape| o.props().material.refractive_index_at(WAVELENGTHS[1]);
+        let n1 = from_object.map_or(1.0, index);
+        let n2 = to_object.map_or(1.0, index);
+
         Comps {
             t,
             object,
@@ -110,16 +206,18 @@ impl<'shape> Intersection<'shape> {
             reflectv,
             n1,
             n2,
+            from_object,
+            to_object,
         }
     }
 }
 
-pub trait Intersections {
-    fn hit(&self) -> Option<&Intersection<'_>>;
+pub trait Intersections<'shape> {
+    fn hit(&self) -> Option<&Intersection<'shape>>;
 }
 
-impl Intersections for Vec<Intersection<'_>> {
-    fn hit(&self) -> Option<&Intersection<'_>> {
+impl<'shape> Intersections<'shape> for Vec<Intersection<'shape>> {
+    fn hit(&self) -> Option<&Intersection<'shape>> {
         self.iter()
             .filter(|&i| i.t >= 0.0)
             .min_by(|&a, &b| a.t.total_cmp(&b.t))
@@ -333,6 +431,27 @@ mod tests {
         assert_eq!(reflectance, 1.0);
     }
 
+    #[test]
+    fn glass_with_only_cauchy_coefficients_reflects_at_grazing_angles() {
+        let shape = Sphere::default().material(
+            Material::default()
+                .transparency(1)
+                .dispersion(Dispersion::Cauchy { a: 1.5, b: 0.004 }),
+        );
+        let r = ray(pt(0, 0, twosqrttwo()), v(0, 1, 0));
+
+        let xs = [
+            shape.intersection(-twosqrttwo()),
+            shape.intersection(twosqrttwo()),
+        ];
+
+        let comps = xs[1].prepare_computations(r, &xs);
+
+        assert_fuzzy_eq!(comps.n1, 1.51158);
+        assert_eq!(comps.n2, 1.0);
+        assert_eq!(comps.schlick(), 1.0);
+    }
+
     #[test]
     fn the_schlick_approximation_with_a_perpendicular_viewing_angle() {
         let shape = glass_sphere();
@@ -355,4 +474,51 @@ mod tests {
         let reflectance = comps.schlick();
         assert_fuzzy_eq!(reflectance, 0.48873);
     }
+
+    #[test]
+    fn precomputing_a_perturbed_normal() {
+        let map = UvPattern::image(Canvas::new_with_color(2, 2, color(1, 0.5, 0.5)));
+        let shape = Plane::default().material(
+            Material::default().perturbation(Perturbation::normal_map(map, UvMapping::Planar)),
+        );
+        let r = ray(pt(0.5, 1, 0.5), v(0, -1, 0));
+        let i = shape.intersection(1.0);
+
+        let comps = i.prepare_computations(r, &[i]);
+
+        assert_fuzzy_eq!(comps.normalv, v(1, 0, 0));
+        assert_fuzzy_eq!(comps.reflectv, v(0, -1, 0).reflect(v(1, 0, 0)));
+        // the offsets still follow the surface itself
+        assert_fuzzy_eq!(comps.over_point, pt(0.5, EPSILON, 0.5));
+        assert_fuzzy_eq!(comps.under_point, pt(0.5, -EPSILON, 0.5));
+        assert!(!comps.inside);
+    }
+
+    #[test]
+    fn finding_the_objects_on_either_side_of_intersections() {
+        let a = glass_sphere().transform(Matrix::scaling(2, 2, 2));
+        let b = glass_sphere()
+            .transform(Matrix::translation(0, 0, -0.25))
+            .material(Material::default().refractive_index(2));
+
+        let r = ray(pt(0, 0, -4), v(0, 0, 1));
+        let xs = &[
+            a.intersection(2.0),
+            b.intersection(2.75),
+            b.intersection(4.75),
+            a.intersection(6.0),
+        ];
+
+        let comps = xs.iter().map(|i| i.prepare_computations(r, xs));
+
+        let a: &dyn Shape = &a;
+        let b: &dyn Shape = &b;
+        let from = [None, Some(a), Some(b), Some(a)];
+        let to = [Some(a), Some(b), Some(a), None];
+
+        for (c, from, to) in izip!(comps, from, to) {
+            assert!(c.from_object == from);
+            assert!(c.to_object == to);
+        }
+    }
 }
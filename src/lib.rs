@@ -3,30 +3,55 @@ mod utils;
 #[macro_use]
 mod matrix;
 
+mod background;
+mod bounds;
 mod camera;
 mod canvas;
+mod integrator;
 mod intersection;
 mod material;
 mod pattern;
 mod ray;
+mod roots;
+mod sampling;
+mod settings;
 mod shapes;
 mod transformation;
 mod tuple;
 mod world;
 
+pub use background::{Background, EnvironmentMap};
+pub use bounds::BoundingBox;
 pub use camera::Camera;
 pub use canvas::{color, Canvas, Color, BLACK, WHITE};
+pub use integrator::Integrator;
 pub use intersection::{Comps, Intersection, Intersections};
-pub use material::Material;
+pub use material::{Brdf, Dispersion, Material, Perturbation};
 pub use matrix::Matrix;
-pub use pattern::{checkers, gradient, ring, stripe, Pattern};
+pub use pattern::{
+    average, blend, checkers, cube_map, dots, gradient, hexagon, marble, mask, perturb,
+    radial_gradient, ring, ring_gradient, stripe, texture_map, turbulence, wood, Noise, Paint,
+    Pattern, UvMapping, UvPattern,
+};
 pub use ray::{point_light, ray, PointLight, Ray};
+pub use sampling::Rng;
+pub use settings::{RayBudget, RenderSettings};
 pub use shapes::{
+    bezier::BezierPatch,
     cone::Cone,
     cube::Cube,
+    curve::Curve,
     cylinder::Cylinder,
+    disk::Disk,
+    heightfield::Heightfield,
+    mesh::Mesh,
+    metaballs::Metaballs,
     plane::{glass as glass_plane, Plane},
+    quadric::Quadric,
+    rect::Rect,
+    sdf::{DistanceFn, Sdf, SdfNode},
     sphere::{glass as glass_sphere, Sphere},
+    torus::Torus,
     {AnyShape, Props, Shape, Transforms},
 };
 pub use transformation::view_transform;
@@ -36,7 +61,6 @@ pub use world::World;
 
 pub type F = f64;
 pub const PI: F = std::f64::consts::PI;
-pub const INFINITY: F = std::f64::INFINITY;
+pub const INFINITY: F = F::INFINITY;
 
 const EPSILON: F = 1e-5;
-const REFLECTION_DEPTH: u8 = 5;
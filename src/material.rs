@@ -1,6 +1,9 @@
-use crate::{color, Color, Pattern, PointLight, Shape, Tuple, BLACK, F, WHITE};
+use crate::{
+    color, v, Color, Pattern, PointLight, Shape, Tuple, UvMapping, UvPattern, BLACK, EPSILON, F,
+    PI, WHITE,
+};
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Material {
     pub color: Color,
     pub ambient: F,
@@ -8,12 +11,136 @@ pub struct Material {
     pub specular: F,
     pub shininess: F,
     pub reflective: F,
+    /// Whether opaque reflective materials reflect more at grazing angles,
+    /// like varnish or plastic, following Schlick's approximation with the
+    /// `refractive_index`.
+    pub fresnel: bool,
     pub transparency: F,
+    /// How far reflected and refracted rays stray from the ideal direction,
+    /// as the tangent of the angle they stray by at most. Zero keeps perfect
+    /// mirrors and clear glass, while higher values give brushed metal and
+    /// frosted glass.
+    pub blur: F,
+    /// The number of rays averaged for blurry reflections and refractions.
+    pub blur_samples: usize,
     pub refractive_index: F,
+    /// Makes the refractive index depend on the wavelength, so that red,
+    /// green and blue light refract at different angles.
+    pub dispersion: Option<Dispersion>,
+    /// How quickly light is absorbed inside the material, per channel and
+    /// unit of distance. Absorbing blue faster than red tints thick glass
+    /// orange, while thin panes stay almost clear.
+    pub absorption: Color,
+    pub emissive: Color,
+    /// How the diffuse and specular terms are computed.
+    pub brdf: Brdf,
+    /// How metal-like a Cook-Torrance material is, from 0 to 1.
+    pub metallic: F,
+    /// How rough a Cook-Torrance material is, from 0 (mirror-like) to 1.
+    pub roughness: F,
+    /// Whether shadows cast through this material take on its color.
+    pub tinted_shadow: bool,
     pub pattern: Option<Pattern>,
+    /// Changes the shading normals, giving the surface some relief.
+    pub perturbation: Option<Perturbation>,
+}
+
+/// The model used for light reflected off a material.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Brdf {
+    /// The classic model, using `diffuse`, `specular` and `shininess`.
+    #[default]
+    Phong,
+    /// A metallic-roughness model with a GGX distribution, Smith geometry
+    /// and Schlick's Fresnel approximation, using `color`, `metallic` and
+    /// `roughness`.
+    CookTorrance,
+}
+
+/// How the refractive index of a material changes with the wavelength.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Dispersion {
+    /// The Abbe number of the material, lower numbers dispersing light
+    /// more. Typical glasses are between 30 and 60.
+    Abbe(F),
+    /// The coefficients of Cauchy's equation `n = a + b / λ²`, with `λ` in
+    /// micrometres.
+    Cauchy { a: F, b: F },
+}
+
+/// The wavelengths in micrometres used for the red, green and blue channels:
+/// the Fraunhofer C, d and F lines that also define the Abbe number.
+pub const WAVELENGTHS: [F; 3] = [0.6563, 0.5876, 0.4861];
+
+/// A way of changing the shading normal of a surface without changing its
+/// geometry.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Perturbation {
+    /// Treats the brightness of the pattern as the height of the surface,
+    /// tilting the normal away from higher ground. `scale` exaggerates the
+    /// slopes.
+    Bump { pattern: Pattern, scale: F },
+    /// Reads tangent space normals from a texture, with red along the
+    /// tangent, green along the bitangent and blue along the normal.
+    NormalMap { map: UvPattern, mapping: UvMapping },
+}
+
+impl Perturbation {
+    #[must_use]
+    pub fn bump(pattern: Pattern, scale: impl Into<F>) -> Self {
+        Self::Bump {
+            pattern,
+            scale: scale.into(),
+        }
+    }
+
+    #[must_use]
+    pub fn normal_map(map: UvPattern, mapping: UvMapping) -> Self {
+        Self::NormalMap { map, mapping }
+    }
+
+    /// The shading normal at `point` given the geometric `normalv`.
+    pub fn perturb(&self, object: &dyn Shape, point: Tuple, normalv: Tuple) -> Tuple {
+        match self {
+            Self::Bump { pattern, scale } => {
+                let height = |p| pattern.color_at_object(object, p).luminance();
+
+                // estimate the gradient of the height with central differences
+                let gradient = v(
+                    height(point + v(EPSILON, 0, 0)) - height(point - v(EPSILON, 0, 0)),
+                    height(point + v(0, EPSILON, 0)) - height(point - v(0, EPSILON, 0)),
+                    height(point + v(0, 0, EPSILON)) - height(point - v(0, 0, EPSILON)),
+                ) * (1.0 / (2.0 * EPSILON));
+
+                // only the slope along the surface tilts the normal
+                let slope = gradient - normalv * gradient.dot(normalv);
+
+                (normalv - slope * *scale).normalize()
+            }
+            Self::NormalMap { map, mapping } => {
+                let object_point = object.props().transform.inverse() * point;
+                let (u, w) = mapping.map(object_point);
+                let c = map.color_at(u, w);
+
+                // make the tangent perpendicular to the normal in case the
+                // transformation skewed them
+                let tangent = object.tangent_at(point);
+                let t = (tangent - normalv * tangent.dot(normalv)).normalize();
+                let b = normalv.cross(t);
+
+                let n = t * (2.0 * c.red - 1.0)
+                    + b * (2.0 * c.green - 1.0)
+                    + normalv * (2.0 * c.blue - 1.0);
+
+                n.normalize()
+            }
+        }
+    }
 }
 
 impl Material {
+    /// The light leaving `point` towards the eye for a single light source,
+    /// including the material's own emission.
     pub fn lighting(
         &self,
         object: &dyn Shape,
@@ -23,27 +150,105 @@ impl Material {
         normalv: Tuple,
         in_shadow: bool,
     ) -> Color {
-        #[allow(clippy::needless_late_init)]
-        let ambient_light: Color;
-        let diffuse_light: Color;
-        let specular_light: Color;
+        let visibility = if in_shadow { BLACK } else { WHITE };
 
-        let color = self
-            .pattern
-            .map_or(self.color, |p| p.color_at_object(object, point));
+        self.emissive + self.illuminate(object, light, point, eyev, normalv, visibility)
+    }
 
-        // combine the surface color with the light's color/intensity
-        let effective_color = color * light.intensity;
+    /// Like `Material::lighting` but without the emission, so that summing it
+    /// over several lights doesn't count the emission more than once. The
+    /// `visibility` is the fraction of the light that reaches the point.
+    pub fn illuminate(
+        &self,
+        object: &dyn Shape,
+        light: PointLight,
+        point: Tuple,
+        eyev: Tuple,
+        normalv: Tuple,
+        visibility: Color,
+    ) -> Color {
+        let color = self.color_at(object, point);
+
+        // compute the ambient contribution
+        let ambient_light = color * light.intensity * self.ambient;
+
+        if visibility == BLACK {
+            return ambient_light;
+        }
 
         // find the direction to the light source
         let lightv = (light.position - point).normalize();
+        let intensity = light.intensity * visibility;
 
-        // compute the ambient contribution
-        ambient_light = effective_color * self.ambient;
+        ambient_light + self.direct_light(color, lightv, intensity, eyev, normalv)
+    }
 
-        if in_shadow {
-            return ambient_light;
+    /// The refractive index for light of the given `wavelength` in
+    /// micrometres, which is just `refractive_index` without dispersion.
+    pub fn refractive_index_at(&self, wavelength: F) -> F {
+        let [c, d, f] = WAVELENGTHS;
+
+        match self.dispersion {
+            None => self.refractive_index,
+            Some(Dispersion::Cauchy { a, b }) => a + b / wavelength.powi(2),
+            Some(Dispersion::Abbe(abbe)) => {
+                // fit Cauchy's equation through the index at the d line and
+                // the spread between the F and C lines given by the number
+                let b = (self.refractive_index - 1.0) / (abbe * (f.powi(-2) - c.powi(-2)));
+                let a = self.refractive_index - b / d.powi(2);
+
+                a + b / wavelength.powi(2)
+            }
+        }
+    }
+
+    /// The fraction of light left after travelling `distance` through the
+    /// material, following the Beer-Lambert law.
+    pub fn transmission(&self, distance: F) -> Color {
+        // skip the exponent for clear channels, which would otherwise turn
+        // into NaN for rays that travel an infinite distance
+        let channel = |a: F| if a == 0.0 { 1.0 } else { (-a * distance).exp() };
+
+        color(
+            channel(self.absorption.red),
+            channel(self.absorption.green),
+            channel(self.absorption.blue),
+        )
+    }
+
+    /// The surface color at `point`, taking the pattern into account.
+    pub fn color_at(&self, object: &dyn Shape, point: Tuple) -> Color {
+        self.pattern
+            .as_ref()
+            .map_or(self.color, |p| p.color_at_object(object, point))
+    }
+
+    /// The diffuse and specular light reflected towards `eyev` from light of
+    /// the given `intensity` arriving along `lightv`.
+    pub fn direct_light(
+        &self,
+        color: Color,
+        lightv: Tuple,
+        intensity: Color,
+        eyev: Tuple,
+        normalv: Tuple,
+    ) -> Color {
+        match self.brdf {
+            Brdf::Phong => self.phong(color, lightv, intensity, eyev, normalv),
+            Brdf::CookTorrance => self.cook_torrance(color, lightv, intensity, eyev, normalv),
         }
+    }
+
+    fn phong(
+        &self,
+        color: Color,
+        lightv: Tuple,
+        intensity: Color,
+        eyev: Tuple,
+        normalv: Tuple,
+    ) -> Color {
+        // combine the surface color with the light's color/intensity
+        let effective_color = color * intensity;
 
         // light_dot_normal represents the cosine of the angle between the
         // light vector and the normal vector. A negative number means the
@@ -51,28 +256,99 @@ impl Material {
         let light_dot_normal = lightv.dot(normalv);
 
         if light_dot_normal < 0.0 {
-            diffuse_light = BLACK;
-            specular_light = BLACK;
+            return BLACK;
+        }
+
+        // compute the diffuse contribution
+        let diffuse_light = effective_color * self.diffuse * light_dot_normal;
+
+        // reflect_dot_eye represents the cosine of the angle between the
+        // reflection vector and the eye vector. A negative number means the
+        // light reflects away from the eye.
+        let reflectv = -lightv.reflect(normalv);
+        let reflect_dot_eye = reflectv.dot(eyev);
+
+        let specular_light = if reflect_dot_eye <= 0.0 {
+            BLACK
         } else {
-            // compute the diffuse contribution
-            diffuse_light = effective_color * self.diffuse * light_dot_normal;
-
-            // reflect_dot_eye represents the cosine of the angle between the
-            // reflection vector and the eye vector. A negative number means the
-            // light reflects away from the eye.
-            let reflectv = -lightv.reflect(normalv);
-            let reflect_dot_eye = reflectv.dot(eyev);
-
-            if reflect_dot_eye <= 0.0 {
-                specular_light = BLACK;
-            } else {
-                // compute the specular contribution
-                let factor = reflect_dot_eye.powf(self.shininess);
-                specular_light = light.intensity * self.specular * factor;
+            // compute the specular contribution
+            let factor = reflect_dot_eye.powf(self.shininess);
+            intensity * self.specular * factor
+        };
+
+        diffuse_light + specular_light
+    }
+
+    fn cook_torrance(
+        &self,
+        color: Color,
+        lightv: Tuple,
+        intensity: Color,
+        eyev: Tuple,
+        normalv: Tuple,
+    ) -> Color {
+        let n_dot_l = normalv.dot(lightv);
+        let n_dot_v = normalv.dot(eyev);
+
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return BLACK;
+        }
+
+        let halfway = (lightv + eyev).normalize();
+        let n_dot_h = normalv.dot(halfway).max(0.0);
+        let v_dot_h = eyev.dot(halfway).max(0.0);
+
+        // GGX normal distribution
+        let alpha2 = self.ggx_alpha().powi(2);
+        let d = alpha2 / (PI * (n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0).powi(2));
+
+        let g = self.smith_geometry(n_dot_l, n_dot_v);
+        let fresnel = self.schlick_reflectance(color, v_dot_h);
+
+        let specular = fresnel * (d * g / (4.0 * n_dot_l * n_dot_v));
+        // metals have no diffuse reflection at all
+        let diffuse = (WHITE - fresnel) * (1.0 - self.metallic) * color;
+
+        // scaled by pi like the rest of the renderer, so that a rough
+        // dielectric is about as bright as a Phong material with a diffuse
+        // of 1
+        (diffuse + specular * PI) * intensity * n_dot_l
+    }
+
+    /// The share of `color` that is scattered diffusely, for light arriving
+    /// from all around rather than from a single direction.
+    pub fn diffuse_albedo(&self, color: Color, eyev: Tuple, normalv: Tuple) -> Color {
+        match self.brdf {
+            Brdf::Phong => color * self.diffuse,
+            Brdf::CookTorrance => {
+                let fresnel = self.schlick_reflectance(color, eyev.dot(normalv).max(0.0));
+
+                (WHITE - fresnel) * (1.0 - self.metallic) * color
             }
         }
+    }
 
-        ambient_light + diffuse_light + specular_light
+    // the GGX roughness, with a lower bound to keep perfectly smooth
+    // surfaces from dividing by zero
+    pub(crate) fn ggx_alpha(&self) -> F {
+        self.roughness.clamp(0.03, 1.0).powi(2)
+    }
+
+    // Smith geometry term with Schlick's approximation for direct light
+    pub(crate) fn smith_geometry(&self, n_dot_l: F, n_dot_v: F) -> F {
+        let k = (self.roughness + 1.0).powi(2) / 8.0;
+        let g1 = |cos: F| cos / (cos * (1.0 - k) + k);
+
+        g1(n_dot_l) * g1(n_dot_v)
+    }
+
+    // the share of light reflected off the surface rather than scattered
+    // into it, by Schlick's approximation
+    pub(crate) fn schlick_reflectance(&self, color: Color, cos: F) -> Color {
+        // dielectrics reflect 4% head-on, metals reflect their own color
+        let f0 = WHITE * 0.04 * (1.0 - self.metallic) + color * self.metallic;
+
+        f0 + (WHITE - f0) * (1.0 - cos).powi(5)
     }
 
     #[must_use]
@@ -124,6 +400,62 @@ impl Material {
         self
     }
 
+    #[must_use]
+    pub fn brdf(mut self, brdf: Brdf) -> Self {
+        self.brdf = brdf;
+
+        self
+    }
+
+    #[must_use]
+    pub fn metallic(mut self, metallic: impl Into<F>) -> Self {
+        self.metallic = metallic.into();
+
+        self
+    }
+
+    #[must_use]
+    pub fn roughness(mut self, roughness: impl Into<F>) -> Self {
+        self.roughness = roughness.into();
+
+        self
+    }
+
+    #[must_use]
+    pub fn perturbation(mut self, perturbation: Perturbation) -> Self {
+        self.perturbation = Some(perturbation);
+
+        self
+    }
+
+    #[must_use]
+    pub fn dispersion(mut self, dispersion: Dispersion) -> Self {
+        self.dispersion = Some(dispersion);
+
+        self
+    }
+
+    #[must_use]
+    pub fn absorption(mut self, absorption: Color) -> Self {
+        self.absorption = absorption;
+
+        self
+    }
+
+    #[must_use]
+    pub fn emissive(mut self, emissive: Color) -> Self {
+        self.emissive = emissive;
+
+        self
+    }
+
+    #[must_use]
+    pub fn tinted_shadow(mut self, tinted_shadow: bool) -> Self {
+        self.tinted_shadow = tinted_shadow;
+
+        self
+    }
+
     #[must_use]
     pub fn reflective(mut self, reflective: impl Into<F>) -> Self {
         self.reflective = reflective.into();
@@ -131,6 +463,13 @@ impl Material {
         self
     }
 
+    #[must_use]
+    pub fn fresnel(mut self, fresnel: bool) -> Self {
+        self.fresnel = fresnel;
+
+        self
+    }
+
     #[must_use]
     pub fn transparency(mut self, transparency: impl Into<F>) -> Self {
         self.transparency = transparency.into();
@@ -138,6 +477,20 @@ impl Material {
         self
     }
 
+    #[must_use]
+    pub fn blur(mut self, blur: impl Into<F>) -> Self {
+        self.blur = blur.into();
+
+        self
+    }
+
+    #[must_use]
+    pub fn blur_samples(mut self, blur_samples: usize) -> Self {
+        self.blur_samples = blur_samples;
+
+        self
+    }
+
     #[must_use]
     pub fn refractive_index(mut self, refractive_index: impl Into<F>) -> Self {
         self.refractive_index = refractive_index.into();
@@ -155,9 +508,20 @@ impl Default for Material {
             specular: 0.9,
             shininess: 200.0,
             reflective: 0.0,
+            fresnel: false,
             transparency: 0.0,
+            blur: 0.0,
+            blur_samples: 16,
             refractive_index: 1.0,
+            dispersion: None,
+            absorption: BLACK,
+            emissive: BLACK,
+            brdf: Brdf::Phong,
+            metallic: 0.0,
+            roughness: 0.5,
+            tinted_shadow: false,
             pattern: None,
+            perturbation: None,
         }
     }
 }
@@ -306,4 +670,263 @@ mod tests {
         assert_eq!(m.transparency, 0.0);
         assert_eq!(m.refractive_index, 1.0);
     }
+
+    #[test]
+    fn the_default_material_does_not_glow() {
+        let m = Material::default();
+
+        assert_eq!(m.emissive, BLACK);
+    }
+
+    #[test]
+    fn lighting_adds_the_emission() {
+        let m = Material::default().emissive(color(0.5, 0.25, 0));
+        let eyev = v(0, 0, -1);
+        let normalv = v(0, 0, -1);
+        let light = point_light(pt(0, 0, -10), color(1, 1, 1));
+        let object = Sphere::default();
+
+        let result = m.lighting(&object, light, pt(0, 0, 0), eyev, normalv, false);
+
+        assert_fuzzy_eq!(result, color(2.4, 2.15, 1.9));
+    }
+
+    #[test]
+    fn lighting_adds_the_emission_in_shadow() {
+        let m = Material::default().emissive(color(0.5, 0.25, 0));
+        let eyev = v(0, 0, -1);
+        let normalv = v(0, 0, -1);
+        let light = point_light(pt(0, 0, -10), color(1, 1, 1));
+        let object = Sphere::default();
+
+        let result = m.lighting(&object, light, pt(0, 0, 0), eyev, normalv, true);
+
+        assert_fuzzy_eq!(result, color(0.6, 0.35, 0.1));
+    }
+
+    #[test]
+    fn lighting_with_the_light_partially_blocked() {
+        let m = Material::default();
+        let eyev = v(0, 0, -1);
+        let normalv = v(0, 0, -1);
+        let light = point_light(pt(0, 0, -10), color(1, 1, 1));
+        let object = Sphere::default();
+        let visibility = color(0.5, 0.5, 0);
+
+        let result = m.illuminate(&object, light, pt(0, 0, 0), eyev, normalv, visibility);
+
+        assert_fuzzy_eq!(result, color(1, 1, 0.1));
+    }
+
+    #[test]
+    fn a_flat_bump_leaves_the_normal_alone() {
+        let bump = Perturbation::bump(stripe(WHITE, WHITE), 1);
+        let object = Plane::default();
+
+        let n = bump.perturb(&object, pt(0.5, 0, 0.5), v(0, 1, 0));
+
+        assert_fuzzy_eq!(n, v(0, 1, 0));
+    }
+
+    #[test]
+    fn a_bump_tilts_the_normal_away_from_higher_ground() {
+        // the height rises by one unit for every unit along x
+        let bump = Perturbation::bump(gradient(BLACK, WHITE), 1);
+        let object = Plane::default();
+
+        let n = bump.perturb(&object, pt(0.5, 0, 0.5), v(0, 1, 0));
+
+        assert_fuzzy_eq!(n, v(-F::sqrt(2.0) / 2.0, F::sqrt(2.0) / 2.0, 0));
+    }
+
+    #[test]
+    fn the_scale_of_a_bump_exaggerates_the_slope() {
+        let object = Plane::default();
+        let gentle = Perturbation::bump(gradient(BLACK, WHITE), 0.5);
+        let steep = Perturbation::bump(gradient(BLACK, WHITE), 2);
+
+        let n1 = gentle.perturb(&object, pt(0.5, 0, 0), v(0, 1, 0));
+        let n2 = steep.perturb(&object, pt(0.5, 0, 0), v(0, 1, 0));
+
+        assert_fuzzy_eq!(n1, v(-0.5, 1, 0).normalize());
+        assert_fuzzy_eq!(n2, v(-2, 1, 0).normalize());
+    }
+
+    #[test]
+    fn a_flat_normal_map_leaves_the_normal_alone() {
+        let map = UvPattern::image(Canvas::new_with_color(2, 2, color(0.5, 0.5, 1)));
+        let normal_map = Perturbation::normal_map(map, UvMapping::Planar);
+        let object = Plane::default();
+
+        let n = normal_map.perturb(&object, pt(0.3, 0, 0.7), v(0, 1, 0));
+
+        assert_fuzzy_eq!(n, v(0, 1, 0));
+    }
+
+    #[test]
+    fn a_normal_map_is_read_in_tangent_space() {
+        let object = Plane::default();
+
+        for (c, expected) in [
+            (color(1, 0.5, 0.5), v(1, 0, 0)),
+            (color(0, 0.5, 0.5), v(-1, 0, 0)),
+            (color(0.5, 1, 0.5), v(0, 0, -1)),
+            (color(0.5, 0.5, 0), v(0, -1, 0)),
+        ] {
+            let map = UvPattern::image(Canvas::new_with_color(2, 2, c));
+            let normal_map = Perturbation::normal_map(map, UvMapping::Planar);
+
+            let n = normal_map.perturb(&object, pt(0.3, 0, 0.7), v(0, 1, 0));
+
+            assert_fuzzy_eq!(n, expected);
+        }
+    }
+
+    #[test]
+    fn a_normal_map_follows_the_object_transformation() {
+        let object = Plane::default().transform(Matrix::rotation_z(PI / 2.0));
+        let map = UvPattern::image(Canvas::new_with_color(2, 2, color(1, 0.5, 0.5)));
+        let normal_map = Perturbation::normal_map(map, UvMapping::Planar);
+
+        let n = normal_map.perturb(&object, pt(0, 0.3, 0.7), v(-1, 0, 0));
+
+        assert_fuzzy_eq!(n, v(0, 1, 0));
+    }
+
+    #[test]
+    fn the_default_material_uses_phong() {
+        let m = Material::default();
+
+        assert_eq!(m.brdf, Brdf::Phong);
+        assert_eq!(m.metallic, 0.0);
+        assert_eq!(m.roughness, 0.5);
+    }
+
+    #[test]
+    fn cook_torrance_lighting_of_a_dielectric_head_on() {
+        let m = Material::default().brdf(Brdf::CookTorrance);
+        let eyev = v(0, 0, -1);
+        let normalv = v(0, 0, -1);
+        let light = point_light(pt(0, 0, -10), color(1, 1, 1));
+        let object = Sphere::default();
+
+        let result = m.lighting(&object, light, pt(0, 0, 0), eyev, normalv, false);
+
+        // ambient + diffuse of the 96% that isn't reflected + 4% specular
+        // peak of 1 / (pi * alpha^2) / 4
+        assert_fuzzy_eq!(result, color(1.22, 1.22, 1.22));
+    }
+
+    #[test]
+    fn cook_torrance_lighting_of_a_metal_head_on() {
+        let m = Material::default()
+            .rgb(1, 0.5, 0)
+            .brdf(Brdf::CookTorrance)
+            .metallic(1);
+        let eyev = v(0, 0, -1);
+        let normalv = v(0, 0, -1);
+        let light = point_light(pt(0, 0, -10), color(1, 1, 1));
+        let object = Sphere::default();
+
+        let result = m.lighting(&object, light, pt(0, 0, 0), eyev, normalv, false);
+
+        // metals tint their reflections and have no diffuse term
+        assert_fuzzy_eq!(result, color(4.1, 2.05, 0));
+    }
+
+    #[test]
+    fn cook_torrance_lighting_with_the_light_behind_the_surface() {
+        let m = Material::default().brdf(Brdf::CookTorrance);
+        let eyev = v(0, 0, -1);
+        let normalv = v(0, 0, -1);
+        let light = point_light(pt(0, 0, 10), color(1, 1, 1));
+        let object = Sphere::default();
+
+        let result = m.lighting(&object, light, pt(0, 0, 0), eyev, normalv, false);
+
+        assert_fuzzy_eq!(result, color(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn cook_torrance_lighting_in_shadow() {
+        let m = Material::default().brdf(Brdf::CookTorrance);
+        let eyev = v(0, 0, -1);
+        let normalv = v(0, 0, -1);
+        let light = point_light(pt(0, 0, -10), color(1, 1, 1));
+        let object = Sphere::default();
+
+        let result = m.lighting(&object, light, pt(0, 0, 0), eyev, normalv, true);
+
+        assert_fuzzy_eq!(result, color(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn smoother_surfaces_have_tighter_highlights() {
+        let eyev = v(0, 0, -1);
+        let normalv = v(0, 0, -1);
+        let object = Sphere::default();
+        let smooth = Material::default()
+            .brdf(Brdf::CookTorrance)
+            .metallic(1)
+            .roughness(0.2);
+        let rough = smooth.clone().roughness(0.8);
+
+        let head_on = point_light(pt(0, 0, -10), WHITE);
+        let oblique = point_light(pt(0, 10, -10), WHITE);
+
+        let lit =
+            |m: &Material, light| m.lighting(&object, light, pt(0, 0, 0), eyev, normalv, false);
+
+        assert!(lit(&smooth, head_on).red > lit(&rough, head_on).red);
+        assert!(lit(&smooth, oblique).red < lit(&rough, oblique).red);
+    }
+
+    #[test]
+    fn the_default_material_absorbs_nothing() {
+        let m = Material::default();
+
+        assert_eq!(m.absorption, BLACK);
+        assert_fuzzy_eq!(m.transmission(10.0), WHITE);
+        assert_fuzzy_eq!(m.transmission(INFINITY), WHITE);
+    }
+
+    #[test]
+    fn transmission_falls_off_exponentially_with_distance() {
+        let m = Material::default().absorption(color(0, 0.5, 1));
+
+        assert_fuzzy_eq!(m.transmission(0.0), WHITE);
+        assert_fuzzy_eq!(m.transmission(1.0), color(1, 0.60653, 0.36788));
+        assert_fuzzy_eq!(m.transmission(2.0), color(1, 0.36788, 0.13534));
+        assert_fuzzy_eq!(m.transmission(INFINITY), color(1, 0, 0));
+    }
+
+    #[test]
+    fn without_dispersion_the_index_is_the_same_for_every_wavelength() {
+        let m = Material::default().refractive_index(1.5);
+
+        for wavelength in WAVELENGTHS {
+            assert_eq!(m.refractive_index_at(wavelength), 1.5);
+        }
+    }
+
+    #[test]
+    fn dispersion_from_an_abbe_number() {
+        let m = Material::default()
+            .refractive_index(1.5)
+            .dispersion(Dispersion::Abbe(50.0));
+
+        let [red, green, blue] = WAVELENGTHS.map(|w| m.refractive_index_at(w));
+
+        assert_fuzzy_eq!(green, 1.5);
+        assert!(red < green && green < blue);
+        assert_fuzzy_eq!((green - 1.0) / (blue - red), 50.0);
+    }
+
+    #[test]
+    fn dispersion_from_cauchy_coefficients() {
+        let m = Material::default().dispersion(Dispersion::Cauchy { a: 1.5, b: 0.004 });
+
+        assert_fuzzy_eq!(m.refractive_index_at(0.5), 1.516);
+        assert_fuzzy_eq!(m.refractive_index_at(1.0), 1.504);
+    }
 }
@@ -118,7 +118,7 @@ impl Matrix<3> {
     pub fn cofactor(&self, row: usize, col: usize) -> F {
         let minor = self.minor(row, col);
 
-        if (row + col) % 2 == 0 {
+        if (row + col).is_multiple_of(2) {
             minor
         } else {
             -minor
@@ -260,7 +260,7 @@ impl Matrix<4> {
     pub fn cofactor(&self, row: usize, col: usize) -> F {
         let minor = self.minor(row, col);
 
-        if (row + col) % 2 == 0 {
+        if (row + col).is_multiple_of(2) {
             minor
         } else {
             -minor
@@ -1,15 +1,422 @@
-use crate::{color, Color, Matrix, Shape, Tuple};
+use crate::{color, pt, v, Canvas, Color, Matrix, Rng, Shape, Tuple, F, PI};
+use std::fmt;
+use std::sync::Arc;
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum PatternDesign {
-    Stripe(Color, Color),
-    Gradient(Color, Color),
-    Ring(Color, Color),
-    Checkers(Color, Color),
+    Stripe(Paint, Paint),
+    Gradient(Paint, Paint),
+    Ring(Paint, Paint),
+    Checkers(Paint, Paint),
+    /// Fades from `a` at the origin to `b` at a distance of one unit and
+    /// beyond.
+    RadialGradient(Paint, Paint),
+    /// Fades from `a` to `b` outwards from the y axis, repeating every unit.
+    RingGradient(Paint, Paint),
+    /// Dots of `b` with the given radius on `a`, centred on every integer
+    /// coordinate.
+    Dots(Paint, Paint, F),
+    /// Tiles the xz plane with hexagons of unit radius in three colors.
+    Hexagon(Paint, Paint, Paint),
+    TextureMap(UvPattern, UvMapping),
+    /// A different texture for every face of a cube, in the order left,
+    /// front, right, back, up and down.
+    CubeMap(Box<[UvPattern; 6]>),
+    /// Veins of `b` running through `a` along the x axis.
+    Marble(Paint, Paint, Noise),
+    /// Irregular rings of `a` and `b` around the y axis.
+    Wood(Paint, Paint, Noise),
+    /// Blends from `a` to `b` with the turbulence of the noise.
+    Turbulence(Paint, Paint, Noise),
+    /// Jitters the point by up to `scale` before handing it to another
+    /// pattern.
+    Perturbed(Box<Pattern>, F, Noise),
+    /// Mixes `a` and `b`, `weight` being the share of `b`.
+    Blend(Paint, Paint, F),
+    /// Picks between `a` and `b` by the brightness of the mask, showing `a`
+    /// where the mask is black and `b` where it's white.
+    Mask(Paint, Paint, Paint),
     Test,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+/// What fills the slots of a pattern: either a plain color or another
+/// pattern, which is evaluated with its own transformation relative to the
+/// pattern containing it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Paint {
+    Solid(Color),
+    Pattern(Box<Pattern>),
+}
+
+impl Paint {
+    pub fn color_at(&self, point: Tuple) -> Color {
+        match self {
+            Self::Solid(color) => *color,
+            Self::Pattern(pattern) => pattern.color_at(pattern.transform.inverse() * point),
+        }
+    }
+}
+
+impl From<Color> for Paint {
+    fn from(color: Color) -> Self {
+        Self::Solid(color)
+    }
+}
+
+impl From<Pattern> for Paint {
+    fn from(pattern: Pattern) -> Self {
+        Self::Pattern(Box::new(pattern))
+    }
+}
+
+/// Seedable Perlin gradient noise. The same seed always produces the same
+/// noise, so renders are reproducible.
+#[derive(Clone, PartialEq)]
+pub struct Noise {
+    seed: u64,
+    octaves: u8,
+    // the shuffled permutation, repeated once to avoid wrapping indices
+    permutation: Box<[u8; 512]>,
+}
+
+impl Noise {
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut p: [u8; 256] = std::array::from_fn(|i| i as u8);
+
+        // Fisher-Yates shuffle
+        for i in (1..256).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            p.swap(i, j);
+        }
+
+        Self {
+            seed,
+            octaves: 4,
+            permutation: Box::new(std::array::from_fn(|i| p[i % 256])),
+        }
+    }
+
+    /// The number of layers of noise summed up by `Noise::fbm` and
+    /// `Noise::turbulence`, each with double the frequency and half the
+    /// amplitude of the last.
+    #[must_use]
+    pub fn octaves(mut self, octaves: u8) -> Self {
+        self.octaves = octaves.max(1);
+
+        self
+    }
+
+    /// Smooth noise between -1 and 1, zero at every integer lattice point.
+    pub fn noise(&self, point: Tuple) -> F {
+        let p = &self.permutation;
+
+        let (xf, yf, zf) = (point.x.floor(), point.y.floor(), point.z.floor());
+        let (x, y, z) = (point.x - xf, point.y - yf, point.z - zf);
+
+        // the unit cube that contains the point
+        let xi = (xf as i64 & 255) as usize;
+        let yi = (yf as i64 & 255) as usize;
+        let zi = (zf as i64 & 255) as usize;
+
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        // hash the coordinates of the 8 cube corners
+        let a = p[xi] as usize + yi;
+        let aa = p[a] as usize + zi;
+        let ab = p[a + 1] as usize + zi;
+        let b = p[xi + 1] as usize + yi;
+        let ba = p[b] as usize + zi;
+        let bb = p[b + 1] as usize + zi;
+
+        // and blend the gradients of all of them
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, grad(p[aa], x, y, z), grad(p[ba], x - 1.0, y, z)),
+                lerp(
+                    u,
+                    grad(p[ab], x, y - 1.0, z),
+                    grad(p[bb], x - 1.0, y - 1.0, z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(p[aa + 1], x, y, z - 1.0),
+                    grad(p[ba + 1], x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    grad(p[ab + 1], x, y - 1.0, z - 1.0),
+                    grad(p[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+
+    /// Fractal Brownian motion: the sum of every octave of the noise,
+    /// normalized to stay between -1 and 1.
+    pub fn fbm(&self, point: Tuple) -> F {
+        self.octave_sum(point, |n| n)
+    }
+
+    /// Like `Noise::fbm` but summing the absolute value of every octave,
+    /// which gives a billowy look between 0 and 1.
+    pub fn turbulence(&self, point: Tuple) -> F {
+        self.octave_sum(point, F::abs)
+    }
+
+    fn octave_sum(&self, point: Tuple, f: impl Fn(F) -> F) -> F {
+        let mut sum = 0.0;
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+
+        for _ in 0..self.octaves {
+            let p = pt(
+                point.x * frequency,
+                point.y * frequency,
+                point.z * frequency,
+            );
+            sum += f(self.noise(p)) * amplitude;
+            total += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+
+        sum / total
+    }
+}
+
+impl fmt::Debug for Noise {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Noise")
+            .field("seed", &self.seed)
+            .field("octaves", &self.octaves)
+            .finish_non_exhaustive()
+    }
+}
+
+fn fade(t: F) -> F {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: F, a: F, b: F) -> F {
+    a + t * (b - a)
+}
+
+fn grad(hash: u8, x: F, y: F, z: F) -> F {
+    // pick one of 12 gradient directions from the low 4 bits of the hash
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// Which of three colors the hexagon containing `(x, z)` gets, such that no
+/// two neighbouring hexagons share a color.
+fn hexagon_at(x: F, z: F) -> usize {
+    // axial coordinates of pointy-topped hexagons with a radius of one
+    let q = F::sqrt(3.0) / 3.0 * x - z / 3.0;
+    let r = 2.0 / 3.0 * z;
+
+    // round to the nearest hexagon in cube coordinates, fixing up whichever
+    // coordinate was rounded the most so that they still add up to zero
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), (-q - r).round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs + q + r).abs());
+
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+
+    (rq - rr).rem_euclid(3.0) as usize
+}
+
+fn mix(a: Color, b: Color, t: F) -> Color {
+    a + (b - a) * t
+}
+
+/// A two dimensional pattern, addressed with `u` and `v` coordinates that
+/// run from 0 to 1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UvPattern {
+    Image(Arc<Canvas>),
+    /// A checkerboard with `width` squares along `u` and `height` along `v`.
+    Checkers {
+        width: usize,
+        height: usize,
+        a: Color,
+        b: Color,
+    },
+}
+
+impl UvPattern {
+    #[must_use]
+    pub fn checkers(width: usize, height: usize, a: Color, b: Color) -> Self {
+        Self::Checkers {
+            width,
+            height,
+            a,
+            b,
+        }
+    }
+
+    #[must_use]
+    pub fn image(canvas: Canvas) -> Self {
+        Self::Image(Arc::new(canvas))
+    }
+
+    pub fn color_at(&self, u: F, v: F) -> Color {
+        match self {
+            Self::Image(canvas) => canvas.color_at_uv(u, v),
+            Self::Checkers {
+                width,
+                height,
+                a,
+                b,
+            } => {
+                let u2 = (u * *width as F).floor() as isize;
+                let v2 = (v * *height as F).floor() as isize;
+
+                if (u2 + v2) % 2 == 0 {
+                    *a
+                } else {
+                    *b
+                }
+            }
+        }
+    }
+}
+
+/// Ways of wrapping a two dimensional pattern around a three dimensional
+/// object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UvMapping {
+    /// Wraps around a unit sphere, with `v` going from the south pole to the
+    /// north pole.
+    Spherical,
+    /// Tiles the xz plane, repeating every unit.
+    Planar,
+    /// Wraps around the y axis, repeating every unit along it.
+    Cylindrical,
+    /// Repeats the pattern on every face of a cube from -1 to 1.
+    Cube,
+}
+
+impl UvMapping {
+    #[must_use]
+    pub fn map(self, point: Tuple) -> (F, F) {
+        match self {
+            Self::Spherical => spherical_map(point),
+            Self::Planar => planar_map(point),
+            Self::Cylindrical => cylindrical_map(point),
+            Self::Cube => {
+                let face = CubeFace::from_point(point);
+                face.map(point)
+            }
+        }
+    }
+}
+
+fn spherical_map(point: Tuple) -> (F, F) {
+    // compute the azimuthal angle, -π < theta <= π
+    // angle increases clockwise as viewed from above,
+    // which is opposite of what we want, but we'll fix it later.
+    let theta = point.x.atan2(point.z);
+
+    // compute the polar angle, 0 <= phi <= π
+    let radius = (point.x.powi(2) + point.y.powi(2) + point.z.powi(2)).sqrt();
+    let phi = (point.y / radius).acos();
+
+    // -0.5 < raw_u <= 0.5
+    let raw_u = theta / (2.0 * PI);
+
+    // 0 <= u < 1
+    // here's also where we fix the direction of u. Subtract it from 1,
+    // so that it increases counterclockwise as viewed from above.
+    let u = 1.0 - (raw_u + 0.5);
+
+    // we want v to be 0 at the south pole of the sphere,
+    // and 1 at the north pole, so we have to "flip it over"
+    // by subtracting it from 1.
+    let v = 1.0 - phi / PI;
+
+    (u, v)
+}
+
+fn planar_map(point: Tuple) -> (F, F) {
+    (point.x.rem_euclid(1.0), point.z.rem_euclid(1.0))
+}
+
+fn cylindrical_map(point: Tuple) -> (F, F) {
+    // compute the azimuthal angle, same as with spherical_map()
+    let theta = point.x.atan2(point.z);
+    let raw_u = theta / (2.0 * PI);
+    let u = 1.0 - (raw_u + 0.5);
+
+    // let v go from 0 to 1 between whole units of y
+    let v = point.y.rem_euclid(1.0);
+
+    (u, v)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CubeFace {
+    Left,
+    Front,
+    Right,
+    Back,
+    Up,
+    Down,
+}
+
+impl CubeFace {
+    fn from_point(Tuple { x, y, z, .. }: Tuple) -> Self {
+        let coord = x.abs().max(y.abs()).max(z.abs());
+
+        if coord == x {
+            Self::Right
+        } else if coord == -x {
+            Self::Left
+        } else if coord == y {
+            Self::Up
+        } else if coord == -y {
+            Self::Down
+        } else if coord == z {
+            Self::Front
+        } else {
+            Self::Back
+        }
+    }
+
+    fn map(self, Tuple { x, y, z, .. }: Tuple) -> (F, F) {
+        let (u, v) = match self {
+            Self::Front => (x + 1.0, y + 1.0),
+            Self::Back => (1.0 - x, y + 1.0),
+            Self::Left => (z + 1.0, y + 1.0),
+            Self::Right => (1.0 - z, y + 1.0),
+            Self::Up => (x + 1.0, 1.0 - z),
+            Self::Down => (x + 1.0, z + 1.0),
+        };
+
+        ((u / 2.0).rem_euclid(1.0), (v / 2.0).rem_euclid(1.0))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Pattern {
     design: PatternDesign,
     transform: Matrix<4>,
@@ -19,36 +426,98 @@ impl Pattern {
     pub fn color_at(&self, point: Tuple) -> Color {
         use PatternDesign::*;
 
-        match self.design {
+        match &self.design {
             Stripe(a, b) => {
                 if point.x.floor() as isize % 2 == 0 {
-                    a
+                    a.color_at(point)
                 } else {
-                    b
+                    b.color_at(point)
                 }
             }
             Gradient(a, b) => {
-                let distance = b - a;
                 let fraction = point.x - point.x.floor();
 
-                a + distance * fraction
+                mix(a.color_at(point), b.color_at(point), fraction)
             }
             Ring(a, b) => {
                 let x2 = point.x * point.x;
                 let z2 = point.z * point.z;
                 if (x2 + z2).sqrt() as isize % 2 == 0 {
-                    a
+                    a.color_at(point)
                 } else {
-                    b
+                    b.color_at(point)
                 }
             }
             PatternDesign::Checkers(a, b) => {
                 if (point.x.floor() + point.y.floor() + point.z.floor()) as isize % 2 == 0 {
-                    a
+                    a.color_at(point)
                 } else {
-                    b
+                    b.color_at(point)
                 }
             }
+            RadialGradient(a, b) => {
+                let distance = (point.x.powi(2) + point.y.powi(2) + point.z.powi(2)).sqrt();
+                let fraction = distance.min(1.0);
+
+                mix(a.color_at(point), b.color_at(point), fraction)
+            }
+            RingGradient(a, b) => {
+                let distance = (point.x * point.x + point.z * point.z).sqrt();
+
+                mix(a.color_at(point), b.color_at(point), distance.fract())
+            }
+            Dots(a, b, radius) => {
+                let nearest = pt(point.x.round(), point.y.round(), point.z.round());
+
+                if (point - nearest).magnitude() < *radius {
+                    b.color_at(point)
+                } else {
+                    a.color_at(point)
+                }
+            }
+            Hexagon(a, b, c) => match hexagon_at(point.x, point.z) {
+                0 => a.color_at(point),
+                1 => b.color_at(point),
+                _ => c.color_at(point),
+            },
+            TextureMap(pattern, mapping) => {
+                let (u, v) = mapping.map(point);
+                pattern.color_at(u, v)
+            }
+            CubeMap(faces) => {
+                let face = CubeFace::from_point(point);
+                let (u, v) = face.map(point);
+                faces[face as usize].color_at(u, v)
+            }
+            Marble(a, b, noise) => {
+                let t = point.x + 5.0 * noise.turbulence(point);
+                mix(a.color_at(point), b.color_at(point), 0.5 * (1.0 + t.sin()))
+            }
+            Wood(a, b, noise) => {
+                let distance = (point.x * point.x + point.z * point.z).sqrt();
+                let t = distance + 0.5 * noise.fbm(point);
+                mix(a.color_at(point), b.color_at(point), t.rem_euclid(1.0))
+            }
+            Turbulence(a, b, noise) => {
+                let t = noise.turbulence(point).min(1.0);
+                mix(a.color_at(point), b.color_at(point), t)
+            }
+            Perturbed(pattern, scale, noise) => {
+                // offset the lookups so the three axes get unrelated noise
+                let jitter = v(
+                    noise.fbm(point),
+                    noise.fbm(point + v(31.4, 0, 0)),
+                    noise.fbm(point + v(0, 0, 27.2)),
+                );
+                let jittered = point + jitter * *scale;
+
+                pattern.color_at(pattern.transform.inverse() * jittered)
+            }
+            Blend(a, b, weight) => mix(a.color_at(point), b.color_at(point), *weight),
+            Mask(mask, a, b) => {
+                let t = mask.color_at(point).luminance().clamp(0.0, 1.0);
+                mix(a.color_at(point), b.color_at(point), t)
+            }
             Test => color(point.x, point.y, point.z),
         }
     }
@@ -69,33 +538,140 @@ impl Pattern {
 }
 
 #[must_use]
-pub fn stripe(a: Color, b: Color) -> Pattern {
+pub fn stripe(a: impl Into<Paint>, b: impl Into<Paint>) -> Pattern {
+    Pattern {
+        design: PatternDesign::Stripe(a.into(), b.into()),
+        transform: Matrix::identity(),
+    }
+}
+
+#[must_use]
+pub fn gradient(a: impl Into<Paint>, b: impl Into<Paint>) -> Pattern {
+    Pattern {
+        design: PatternDesign::Gradient(a.into(), b.into()),
+        transform: Matrix::identity(),
+    }
+}
+
+#[must_use]
+pub fn ring(a: impl Into<Paint>, b: impl Into<Paint>) -> Pattern {
+    Pattern {
+        design: PatternDesign::Ring(a.into(), b.into()),
+        transform: Matrix::identity(),
+    }
+}
+
+#[must_use]
+pub fn checkers(a: impl Into<Paint>, b: impl Into<Paint>) -> Pattern {
+    Pattern {
+        design: PatternDesign::Checkers(a.into(), b.into()),
+        transform: Matrix::identity(),
+    }
+}
+
+#[must_use]
+pub fn radial_gradient(a: impl Into<Paint>, b: impl Into<Paint>) -> Pattern {
+    Pattern {
+        design: PatternDesign::RadialGradient(a.into(), b.into()),
+        transform: Matrix::identity(),
+    }
+}
+
+#[must_use]
+pub fn ring_gradient(a: impl Into<Paint>, b: impl Into<Paint>) -> Pattern {
+    Pattern {
+        design: PatternDesign::RingGradient(a.into(), b.into()),
+        transform: Matrix::identity(),
+    }
+}
+
+#[must_use]
+pub fn dots(a: impl Into<Paint>, b: impl Into<Paint>, radius: impl Into<F>) -> Pattern {
+    Pattern {
+        design: PatternDesign::Dots(a.into(), b.into(), radius.into()),
+        transform: Matrix::identity(),
+    }
+}
+
+#[must_use]
+pub fn hexagon(a: impl Into<Paint>, b: impl Into<Paint>, c: impl Into<Paint>) -> Pattern {
+    Pattern {
+        design: PatternDesign::Hexagon(a.into(), b.into(), c.into()),
+        transform: Matrix::identity(),
+    }
+}
+
+#[must_use]
+pub fn texture_map(pattern: UvPattern, mapping: UvMapping) -> Pattern {
+    Pattern {
+        design: PatternDesign::TextureMap(pattern, mapping),
+        transform: Matrix::identity(),
+    }
+}
+
+#[must_use]
+pub fn cube_map(faces: [UvPattern; 6]) -> Pattern {
+    Pattern {
+        design: PatternDesign::CubeMap(Box::new(faces)),
+        transform: Matrix::identity(),
+    }
+}
+
+#[must_use]
+pub fn marble(a: impl Into<Paint>, b: impl Into<Paint>, noise: Noise) -> Pattern {
+    Pattern {
+        design: PatternDesign::Marble(a.into(), b.into(), noise),
+        transform: Matrix::identity(),
+    }
+}
+
+#[must_use]
+pub fn wood(a: impl Into<Paint>, b: impl Into<Paint>, noise: Noise) -> Pattern {
+    Pattern {
+        design: PatternDesign::Wood(a.into(), b.into(), noise),
+        transform: Matrix::identity(),
+    }
+}
+
+#[must_use]
+pub fn turbulence(a: impl Into<Paint>, b: impl Into<Paint>, noise: Noise) -> Pattern {
     Pattern {
-        design: PatternDesign::Stripe(a, b),
+        design: PatternDesign::Turbulence(a.into(), b.into(), noise),
         transform: Matrix::identity(),
     }
 }
 
+/// Wraps `pattern` so that it's looked up at a point jittered by `noise`,
+/// the jitter being at most `scale` along every axis.
 #[must_use]
-pub fn gradient(a: Color, b: Color) -> Pattern {
+pub fn perturb(pattern: Pattern, scale: impl Into<F>, noise: Noise) -> Pattern {
     Pattern {
-        design: PatternDesign::Gradient(a, b),
+        design: PatternDesign::Perturbed(Box::new(pattern), scale.into(), noise),
         transform: Matrix::identity(),
     }
 }
 
+/// Mixes two colors or patterns, `weight` being the share of `b`.
 #[must_use]
-pub fn ring(a: Color, b: Color) -> Pattern {
+pub fn blend(a: impl Into<Paint>, b: impl Into<Paint>, weight: impl Into<F>) -> Pattern {
     Pattern {
-        design: PatternDesign::Ring(a, b),
+        design: PatternDesign::Blend(a.into(), b.into(), weight.into()),
         transform: Matrix::identity(),
     }
 }
 
+/// Mixes two colors or patterns evenly.
+#[must_use]
+pub fn average(a: impl Into<Paint>, b: impl Into<Paint>) -> Pattern {
+    blend(a, b, 0.5)
+}
+
+/// Shows `a` where `mask` is black and `b` where it's white, blending the
+/// two in between.
 #[must_use]
-pub fn checkers(a: Color, b: Color) -> Pattern {
+pub fn mask(mask: impl Into<Paint>, a: impl Into<Paint>, b: impl Into<Paint>) -> Pattern {
     Pattern {
-        design: PatternDesign::Checkers(a, b),
+        design: PatternDesign::Mask(mask.into(), a.into(), b.into()),
         transform: Matrix::identity(),
     }
 }
@@ -116,14 +692,14 @@ mod tests {
 
     #[test]
     fn creating_a_stripe_pattern() {
-        let pattern = PatternDesign::Stripe(WHITE, BLACK);
+        let pattern = PatternDesign::Stripe(WHITE.into(), BLACK.into());
 
         let PatternDesign::Stripe(a, b) = pattern else {
             unreachable!()
         };
 
-        assert_fuzzy_eq!(a, WHITE);
-        assert_fuzzy_eq!(b, BLACK);
+        assert_eq!(a, Paint::Solid(WHITE));
+        assert_eq!(b, Paint::Solid(BLACK));
     }
 
     #[test]
@@ -268,4 +844,472 @@ mod tests {
         assert_fuzzy_eq!(pattern.color_at(pt(0.0, 0.0, 0.99)), WHITE);
         assert_fuzzy_eq!(pattern.color_at(pt(0.0, 0.0, 1.01)), BLACK);
     }
+
+    #[test]
+    fn using_a_spherical_mapping_on_a_3d_point() {
+        for (point, u, v) in [
+            (pt(0, 0, -1), 0.0, 0.5),
+            (pt(1, 0, 0), 0.25, 0.5),
+            (pt(0, 0, 1), 0.5, 0.5),
+            (pt(-1, 0, 0), 0.75, 0.5),
+            (pt(0, 1, 0), 0.5, 1.0),
+            (pt(0, -1, 0), 0.5, 0.0),
+            (pt(F::sqrt(2.0) / 2.0, F::sqrt(2.0) / 2.0, 0), 0.25, 0.75),
+        ] {
+            let (mu, mv) = UvMapping::Spherical.map(point);
+
+            assert_fuzzy_eq!(mu, u);
+            assert_fuzzy_eq!(mv, v);
+        }
+    }
+
+    #[test]
+    fn using_a_planar_mapping_on_a_3d_point() {
+        for (point, u, v) in [
+            (pt(0.25, 0, 0.5), 0.25, 0.5),
+            (pt(0.25, 0, -0.25), 0.25, 0.75),
+            (pt(0.25, 0.5, -0.25), 0.25, 0.75),
+            (pt(1.25, 0, 0.5), 0.25, 0.5),
+            (pt(0.25, 0, -1.75), 0.25, 0.25),
+            (pt(1, 0, -1), 0.0, 0.0),
+            (pt(0, 0, 0), 0.0, 0.0),
+        ] {
+            let (mu, mv) = UvMapping::Planar.map(point);
+
+            assert_fuzzy_eq!(mu, u);
+            assert_fuzzy_eq!(mv, v);
+        }
+    }
+
+    #[test]
+    fn using_a_cylindrical_mapping_on_a_3d_point() {
+        let h = F::sqrt(2.0) / 2.0;
+
+        for (point, u, v) in [
+            (pt(0, 0, -1), 0.0, 0.0),
+            (pt(0, 0.5, -1), 0.0, 0.5),
+            (pt(0, 1, -1), 0.0, 0.0),
+            (pt(h, 0.5, -h), 0.125, 0.5),
+            (pt(1, 0.5, 0), 0.25, 0.5),
+            (pt(h, 0.5, h), 0.375, 0.5),
+            (pt(0, -0.25, 1), 0.5, 0.75),
+            (pt(-h, 0.5, h), 0.625, 0.5),
+            (pt(-1, 1.25, 0), 0.75, 0.25),
+            (pt(-h, 0.5, -h), 0.875, 0.5),
+        ] {
+            let (mu, mv) = UvMapping::Cylindrical.map(point);
+
+            assert_fuzzy_eq!(mu, u);
+            assert_fuzzy_eq!(mv, v);
+        }
+    }
+
+    #[test]
+    fn identifying_the_face_of_a_cube_from_a_point() {
+        for (point, face) in [
+            (pt(-1, 0.5, -0.25), CubeFace::Left),
+            (pt(1.1, -0.75, 0.8), CubeFace::Right),
+            (pt(0.1, 0.6, 0.9), CubeFace::Front),
+            (pt(-0.7, 0, -2), CubeFace::Back),
+            (pt(0.5, 1, 0.9), CubeFace::Up),
+            (pt(-0.2, -1.3, 1.1), CubeFace::Down),
+        ] {
+            assert_eq!(CubeFace::from_point(point), face);
+        }
+    }
+
+    #[test]
+    fn uv_mapping_the_faces_of_a_cube() {
+        for (face, point, u, v) in [
+            (CubeFace::Front, pt(-0.5, 0.5, 1), 0.25, 0.75),
+            (CubeFace::Front, pt(0.5, -0.5, 1), 0.75, 0.25),
+            (CubeFace::Back, pt(0.5, 0.5, -1), 0.25, 0.75),
+            (CubeFace::Back, pt(-0.5, -0.5, -1), 0.75, 0.25),
+            (CubeFace::Left, pt(-1, 0.5, -0.5), 0.25, 0.75),
+            (CubeFace::Left, pt(-1, -0.5, 0.5), 0.75, 0.25),
+            (CubeFace::Right, pt(1, 0.5, 0.5), 0.25, 0.75),
+            (CubeFace::Right, pt(1, -0.5, -0.5), 0.75, 0.25),
+            (CubeFace::Up, pt(-0.5, 1, -0.5), 0.25, 0.75),
+            (CubeFace::Up, pt(0.5, 1, 0.5), 0.75, 0.25),
+            (CubeFace::Down, pt(-0.5, -1, 0.5), 0.25, 0.75),
+            (CubeFace::Down, pt(0.5, -1, -0.5), 0.75, 0.25),
+        ] {
+            let (mu, mv) = face.map(point);
+
+            assert_fuzzy_eq!(mu, u);
+            assert_fuzzy_eq!(mv, v);
+        }
+    }
+
+    fn gradient_image() -> Canvas {
+        let mut canvas = Canvas::new(10, 10);
+
+        for x in 0..10 {
+            for y in 0..10 {
+                canvas.write_pixel(x, y, color(x as F / 9.0, y as F / 9.0, 0));
+            }
+        }
+
+        canvas
+    }
+
+    #[test]
+    fn an_image_pattern_picks_the_nearest_pixel() {
+        let pattern = UvPattern::image(gradient_image());
+
+        for (u, v, x, y) in [
+            (0.0, 0.0, 0, 9),
+            (0.3, 0.0, 3, 9),
+            (0.6, 0.3, 5, 6),
+            (1.0, 1.0, 9, 0),
+        ] {
+            let expected = color(x as F / 9.0, y as F / 9.0, 0);
+
+            assert_fuzzy_eq!(pattern.color_at(u, v), expected);
+        }
+    }
+
+    #[test]
+    fn a_texture_map_with_a_spherical_mapping() {
+        let pattern = texture_map(UvPattern::image(gradient_image()), UvMapping::Spherical);
+
+        // the north pole is the top row of the image
+        assert_fuzzy_eq!(pattern.color_at(pt(0, 1, 0)), color(0.55556, 0, 0));
+        // the south pole is the bottom row of the image
+        assert_fuzzy_eq!(pattern.color_at(pt(0, -1, 0)), color(0.55556, 1, 0));
+    }
+
+    #[test]
+    fn a_cube_map_has_a_texture_for_every_face() {
+        let faces = [
+            color(1, 0, 0),
+            color(0, 1, 0),
+            color(0, 0, 1),
+            color(1, 1, 0),
+            color(0, 1, 1),
+            color(1, 0, 1),
+        ];
+
+        let pattern = cube_map(faces.map(|c| UvPattern::image(Canvas::new_with_color(2, 2, c))));
+
+        for (point, expected) in [
+            (pt(-1, 0, 0), faces[0]),
+            (pt(0, 0, 1), faces[1]),
+            (pt(1, 0, 0), faces[2]),
+            (pt(0, 0, -1), faces[3]),
+            (pt(0, 1, 0), faces[4]),
+            (pt(0, -1, 0), faces[5]),
+        ] {
+            assert_fuzzy_eq!(pattern.color_at(point), expected);
+        }
+    }
+
+    #[test]
+    fn a_texture_map_follows_the_object_transformation() {
+        let object = Sphere::default().transform(Matrix::scaling(2, 2, 2));
+        let pattern = texture_map(UvPattern::image(gradient_image()), UvMapping::Spherical);
+
+        let c = pattern.color_at_object(&object, pt(0, 2, 0));
+
+        assert_fuzzy_eq!(c, color(0.55556, 0, 0));
+    }
+
+    #[test]
+    fn noise_is_zero_on_the_lattice() {
+        let noise = Noise::new(1);
+
+        assert_fuzzy_eq!(noise.noise(pt(0, 0, 0)), 0.0);
+        assert_fuzzy_eq!(noise.noise(pt(3, -2, 7)), 0.0);
+        assert_fuzzy_eq!(noise.noise(pt(-12, 5, 300)), 0.0);
+    }
+
+    #[test]
+    fn noise_is_deterministic() {
+        let a = Noise::new(7);
+        let b = Noise::new(7);
+        let c = Noise::new(8);
+        let p = pt(0.3, 1.7, -2.4);
+
+        assert_eq!(a.noise(p), b.noise(p));
+        assert_ne!(a.noise(p), c.noise(p));
+    }
+
+    #[test]
+    fn noise_stays_within_range() {
+        let noise = Noise::new(3).octaves(6);
+        let mut rng = Rng::new(0);
+
+        for _ in 0..1000 {
+            let p = pt(
+                rng.next_f() * 20.0 - 10.0,
+                rng.next_f() * 20.0 - 10.0,
+                rng.next_f() * 20.0 - 10.0,
+            );
+
+            assert!((-1.0..=1.0).contains(&noise.noise(p)));
+            assert!((-1.0..=1.0).contains(&noise.fbm(p)));
+            assert!((0.0..=1.0).contains(&noise.turbulence(p)));
+        }
+    }
+
+    #[test]
+    fn noise_is_continuous() {
+        let noise = Noise::new(5);
+        let p = pt(0.5, 0.25, 0.75);
+
+        let d = noise.noise(p) - noise.noise(p + v(0.0001, 0, 0));
+
+        assert!(d.abs() < 0.001);
+    }
+
+    #[test]
+    fn a_single_octave_of_fbm_is_the_noise() {
+        let noise = Noise::new(2).octaves(1);
+        let p = pt(1.3, 0.2, 4.1);
+
+        assert_fuzzy_eq!(noise.fbm(p), noise.noise(p));
+        assert_fuzzy_eq!(noise.turbulence(p), noise.noise(p).abs());
+    }
+
+    #[test]
+    fn a_turbulence_pattern_blends_between_colors() {
+        let noise = Noise::new(4);
+        let pattern = turbulence(WHITE, BLACK, noise.clone());
+        let p = pt(0.4, 0.6, 0.2);
+
+        let t = noise.turbulence(p);
+
+        assert_fuzzy_eq!(pattern.color_at(p), color(1.0 - t, 1.0 - t, 1.0 - t));
+    }
+
+    #[test]
+    fn marble_and_wood_stay_between_their_colors() {
+        let a = color(0.2, 0.4, 0.6);
+        let b = color(0.8, 0.6, 0.4);
+        let mut rng = Rng::new(1);
+
+        for pattern in [marble(a, b, Noise::new(1)), wood(a, b, Noise::new(1))] {
+            for _ in 0..100 {
+                let p = pt(rng.next_f() * 4.0, rng.next_f() * 4.0, rng.next_f() * 4.0);
+                let c = pattern.color_at(p);
+
+                assert!((0.2..=0.8).contains(&c.red));
+                assert!((0.4..=0.6).contains(&c.green));
+            }
+        }
+    }
+
+    #[test]
+    fn a_perturbed_pattern_without_jitter_is_unchanged() {
+        let pattern = perturb(test(), 0, Noise::new(1));
+
+        assert_fuzzy_eq!(pattern.color_at(pt(0.3, 0.5, 0.7)), color(0.3, 0.5, 0.7));
+    }
+
+    #[test]
+    fn a_perturbed_pattern_jitters_the_point() {
+        let noise = Noise::new(9);
+        let pattern = perturb(test(), 0.5, noise.clone());
+        let p = pt(0.3, 0.5, 0.7);
+
+        let c = pattern.color_at(p);
+
+        assert_fuzzy_eq!(c.red, p.x + 0.5 * noise.fbm(p));
+        assert!((c.red - p.x).abs() <= 0.5);
+        assert!((c.green - p.y).abs() <= 0.5);
+        assert!((c.blue - p.z).abs() <= 0.5);
+    }
+
+    #[test]
+    fn a_perturbed_pattern_applies_the_inner_transformation() {
+        let inner = test().transform(Matrix::scaling(2, 2, 2));
+        let pattern = perturb(inner, 0, Noise::new(1));
+
+        assert_fuzzy_eq!(pattern.color_at(pt(2, 3, 4)), color(1, 1.5, 2));
+    }
+
+    #[test]
+    fn a_pattern_of_patterns() {
+        let pattern = checkers(stripe(WHITE, BLACK), color(0, 0, 1));
+
+        assert_fuzzy_eq!(pattern.color_at(pt(0.5, 0.5, 0.5)), WHITE);
+        assert_fuzzy_eq!(pattern.color_at(pt(1.5, 0.5, 1.5)), BLACK);
+        assert_fuzzy_eq!(pattern.color_at(pt(1.5, 0.5, 0.5)), color(0, 0, 1));
+    }
+
+    #[test]
+    fn a_nested_pattern_uses_its_own_transformation() {
+        let inner = test().transform(Matrix::translation(0.5, 1, 1.5));
+        let pattern = stripe(inner, BLACK).transform(Matrix::scaling(2, 2, 2));
+        let object = Sphere::default();
+
+        let c = pattern.color_at_object(&object, pt(1, 3, 4));
+
+        assert_fuzzy_eq!(c, color(0, 0.5, 0.5));
+    }
+
+    #[test]
+    fn nesting_works_recursively() {
+        let inner = stripe(color(1, 0, 0), color(0, 1, 0));
+        let middle = ring(inner, BLACK);
+        let pattern = checkers(middle, WHITE);
+
+        assert_fuzzy_eq!(pattern.color_at(pt(0.5, 0, 0.5)), color(1, 0, 0));
+        assert_fuzzy_eq!(pattern.color_at(pt(1.5, 0, 1.5)), color(0, 1, 0));
+        assert_fuzzy_eq!(pattern.color_at(pt(1.5, 0, 0.5)), WHITE);
+    }
+
+    #[test]
+    fn averaging_two_patterns() {
+        let a = stripe(WHITE, BLACK);
+        let b = stripe(WHITE, BLACK).transform(Matrix::rotation_y(PI / 2.0));
+        let pattern = average(a, b);
+
+        assert_fuzzy_eq!(pattern.color_at(pt(0.5, 0, -0.5)), WHITE);
+        assert_fuzzy_eq!(pattern.color_at(pt(1.5, 0, -0.5)), color(0.5, 0.5, 0.5));
+        assert_fuzzy_eq!(pattern.color_at(pt(1.5, 0, 0.5)), BLACK);
+    }
+
+    #[test]
+    fn a_weighted_blend() {
+        let pattern = blend(color(1, 0, 0), color(0, 0, 1), 0.25);
+
+        assert_fuzzy_eq!(pattern.color_at(pt(0, 0, 0)), color(0.75, 0, 0.25));
+    }
+
+    #[test]
+    fn a_mask_picks_between_two_patterns() {
+        let pattern = mask(stripe(BLACK, WHITE), color(1, 0, 0), checkers(WHITE, BLACK));
+
+        assert_fuzzy_eq!(pattern.color_at(pt(0.5, 0, 0)), color(1, 0, 0));
+        assert_fuzzy_eq!(pattern.color_at(pt(1.5, 0, 0)), BLACK);
+        assert_fuzzy_eq!(pattern.color_at(pt(1.5, 1, 0)), WHITE);
+    }
+
+    #[test]
+    fn a_gray_mask_blends() {
+        let pattern = mask(color(0.5, 0.5, 0.5), BLACK, WHITE);
+
+        assert_fuzzy_eq!(pattern.color_at(pt(0, 0, 0)), color(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn a_radial_gradient_fades_out_from_the_origin() {
+        let pattern = radial_gradient(WHITE, BLACK);
+
+        assert_fuzzy_eq!(pattern.color_at(pt(0, 0, 0)), WHITE);
+        assert_fuzzy_eq!(pattern.color_at(pt(0.25, 0, 0)), color(0.75, 0.75, 0.75));
+        assert_fuzzy_eq!(pattern.color_at(pt(0, 0.5, 0)), color(0.5, 0.5, 0.5));
+        assert_fuzzy_eq!(pattern.color_at(pt(0, 0.6, 0.8)), BLACK);
+        assert_fuzzy_eq!(pattern.color_at(pt(3, 0, 0)), BLACK);
+    }
+
+    #[test]
+    fn a_ring_gradient_repeats_around_the_y_axis() {
+        let pattern = ring_gradient(WHITE, BLACK);
+
+        assert_fuzzy_eq!(pattern.color_at(pt(0, 0, 0)), WHITE);
+        assert_fuzzy_eq!(pattern.color_at(pt(0.25, 0, 0)), color(0.75, 0.75, 0.75));
+        assert_fuzzy_eq!(pattern.color_at(pt(0, 5, 0.5)), color(0.5, 0.5, 0.5));
+        assert_fuzzy_eq!(pattern.color_at(pt(0.6, 0, 0.8)), WHITE);
+        assert_fuzzy_eq!(pattern.color_at(pt(-1.75, 0, 0)), color(0.25, 0.25, 0.25));
+    }
+
+    #[test]
+    fn dots_are_centred_on_integer_coordinates() {
+        let pattern = dots(WHITE, BLACK, 0.25);
+
+        assert_fuzzy_eq!(pattern.color_at(pt(0, 0, 0)), BLACK);
+        assert_fuzzy_eq!(pattern.color_at(pt(1.1, 0, -2.1)), BLACK);
+        assert_fuzzy_eq!(pattern.color_at(pt(0.2, 0.2, 0)), WHITE);
+        assert_fuzzy_eq!(pattern.color_at(pt(0.5, 0, 0.5)), WHITE);
+        assert_fuzzy_eq!(pattern.color_at(pt(3, 0.9, 0)), BLACK);
+    }
+
+    #[test]
+    fn hexagons_tile_the_xz_plane() {
+        let r = color(1, 0, 0);
+        let g = color(0, 1, 0);
+        let b = color(0, 0, 1);
+        let pattern = hexagon(r, g, b);
+        let w = F::sqrt(3.0);
+
+        // the hexagon around the origin reaches sqrt(3)/2 along x
+        assert_fuzzy_eq!(pattern.color_at(pt(0, 0, 0)), r);
+        assert_fuzzy_eq!(pattern.color_at(pt(0.8, 0, 0)), r);
+        assert_fuzzy_eq!(pattern.color_at(pt(0, 0, 0.9)), r);
+        assert_fuzzy_eq!(pattern.color_at(pt(0, 7, 0)), r);
+
+        // the six neighbours only use the other two colors
+        for (x, z) in [
+            (w, 0.0),
+            (-w, 0.0),
+            (w / 2.0, 1.5),
+            (-w / 2.0, 1.5),
+            (w / 2.0, -1.5),
+            (-w / 2.0, -1.5),
+        ] {
+            let c = pattern.color_at(pt(x, 0, z));
+
+            assert!(c == g || c == b, "{c:?} at ({x}, {z})");
+        }
+
+        // as do any two neighbours of each other
+        assert_ne!(
+            pattern.color_at(pt(w, 0, 0)),
+            pattern.color_at(pt(w / 2.0, 0, 1.5))
+        );
+    }
+
+    #[test]
+    fn checkers_pattern_in_2d() {
+        let checkers = UvPattern::checkers(2, 2, BLACK, WHITE);
+
+        assert_fuzzy_eq!(checkers.color_at(0.0, 0.0), BLACK);
+        assert_fuzzy_eq!(checkers.color_at(0.5, 0.0), WHITE);
+        assert_fuzzy_eq!(checkers.color_at(0.0, 0.5), WHITE);
+        assert_fuzzy_eq!(checkers.color_at(0.5, 0.5), BLACK);
+        assert_fuzzy_eq!(checkers.color_at(1.0, 1.0), BLACK);
+    }
+
+    #[test]
+    fn using_a_texture_map_pattern_with_a_spherical_map() {
+        let checkers = UvPattern::checkers(16, 8, BLACK, WHITE);
+        let pattern = texture_map(checkers, UvMapping::Spherical);
+
+        for (point, expected) in [
+            (pt(0.4315, 0.4670, 0.7719), WHITE),
+            (pt(-0.9654, 0.2552, -0.0534), BLACK),
+            (pt(0.1039, 0.7090, 0.6975), WHITE),
+            (pt(-0.4986, -0.7856, -0.3663), BLACK),
+            (pt(-0.0317, -0.9395, 0.3411), BLACK),
+            (pt(0.4809, -0.7721, 0.4154), BLACK),
+            (pt(0.0285, -0.9612, -0.2745), BLACK),
+            (pt(-0.5734, -0.2162, -0.7903), WHITE),
+            (pt(0.7688, -0.1470, 0.6223), BLACK),
+            (pt(-0.7652, 0.2175, 0.6060), BLACK),
+        ] {
+            assert_fuzzy_eq!(pattern.color_at(point), expected);
+        }
+    }
+
+    #[test]
+    fn planar_uv_checkers_have_no_acne_on_the_xz_plane() {
+        let pattern = texture_map(UvPattern::checkers(2, 2, WHITE, BLACK), UvMapping::Planar);
+
+        // points just above and below y = 0 get the same color, unlike
+        // with the 3D checkers
+        for (x, z) in [(0.25, 0.25), (0.75, 0.25), (1.25, 1.75)] {
+            assert_fuzzy_eq!(
+                pattern.color_at(pt(x, 0.00001, z)),
+                pattern.color_at(pt(x, -0.00001, z))
+            );
+        }
+
+        let checkers = checkers(WHITE, BLACK);
+        assert_fuzzy_ne!(
+            checkers.color_at(pt(0.25, 0.00001, 0.25)),
+            checkers.color_at(pt(0.25, -0.00001, 0.25))
+        );
+    }
 }
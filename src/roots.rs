@@ -0,0 +1,255 @@
+use crate::{F, PI};
+
+// a value this much smaller than the numbers it was worked out from is
+// treated as zero, so that the same equation scaled up or down gives the
+// same roots
+const TOLERANCE: F = 1e-9;
+
+fn is_zero(x: F, scale: F) -> bool {
+    x.abs() <= TOLERANCE * scale
+}
+
+/// The real roots of `a x² + b x + c`, in ascending order.
+#[must_use]
+pub fn solve_quadratic(a: F, b: F, c: F) -> Vec<F> {
+    // the coefficients go with different powers of x, so there's nothing to
+    // measure a small leading one against: only a missing one lowers the
+    // degree
+    if a == 0.0 {
+        return if b == 0.0 { vec![] } else { vec![-c / b] };
+    }
+
+    // normal form: x² + px + q = 0
+    let p = b / (2.0 * a);
+    let q = c / a;
+    let d = p * p - q;
+
+    if is_zero(d, p * p + q.abs()) {
+        vec![-p]
+    } else if d < 0.0 {
+        vec![]
+    } else {
+        let sqrt_d = d.sqrt();
+        vec![-sqrt_d - p, sqrt_d - p]
+    }
+}
+
+/// The real roots of `a x³ + b x² + c x + d`, using Cardano's method.
+#[must_use]
+pub fn solve_cubic(a: F, b: F, c: F, d: F) -> Vec<F> {
+    if a == 0.0 {
+        return solve_quadratic(b, c, d);
+    }
+
+    // normal form: x³ + Ax² + Bx + C = 0
+    let (a, b, c) = (b / a, c / a, d / a);
+
+    // substitute x = y - A/3 to eliminate the quadric term: y³ + 3py + 2q = 0
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * b / 3.0 + c) / 2.0;
+    let q_scale = (2.0 / 27.0 * (a * sq_a).abs() + (a * b).abs() / 3.0 + c.abs()) / 2.0;
+
+    let cb_p = p * p * p;
+    let discriminant = q * q + cb_p;
+
+    let mut roots = if is_zero(discriminant, q * q + cb_p.abs()) {
+        if is_zero(q, q_scale) {
+            // one triple root
+            vec![0.0]
+        } else {
+            // one single and one double root
+            let u = (-q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if discriminant < 0.0 {
+        // three real roots
+        let phi = (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+
+        vec![
+            t * phi.cos(),
+            -t * (phi + PI / 3.0).cos(),
+            -t * (phi - PI / 3.0).cos(),
+        ]
+    } else {
+        // one real root
+        let sqrt_d = discriminant.sqrt();
+        vec![(sqrt_d - q).cbrt() - (sqrt_d + q).cbrt()]
+    };
+
+    for root in &mut roots {
+        *root -= a / 3.0;
+    }
+
+    roots.sort_by(F::total_cmp);
+    roots
+}
+
+/// The real roots of `a x⁴ + b x³ + c x² + d x + e`, in ascending order.
+///
+/// The roots are found with Ferrari's method and then polished with a few
+/// steps of Newton's method, since the closed form loses a lot of precision
+/// when the coefficients differ by orders of magnitude.
+#[must_use]
+pub fn solve_quartic(a: F, b: F, c: F, d: F, e: F) -> Vec<F> {
+    if a == 0.0 {
+        return solve_cubic(b, c, d, e);
+    }
+
+    // normal form: x⁴ + Ax³ + Bx² + Cx + D = 0
+    let (qa, qb, qc, qd) = (b / a, c / a, d / a, e / a);
+
+    // substitute x = y - A/4 to eliminate the cubic term:
+    // y⁴ + py² + qy + r = 0
+    let sq_a = qa * qa;
+    let p = -3.0 / 8.0 * sq_a + qb;
+    let q = sq_a * qa / 8.0 - qa * qb / 2.0 + qc;
+    let r = -3.0 / 256.0 * sq_a * sq_a + sq_a * qb / 16.0 - qa * qc / 4.0 + qd;
+    let r_scale =
+        3.0 / 256.0 * sq_a * sq_a + (sq_a * qb).abs() / 16.0 + (qa * qc).abs() / 4.0 + qd.abs();
+
+    let mut roots = if is_zero(r, r_scale) {
+        // no absolute term: y(y³ + py + q) = 0
+        let mut roots = solve_cubic(1.0, 0.0, p, q);
+        roots.push(0.0);
+        roots
+    } else {
+        // solve the resolvent cubic and take one of its real roots
+        let resolvent = solve_cubic(1.0, -p / 2.0, -r, r * p / 2.0 - q * q / 8.0);
+        let Some(&z) = resolvent.last() else {
+            return vec![];
+        };
+
+        // and use it to split the quartic into two quadratics
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+
+        let u = if is_zero(u, z * z + r.abs()) {
+            0.0
+        } else if u > 0.0 {
+            u.sqrt()
+        } else {
+            return vec![];
+        };
+
+        let v = if is_zero(v, 2.0 * z.abs() + p.abs()) {
+            0.0
+        } else if v > 0.0 {
+            v.sqrt()
+        } else {
+            return vec![];
+        };
+
+        let v = if q < 0.0 { -v } else { v };
+
+        let mut roots = solve_quadratic(1.0, v, z - u);
+        roots.extend(solve_quadratic(1.0, -v, z + u));
+        roots
+    };
+
+    let polynomial = |x: F| (((a * x + b) * x + c) * x + d) * x + e;
+    let derivative = |x: F| ((4.0 * a * x + 3.0 * b) * x + 2.0 * c) * x + d;
+
+    for root in &mut roots {
+        *root -= qa / 4.0;
+
+        for _ in 0..3 {
+            let slope = derivative(*root);
+            if slope == 0.0 {
+                break;
+            }
+            *root -= polynomial(*root) / slope;
+        }
+    }
+
+    roots.sort_by(F::total_cmp);
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn solving_quadratics() {
+        assert_eq!(solve_quadratic(1.0, -3.0, 2.0), vec![1.0, 2.0]);
+        assert_eq!(solve_quadratic(1.0, -2.0, 1.0), vec![1.0]);
+        assert_eq!(solve_quadratic(1.0, 0.0, 1.0), vec![]);
+        assert_eq!(solve_quadratic(0.0, 2.0, -1.0), vec![0.5]);
+    }
+
+    #[test]
+    fn solving_cubics() {
+        // (x + 1)(x - 2)(x - 3)
+        let roots = solve_cubic(1.0, -4.0, 1.0, 6.0);
+        assert_eq!(roots.len(), 3);
+        for (root, expected) in roots.iter().zip([-1.0, 2.0, 3.0]) {
+            assert_fuzzy_eq!(*root, expected);
+        }
+
+        // x³ - 8 has a single real root
+        let roots = solve_cubic(1.0, 0.0, 0.0, -8.0);
+        assert_eq!(roots.len(), 1);
+        assert_fuzzy_eq!(roots[0], 2.0);
+    }
+
+    #[test]
+    fn solving_quartics() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        let roots = solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0);
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip([1.0, 2.0, 3.0, 4.0]) {
+            assert_fuzzy_eq!(*root, expected);
+        }
+
+        let roots = solve_quartic(2.0, 0.0, 0.0, 0.0, -2.0);
+        assert_eq!(roots.len(), 2);
+        assert_fuzzy_eq!(roots[0], -1.0);
+        assert_fuzzy_eq!(roots[1], 1.0);
+
+        assert!(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0).is_empty());
+    }
+
+    #[test]
+    fn scaling_an_equation_keeps_its_roots() {
+        for scale in [1e-12, 1e-6, 1.0, 1e6, 1e12] {
+            // a ray with a very short or very long direction gives
+            // coefficients like these
+            assert_eq!(
+                solve_quadratic(scale, -3.0 * scale, 2.0 * scale),
+                vec![1.0, 2.0]
+            );
+
+            let roots = solve_cubic(scale, -4.0 * scale, scale, 6.0 * scale);
+            assert_eq!(roots.len(), 3);
+
+            let roots = solve_quartic(
+                scale,
+                -10.0 * scale,
+                35.0 * scale,
+                -50.0 * scale,
+                24.0 * scale,
+            );
+            assert_eq!(roots.len(), 4);
+        }
+
+        // roots a hundred thousandth apart are still told apart
+        let roots = solve_quadratic(1.0, -2e-5, 0.75e-10);
+        assert_eq!(roots.len(), 2);
+        assert_fuzzy_eq!(roots[0], 0.5e-5);
+        assert_fuzzy_eq!(roots[1], 1.5e-5);
+    }
+
+    #[test]
+    fn quartic_roots_are_refined() {
+        // roots far apart in magnitude: (x - 0.001)(x - 1)(x - 10)(x - 1000)
+        let roots = solve_quartic(1.0, -1011.001, 11011.011, -10011.01, 10.0);
+
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip([0.001, 1.0, 10.0, 1000.0]) {
+            assert!((root - expected).abs() < 1e-9 * expected.max(1.0));
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use crate::{v, Tuple, F, PI};
+
+/// A small xorshift generator. Renders seed one per pixel so the output
+/// doesn't depend on how rayon schedules the work.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        // scramble the seed with splitmix64 so that neighbouring seeds
+        // produce unrelated sequences (and a zero seed still works)
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+
+        Self { state: z.max(1) }
+    }
+
+    /// A generator seeded by the position of `point`, for code that needs
+    /// the same random numbers every time it looks at the same point.
+    #[must_use]
+    pub fn for_point(point: Tuple) -> Self {
+        Self::new(
+            point.x.to_bits()
+                ^ point.y.to_bits().rotate_left(21)
+                ^ point.z.to_bits().rotate_left(42),
+        )
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A uniformly distributed number in `[0, 1)`.
+    pub fn next_f(&mut self) -> F {
+        (self.next_u64() >> 11) as F / (1_u64 << 53) as F
+    }
+}
+
+/// Two unit vectors that together with `n` form an orthonormal basis.
+#[must_use]
+pub fn orthonormal_basis(n: Tuple) -> (Tuple, Tuple) {
+    // pick the axis least aligned with n to avoid a degenerate cross product
+    let a = if n.x.abs() > 0.9 {
+        v(0, 1, 0)
+    } else {
+        v(1, 0, 0)
+    };
+    let t = n.cross(a).normalize();
+    let b = n.cross(t);
+
+    (t, b)
+}
+
+/// Samples a direction in the hemisphere around `normal` with a density
+/// proportional to the cosine of its angle to the normal.
+#[must_use]
+pub fn cosine_hemisphere(normal: Tuple, u1: F, u2: F) -> Tuple {
+    let r = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    let (t, b) = orthonormal_basis(normal);
+
+    (t * (r * phi.cos()) + b * (r * phi.sin()) + normal * (1.0 - u1).max(0.0).sqrt()).normalize()
+}
+
+/// Jitters `direction` within a cone, `spread` being the tangent of the
+/// cone's half angle.
+#[must_use]
+pub fn cone_sample(direction: Tuple, spread: F, u1: F, u2: F) -> Tuple {
+    let r = spread * u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    let (t, b) = orthonormal_basis(direction);
+
+    (direction + t * (r * phi.cos()) + b * (r * phi.sin())).normalize()
+}
+
+/// Jitters `direction` within a cone of the given `blur`, keeping it on the
+/// same side of the surface with the given `normal`.
+#[must_use]
+pub fn glossy_direction(direction: Tuple, normal: Tuple, blur: F, rng: &mut Rng) -> Tuple {
+    if blur == 0.0 {
+        return direction;
+    }
+
+    let d = cone_sample(direction, blur, rng.next_f(), rng.next_f());
+
+    if d.dot(normal) * direction.dot(normal) < 0.0 {
+        // mirror it back through the surface
+        d - normal * (2.0 * d.dot(normal))
+    } else {
+        d
+    }
+}
+
+/// Samples a microfacet normal around `normal` from the GGX distribution
+/// with roughness `alpha`, with a density proportional to the distribution
+/// times the cosine of its angle to the normal.
+#[must_use]
+pub fn ggx_half_vector(normal: Tuple, alpha: F, u1: F, u2: F) -> Tuple {
+    let cos2 = (1.0 - u1) / (1.0 + (alpha * alpha - 1.0) * u1);
+    let sin = (1.0 - cos2).max(0.0).sqrt();
+    let phi = 2.0 * PI * u2;
+    let (t, b) = orthonormal_basis(normal);
+
+    (t * (sin * phi.cos()) + b * (sin * phi.sin()) + normal * cos2.sqrt()).normalize()
+}
+
+/// Picks indices with a probability proportional to their weight.
+#[derive(Debug, Clone)]
+pub struct Distribution {
+    cdf: Vec<F>,
+    total: F,
+}
+
+impl Distribution {
+    /// Falls back to a uniform distribution when all the weights are zero.
+    #[must_use]
+    pub fn new(weights: &[F]) -> Self {
+        let total = weights.iter().sum::<F>();
+
+        let mut cdf = Vec::with_capacity(weights.len() + 1);
+        cdf.push(0.0);
+
+        for (i, w) in weights.iter().enumerate() {
+            let next = if total > 0.0 {
+                cdf[i] + w / total
+            } else {
+                (i + 1) as F / weights.len() as F
+            };
+            cdf.push(next);
+        }
+
+        Self { cdf, total }
+    }
+
+    /// The sum of all the weights.
+    #[must_use]
+    pub fn total(&self) -> F {
+        self.total
+    }
+
+    #[must_use]
+    pub fn probability(&self, index: usize) -> F {
+        self.cdf[index + 1] - self.cdf[index]
+    }
+
+    /// Turns a uniform `u` in `[0, 1)` into an index, along with where `u`
+    /// fell inside that index's share of the interval so that it can be
+    /// reused as another uniform number.
+    #[must_use]
+    pub fn sample(&self, u: F) -> (usize, F) {
+        let last = self.cdf.len() - 2;
+        let index = self
+            .cdf
+            .partition_point(|&c| c <= u)
+            .saturating_sub(1)
+            .min(last);
+
+        let width = self.probability(index);
+        let remainder = if width > 0.0 {
+            ((u - self.cdf[index]) / width).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        (index, remainder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn the_generator_is_deterministic() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn different_seeds_produce_different_sequences() {
+        let mut a = Rng::new(0);
+        let mut b = Rng::new(1);
+
+        assert_ne!(a.next_u64(), b.next_u64());
+    }
+
+    #[test]
+    fn random_numbers_are_in_the_unit_interval() {
+        let mut rng = Rng::new(7);
+
+        for _ in 0..1000 {
+            let n = rng.next_f();
+            assert!((0.0..1.0).contains(&n));
+        }
+    }
+
+    #[test]
+    fn an_orthonormal_basis_is_perpendicular_to_the_normal() {
+        for n in [v(0, 1, 0), v(1, 0, 0), v(1, 2, 3).normalize()] {
+            let (t, b) = orthonormal_basis(n);
+
+            assert_fuzzy_eq!(t.dot(n), 0.0);
+            assert_fuzzy_eq!(b.dot(n), 0.0);
+            assert_fuzzy_eq!(t.dot(b), 0.0);
+            assert_fuzzy_eq!(t.magnitude(), 1.0);
+            assert_fuzzy_eq!(b.magnitude(), 1.0);
+        }
+    }
+
+    #[test]
+    fn cosine_samples_lie_in_the_hemisphere_of_the_normal() {
+        let mut rng = Rng::new(3);
+        let normal = v(0, 0, -1);
+
+        for _ in 0..1000 {
+            let d = cosine_hemisphere(normal, rng.next_f(), rng.next_f());
+
+            assert!(d.dot(normal) >= 0.0);
+            assert_fuzzy_eq!(d.magnitude(), 1.0);
+        }
+    }
+
+    #[test]
+    fn ggx_samples_gather_around_the_normal_of_smooth_surfaces() {
+        let mut rng = Rng::new(5);
+        let normal = v(1, 0, 0);
+
+        for _ in 0..1000 {
+            let (u1, u2) = (rng.next_f(), rng.next_f());
+            let rough = ggx_half_vector(normal, 1.0, u1, u2);
+            let smooth = ggx_half_vector(normal, 0.01, u1, u2);
+
+            assert_fuzzy_eq!(smooth.magnitude(), 1.0);
+            assert!(rough.dot(normal) >= 0.0);
+            assert!(smooth.dot(normal) >= rough.dot(normal) - EPSILON);
+        }
+    }
+
+    #[test]
+    fn a_distribution_picks_indices_in_proportion_to_their_weights() {
+        let d = Distribution::new(&[1.0, 0.0, 3.0]);
+
+        assert_fuzzy_eq!(d.total(), 4.0);
+        assert_fuzzy_eq!(d.probability(0), 0.25);
+        assert_fuzzy_eq!(d.probability(1), 0.0);
+        assert_fuzzy_eq!(d.probability(2), 0.75);
+
+        assert_eq!(d.sample(0.0).0, 0);
+        assert_eq!(d.sample(0.2).0, 0);
+        assert_eq!(d.sample(0.25).0, 2);
+        assert_eq!(d.sample(0.99).0, 2);
+    }
+
+    #[test]
+    fn sampling_a_distribution_remaps_the_random_number() {
+        let d = Distribution::new(&[1.0, 1.0]);
+
+        let (i, u) = d.sample(0.75);
+
+        assert_eq!(i, 1);
+        assert_fuzzy_eq!(u, 0.5);
+    }
+
+    #[test]
+    fn a_distribution_without_weight_is_uniform() {
+        let d = Distribution::new(&[0.0, 0.0, 0.0, 0.0]);
+
+        assert_fuzzy_eq!(d.probability(3), 0.25);
+        assert_eq!(d.sample(0.6).0, 2);
+    }
+
+    #[test]
+    fn generators_for_the_same_point_agree() {
+        let mut a = Rng::for_point(pt(0.5, -1.25, 3));
+        let mut b = Rng::for_point(pt(0.5, -1.25, 3));
+        let mut c = Rng::for_point(pt(0.5, 3, -1.25));
+
+        let n = a.next_u64();
+
+        assert_eq!(n, b.next_u64());
+        assert_ne!(n, c.next_u64());
+    }
+
+    #[test]
+    fn cone_samples_stay_within_the_cone() {
+        let mut rng = Rng::new(11);
+        let direction = v(1, 1, 0).normalize();
+        let cos_max = 1.0 / F::sqrt(1.0 + 0.3 * 0.3);
+
+        for _ in 0..1000 {
+            let d = cone_sample(direction, 0.3, rng.next_f(), rng.next_f());
+
+            assert_fuzzy_eq!(d.magnitude(), 1.0);
+            assert!(d.dot(direction) >= cos_max - EPSILON);
+        }
+    }
+
+    #[test]
+    fn blurry_rays_stay_on_the_side_of_the_surface() {
+        let mut rng = Rng::new(0);
+        let direction = v(0, 1, 1).normalize();
+
+        for _ in 0..1000 {
+            let d = glossy_direction(direction, v(0, 1, 0), 5.0, &mut rng);
+
+            assert!(d.y >= 0.0);
+        }
+    }
+
+    #[test]
+    fn a_cone_without_spread_is_the_direction_itself() {
+        let direction = v(0, 0, -1);
+
+        assert_fuzzy_eq!(cone_sample(direction, 0.0, 0.3, 0.7), direction);
+    }
+}
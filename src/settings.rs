@@ -0,0 +1,187 @@
+use crate::F;
+
+/// Limits on how far `Camera::render` follows reflected and refracted rays.
+///
+/// Reflections and refractions are counted separately, so a path may bounce
+/// up to `reflection_depth + refraction_depth` times in all.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+    /// The number of reflections followed along a path, in total.
+    pub reflection_depth: u8,
+    /// The number of refractions followed along a path, in total.
+    pub refraction_depth: u8,
+    /// Rays that would add less than this fraction of their light to the
+    /// pixel aren't traced at all.
+    pub min_contribution: F,
+}
+
+impl RenderSettings {
+    #[must_use]
+    pub fn reflection_depth(mut self, reflection_depth: u8) -> Self {
+        self.reflection_depth = reflection_depth;
+
+        self
+    }
+
+    #[must_use]
+    pub fn refraction_depth(mut self, refraction_depth: u8) -> Self {
+        self.refraction_depth = refraction_depth;
+
+        self
+    }
+
+    #[must_use]
+    pub fn min_contribution(mut self, min_contribution: impl Into<F>) -> Self {
+        self.min_contribution = min_contribution.into();
+
+        self
+    }
+
+    /// The budget of a ray leaving the camera.
+    #[must_use]
+    pub fn budget(&self) -> RayBudget {
+        RayBudget {
+            reflections: self.reflection_depth,
+            refractions: self.refraction_depth,
+            weight: 1.0,
+            min_contribution: self.min_contribution,
+            blurred: false,
+        }
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            reflection_depth: 5,
+            refraction_depth: 5,
+            min_contribution: 0.0,
+        }
+    }
+}
+
+/// What's left of the render settings for a ray being traced: the number of
+/// reflections and refractions it may still go through, how much it
+/// contributes to the pixel it was traced for, and whether it was already
+/// split into several rays at a blurry surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayBudget {
+    pub reflections: u8,
+    pub refractions: u8,
+    pub weight: F,
+    pub min_contribution: F,
+    pub blurred: bool,
+}
+
+impl RayBudget {
+    /// The budget of a reflected ray carrying `factor` of the light, or
+    /// `None` when it's not worth tracing.
+    #[must_use]
+    pub fn reflect(self, factor: F) -> Option<Self> {
+        let weight = self.weight * factor;
+
+        (self.reflections > 0 && weight >= self.min_contribution).then(|| Self {
+            reflections: self.reflections - 1,
+            weight,
+            ..self
+        })
+    }
+
+    /// The budget of a refracted ray carrying `factor` of the light, or
+    /// `None` when it's not worth tracing.
+    #[must_use]
+    pub fn refract(self, factor: F) -> Option<Self> {
+        let weight = self.weight * factor;
+
+        (self.refractions > 0 && weight >= self.min_contribution).then(|| Self {
+            refractions: self.refractions - 1,
+            weight,
+            ..self
+        })
+    }
+
+    /// The budget of each of `samples` rays the light of this one is split
+    /// into and averaged over.
+    #[must_use]
+    pub fn split(self, samples: usize) -> Self {
+        Self {
+            weight: self.weight / samples as F,
+            blurred: true,
+            ..self
+        }
+    }
+}
+
+/// A budget of `depth` reflections and refractions without a cutoff.
+impl From<u8> for RayBudget {
+    fn from(depth: u8) -> Self {
+        RenderSettings::default()
+            .reflection_depth(depth)
+            .refraction_depth(depth)
+            .budget()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_default_settings() {
+        let settings = RenderSettings::default();
+
+        assert_eq!(settings.reflection_depth, 5);
+        assert_eq!(settings.refraction_depth, 5);
+        assert_eq!(settings.min_contribution, 0.0);
+    }
+
+    #[test]
+    fn a_budget_starts_at_full_weight() {
+        let budget = RenderSettings::default()
+            .reflection_depth(3)
+            .refraction_depth(7)
+            .min_contribution(0.01)
+            .budget();
+
+        assert_eq!(budget.reflections, 3);
+        assert_eq!(budget.refractions, 7);
+        assert_eq!(budget.weight, 1.0);
+        assert_eq!(budget.min_contribution, 0.01);
+    }
+
+    #[test]
+    fn reflecting_and_refracting_use_separate_limits() {
+        let budget = RayBudget::from(1);
+
+        let reflected = budget.reflect(0.5).unwrap();
+        assert_eq!(reflected.reflections, 0);
+        assert_eq!(reflected.refractions, 1);
+        assert_eq!(reflected.weight, 0.5);
+        assert_eq!(reflected.reflect(0.5), None);
+
+        let refracted = reflected.refract(0.5).unwrap();
+        assert_eq!(refracted.refractions, 0);
+        assert_eq!(refracted.weight, 0.25);
+        assert_eq!(refracted.refract(0.5), None);
+    }
+
+    #[test]
+    fn splitting_a_ray_shares_its_weight() {
+        let budget = RayBudget::from(5).reflect(0.5).unwrap().split(4);
+
+        assert_eq!(budget.reflections, 4);
+        assert_eq!(budget.weight, 0.125);
+        assert!(budget.blurred);
+    }
+
+    #[test]
+    fn rays_below_the_minimum_contribution_are_not_traced() {
+        let budget = RenderSettings::default().min_contribution(0.1).budget();
+
+        let once = budget.reflect(0.2).unwrap();
+        let twice = once.reflect(0.2);
+
+        assert_eq!(once.weight, 0.2);
+        assert_eq!(twice, None);
+    }
+}
@@ -1,10 +1,23 @@
+pub mod bezier;
 pub mod cone;
 pub mod cube;
+pub mod curve;
 pub mod cylinder;
+pub mod disk;
+pub mod heightfield;
+pub mod mesh;
+pub mod metaballs;
 pub mod plane;
+pub mod ply;
+pub mod quadric;
+pub mod rect;
+pub mod sdf;
 pub mod sphere;
+pub mod stl;
+pub mod torus;
 
-use crate::{Intersection, Material, Matrix, Ray, Tuple, F};
+use crate::sampling::orthonormal_basis;
+use crate::{BoundingBox, Intersection, Material, Matrix, Ray, Tuple, F};
 use std::any::Any;
 use std::fmt::Debug;
 
@@ -12,14 +25,36 @@ use std::fmt::Debug;
 pub struct Props {
     pub material: Material,
     pub transform: Matrix<4>,
+    pub casts_shadow: bool,
 }
 
 pub trait Shape: Debug + Sync + Send + AnyShape {
     fn props(&self) -> &Props;
     fn props_mut(&mut self) -> &mut Props;
-    fn local_intersect(&self, ray: Ray) -> Vec<Intersection>;
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>>;
     fn local_normal_at(&self, point: Tuple) -> Tuple;
-    fn intersect(&self, ray: Ray) -> Vec<Intersection> {
+    /// The normal at `point` where `hit` met the shape. Shapes that record
+    /// where on them a ray hit, like meshes, override it to use that instead
+    /// of working it out from the point.
+    fn local_normal_at_hit(&self, point: Tuple, _hit: &Intersection) -> Tuple {
+        self.local_normal_at(point)
+    }
+    /// A unit vector along the surface at `point`, used to orient normal
+    /// maps. Shapes override it to follow the direction in which `u` grows
+    /// in their usual UV mapping.
+    fn local_tangent_at(&self, point: Tuple) -> Tuple {
+        orthonormal_basis(self.local_normal_at(point).normalize()).0
+    }
+    /// The box around the shape in object space. Shapes that don't end
+    /// keep the infinite default.
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::infinite()
+    }
+    /// The box around the shape in the space of whatever contains it.
+    fn parent_space_bounds(&self) -> BoundingBox {
+        self.bounds().transform(self.props().transform)
+    }
+    fn intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
         // convert into object space
         let ray = ray.transform(self.props().transform.inverse());
         self.local_intersect(ray)
@@ -27,16 +62,36 @@ pub trait Shape: Debug + Sync + Send + AnyShape {
     fn normal_at(&self, point: Tuple) -> Tuple {
         let local_point = self.props().transform.inverse() * point;
         let local_normal = self.local_normal_at(local_point);
-        let mut world_normal = self.props().transform.inverse().transpose() * local_normal;
-        world_normal.w = 0.0;
 
-        world_normal.normalize()
+        normal_to_world(self.props().transform, local_normal)
+    }
+    fn normal_at_hit(&self, point: Tuple, hit: &Intersection) -> Tuple {
+        let local_point = self.props().transform.inverse() * point;
+        let local_normal = self.local_normal_at_hit(local_point, hit);
+
+        normal_to_world(self.props().transform, local_normal)
+    }
+    fn tangent_at(&self, point: Tuple) -> Tuple {
+        let local_point = self.props().transform.inverse() * point;
+        let local_tangent = self.local_tangent_at(local_point);
+        // unlike normals, tangents lie on the surface and transform with it
+        let mut world_tangent = self.props().transform * local_tangent;
+        world_tangent.w = 0.0;
+
+        world_tangent.normalize()
     }
     fn intersection(&self, t: F) -> Intersection<'_> {
         Intersection::new(t, self.as_shape())
     }
 }
 
+fn normal_to_world(transform: Matrix<4>, local_normal: Tuple) -> Tuple {
+    let mut world_normal = transform.inverse().transpose() * local_normal;
+    world_normal.w = 0.0;
+
+    world_normal.normalize()
+}
+
 pub trait AnyShape: Any {
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
@@ -63,24 +118,32 @@ impl<T: Shape> AnyShape for T {
 }
 
 pub trait Transforms {
+    #[must_use]
     fn transform(self, transform: Matrix<4>) -> Self;
+    #[must_use]
     fn material(self, material: Material) -> Self;
+    #[must_use]
+    fn casts_shadow(self, casts_shadow: bool) -> Self;
 }
 
 impl<T: Shape> Transforms for T {
-    #[must_use]
     fn transform(mut self, transform: Matrix<4>) -> Self {
         self.props_mut().transform = transform;
 
         self
     }
 
-    #[must_use]
     fn material(mut self, material: Material) -> Self {
         self.props_mut().material = material;
 
         self
     }
+
+    fn casts_shadow(mut self, casts_shadow: bool) -> Self {
+        self.props_mut().casts_shadow = casts_shadow;
+
+        self
+    }
 }
 
 impl<T: Shape> From<T> for Box<dyn Shape> {
@@ -94,6 +157,7 @@ impl Default for Props {
         Self {
             transform: Matrix::identity(),
             material: Material::default(),
+            casts_shadow: true,
         }
     }
 }
@@ -137,7 +201,7 @@ mod tests {
                 &mut self.props
             }
 
-            fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
+            fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
                 unsafe { SAVED_RAY = Some(ray) };
 
                 vec![]
@@ -167,20 +231,28 @@ mod tests {
     #[test]
     fn the_default_material() {
         let s = TestShape::new();
-        let m = s.props().material;
+        let m = &s.props().material;
 
-        assert_eq!(m, Material::default());
+        assert_eq!(*m, Material::default());
     }
 
     #[test]
     fn assigning_a_material() {
         let mut s = TestShape::new();
         let m = Material::default().ambient(1);
-        s.props_mut().material = m;
+        s.props_mut().material = m.clone();
 
         assert_eq!(s.props().material, m);
     }
 
+    #[test]
+    fn shapes_cast_shadows_by_default() {
+        let s = TestShape::new();
+
+        assert!(s.props().casts_shadow);
+        assert!(!s.casts_shadow(false).props().casts_shadow);
+    }
+
     #[test]
     fn intersecting_a_scaled_shape_with_a_ray() {
         let r = ray(pt(0, 0, -5), v(0, 0, 1));
@@ -220,4 +292,42 @@ mod tests {
 
         assert_fuzzy_eq!(n, v(0, 0.97014, -0.24254));
     }
+
+    #[test]
+    fn the_default_tangent_is_perpendicular_to_the_normal() {
+        let s = TestShape::new();
+        let p = pt(1, 2, 3);
+
+        let t = s.local_tangent_at(p);
+
+        assert_fuzzy_eq!(t.dot(s.local_normal_at(p)), 0.0);
+        assert_fuzzy_eq!(t.magnitude(), 1.0);
+    }
+
+    #[test]
+    fn computing_the_tangent_on_a_transformed_shape() {
+        let s = Plane::default().transform(Matrix::rotation_y(PI / 2.0) * Matrix::scaling(2, 2, 2));
+
+        let t = s.tangent_at(pt(0, 0, 0));
+
+        assert_fuzzy_eq!(t, v(0, 0, -1));
+    }
+
+    #[test]
+    fn shapes_are_unbounded_by_default() {
+        let s = TestShape::new();
+
+        assert_eq!(s.bounds(), BoundingBox::infinite());
+    }
+
+    #[test]
+    fn querying_a_shapes_bounding_box_in_its_parents_space() {
+        let s =
+            Sphere::default().transform(Matrix::translation(1, -3, 5) * Matrix::scaling(0.5, 2, 4));
+
+        let bounds = s.parent_space_bounds();
+
+        assert_fuzzy_eq!(bounds.min, pt(0.5, -5, 1));
+        assert_fuzzy_eq!(bounds.max, pt(1.5, -1, 9));
+    }
 }
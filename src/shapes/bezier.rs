@@ -0,0 +1,380 @@
+use crate::{pt, v, Mesh, Tuple, F};
+use std::collections::HashMap;
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult};
+
+/// The weights of the four control points of a cubic Bézier curve at `t`.
+#[must_use]
+pub(crate) fn bernstein(t: F) -> [F; 4] {
+    let s = 1.0 - t;
+
+    [s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t]
+}
+
+// how fast each weight changes with `t`
+fn bernstein_derivative(t: F) -> [F; 4] {
+    let s = 1.0 - t;
+
+    [
+        -3.0 * s * s,
+        3.0 * s * s - 6.0 * s * t,
+        6.0 * s * t - 3.0 * t * t,
+        3.0 * t * t,
+    ]
+}
+
+/// The point at `t` on the cubic Bézier curve with the given control points.
+#[must_use]
+pub(crate) fn cubic(points: [Tuple; 4], t: F) -> Tuple {
+    bernstein(t)
+        .into_iter()
+        .zip(points)
+        .fold(v(0, 0, 0), |sum, (weight, point)| sum + point * weight)
+}
+
+/// A bicubic Bézier patch, the surface used for the Utah teapot. The
+/// control points are given row by row, `u` running along a row and `v`
+/// from one row to the next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BezierPatch {
+    pub points: [Tuple; 16],
+}
+
+impl BezierPatch {
+    #[must_use]
+    pub fn new(points: [Tuple; 16]) -> Self {
+        Self { points }
+    }
+
+    fn weighted_sum(&self, u_weights: [F; 4], v_weights: [F; 4]) -> Tuple {
+        let mut sum = v(0, 0, 0);
+
+        for (row, v_weight) in v_weights.into_iter().enumerate() {
+            for (column, u_weight) in u_weights.into_iter().enumerate() {
+                sum = sum + self.points[row * 4 + column] * (u_weight * v_weight);
+            }
+        }
+
+        sum
+    }
+
+    #[must_use]
+    pub fn point_at(&self, u: F, v: F) -> Tuple {
+        self.weighted_sum(bernstein(u), bernstein(v))
+    }
+
+    #[must_use]
+    pub fn normal_at(&self, u: F, v: F) -> Tuple {
+        let du = self.weighted_sum(bernstein_derivative(u), bernstein(v));
+        let dv = self.weighted_sum(bernstein(u), bernstein_derivative(v));
+        let normal = du.cross(dv);
+
+        // where control points meet, like at the top of the teapot's lid,
+        // the patch has no normal of its own; borrow the one right next to it
+        if normal.magnitude() < 1e-9 {
+            let nudge = |t: F| t + (0.5 - t) * 1e-4;
+            let du = self.weighted_sum(bernstein_derivative(nudge(u)), bernstein(nudge(v)));
+            let dv = self.weighted_sum(bernstein(nudge(u)), bernstein_derivative(nudge(v)));
+
+            return du.cross(dv).normalize();
+        }
+
+        normal.normalize()
+    }
+
+    /// Reads patches in the format of Newell's teapot data: the number of
+    /// patches followed by the 16 indices of the control points of each,
+    /// counted from 1, and then the number of points followed by their
+    /// coordinates. Numbers are separated by commas or whitespace.
+    pub fn read(reader: &mut impl Read) -> IoResult<Vec<Self>> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+
+        let mut numbers = text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty());
+        let mut next = |what: &str| {
+            numbers
+                .next()
+                .ok_or_else(|| {
+                    IoError::new(ErrorKind::UnexpectedEof, "the patch file is truncated")
+                })
+                .and_then(|n| {
+                    n.parse::<F>()
+                        .map_err(|_| invalid_data(&format!("malformed {what} in patch file")))
+                })
+        };
+
+        // the counts come from the file, so nothing is allocated up front
+        // and a count too big for the data runs out of numbers instead
+        let index_count = count(next("patch count")?)?
+            .checked_mul(16)
+            .ok_or_else(|| invalid_data("too many patches in patch file"))?;
+        let mut indices = Vec::new();
+        for _ in 0..index_count {
+            indices.push(count(next("index")?)?);
+        }
+
+        let point_count = count(next("point count")?)?;
+        let mut points = Vec::new();
+        for _ in 0..point_count {
+            points.push(pt(
+                next("coordinate")?,
+                next("coordinate")?,
+                next("coordinate")?,
+            ));
+        }
+
+        indices
+            .chunks_exact(16)
+            .map(|chunk| {
+                let mut patch = [v(0, 0, 0); 16];
+
+                for (control, &index) in patch.iter_mut().zip(chunk) {
+                    *control = *index
+                        .checked_sub(1)
+                        .and_then(|i| points.get(i))
+                        .ok_or_else(|| invalid_data("a patch refers to a missing point"))?;
+                }
+
+                Ok(Self::new(patch))
+            })
+            .collect()
+    }
+}
+
+fn invalid_data(message: &str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, message)
+}
+
+// counts and indices are written like any other number
+fn count(n: F) -> IoResult<usize> {
+    if n >= 0.0 && n.fract() == 0.0 {
+        Ok(n as usize)
+    } else {
+        Err(invalid_data("expected a whole number in patch file"))
+    }
+}
+
+impl Mesh {
+    /// Tessellates every patch into a grid of `divisions` by `divisions`
+    /// pairs of triangles, with the normals of the patch at their corners.
+    /// Patches that share an edge share the vertices along it too.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `divisions` is zero.
+    #[must_use]
+    pub fn from_patches(patches: &[BezierPatch], divisions: usize) -> Self {
+        assert!(divisions > 0, "a patch needs at least one division");
+
+        let n = divisions;
+        let mut vertices = vec![];
+        let mut normals = vec![];
+        let mut triangles = Vec::with_capacity(patches.len() * n * n * 2);
+
+        // a point on a shared edge is computed from the control points of
+        // each patch in turn, which can round differently, so points are
+        // merged when they agree to within a tiny fraction of the patches'
+        // size
+        let extent = patches
+            .iter()
+            .flat_map(|patch| patch.points)
+            .map(|p| p.x.abs().max(p.y.abs()).max(p.z.abs()))
+            .fold(F::MIN_POSITIVE, F::max);
+        let quantum = extent * 1e-9;
+        let mut seen = HashMap::new();
+
+        for patch in patches {
+            let mut grid = Vec::with_capacity((n + 1) * (n + 1));
+
+            for row in 0..=n {
+                for column in 0..=n {
+                    let (u, v) = (column as F / n as F, row as F / n as F);
+                    let point = patch.point_at(u, v);
+                    let key = [point.x, point.y, point.z].map(|c| (c / quantum).round() as i64);
+
+                    grid.push(*seen.entry(key).or_insert_with(|| {
+                        vertices.push(point);
+                        normals.push(patch.normal_at(u, v));
+                        vertices.len() - 1
+                    }));
+                }
+            }
+
+            for row in 0..n {
+                for column in 0..n {
+                    let i = row * (n + 1) + column;
+
+                    // where control points meet, like at the top of the lid,
+                    // some triangles collapse into a line
+                    for [a, b, c] in [[i, i + 1, i + n + 2], [i, i + n + 2, i + n + 1]] {
+                        let triangle = [grid[a], grid[b], grid[c]];
+
+                        if triangle[0] != triangle[1]
+                            && triangle[1] != triangle[2]
+                            && triangle[0] != triangle[2]
+                        {
+                            triangles.push(triangle);
+                        }
+                    }
+                }
+            }
+        }
+
+        Mesh::new(vertices, triangles).normals(normals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    // a flat patch over the square from 0 to 3 in x and y
+    fn flat() -> BezierPatch {
+        let mut points = [v(0, 0, 0); 16];
+        for (i, point) in points.iter_mut().enumerate() {
+            *point = pt((i % 4) as F, (i / 4) as F, 0);
+        }
+
+        BezierPatch::new(points)
+    }
+
+    const FLAT: &str = "1
+1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16
+16
+0,0,0
+1,0,0
+2,0,0
+3,0,0
+0,1,0
+1,1,0
+2,1,0
+3,1,0
+0,2,0
+1,2,0
+2,2,0
+3,2,0
+0,3,0
+1,3,0
+2,3,0
+3,3,0
+";
+
+    #[test]
+    fn the_weights_of_a_cubic_bezier() {
+        assert_eq!(bernstein(0.0), [1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(bernstein(1.0), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(bernstein(0.5), [0.125, 0.375, 0.375, 0.125]);
+    }
+
+    #[test]
+    fn points_on_a_cubic_bezier() {
+        let points = [pt(0, 0, 0), pt(0, 1, 0), pt(1, 1, 0), pt(1, 0, 0)];
+
+        assert_fuzzy_eq!(cubic(points, 0.0), pt(0, 0, 0));
+        assert_fuzzy_eq!(cubic(points, 0.5), pt(0.5, 0.75, 0));
+        assert_fuzzy_eq!(cubic(points, 1.0), pt(1, 0, 0));
+    }
+
+    #[test]
+    fn points_and_normals_on_a_patch() {
+        let patch = flat();
+
+        assert_fuzzy_eq!(patch.point_at(0.0, 0.0), pt(0, 0, 0));
+        assert_fuzzy_eq!(patch.point_at(0.5, 0.5), pt(1.5, 1.5, 0));
+        assert_fuzzy_eq!(patch.point_at(1.0, 0.25), pt(3, 0.75, 0));
+        assert_fuzzy_eq!(patch.normal_at(0.3, 0.6), v(0, 0, 1));
+    }
+
+    #[test]
+    fn the_normal_where_a_patch_comes_to_a_point() {
+        // the first row of control points all meet at the origin, like at
+        // the top of a lid
+        let mut patch = flat();
+        for point in &mut patch.points[..4] {
+            *point = pt(1.5, 0, 0);
+        }
+
+        assert_fuzzy_eq!(patch.normal_at(0.5, 0.0), v(0, 0, 1));
+    }
+
+    #[test]
+    fn reading_patches() {
+        let patches = BezierPatch::read(&mut FLAT.as_bytes()).unwrap();
+
+        assert_eq!(patches, vec![flat()]);
+    }
+
+    #[test]
+    fn reading_a_broken_patch_file() {
+        for (text, kind) in [
+            ("", ErrorKind::UnexpectedEof),
+            ("1\n1,2,3", ErrorKind::UnexpectedEof),
+            ("one\n", ErrorKind::InvalidData),
+            ("1.5\n", ErrorKind::InvalidData),
+            ("1e30", ErrorKind::InvalidData),
+            ("1e12", ErrorKind::UnexpectedEof),
+            ("0\n1e12", ErrorKind::UnexpectedEof),
+            (
+                &FLAT.replace("16\n0,0,0", "16\nx,0,0"),
+                ErrorKind::InvalidData,
+            ),
+            (&FLAT.replacen("1,2,3", "0,2,3", 1), ErrorKind::InvalidData),
+            (
+                &FLAT.replacen("14,15,16", "14,15,17", 1),
+                ErrorKind::InvalidData,
+            ),
+        ] {
+            let error = BezierPatch::read(&mut text.as_bytes()).unwrap_err();
+
+            assert_eq!(error.kind(), kind);
+        }
+    }
+
+    #[test]
+    fn tessellating_patches_into_a_mesh() {
+        let mesh = Mesh::from_patches(&[flat()], 4);
+
+        assert_eq!(mesh.vertices().len(), 25);
+        assert_eq!(mesh.triangles().len(), 32);
+
+        let xs = mesh.local_intersect(ray(pt(1.2, 2.9, 5), v(0, 0, -1)));
+        assert_eq!(xs.len(), 1);
+        assert_fuzzy_eq!(xs[0].t, 5.0);
+
+        let n = mesh.local_normal_at(pt(1.2, 2.9, 0)).normalize();
+        assert_fuzzy_eq!(n, v(0, 0, 1));
+    }
+
+    #[test]
+    fn patches_sharing_an_edge_share_its_vertices() {
+        // the neighbour runs the other way up, so the shared edge is
+        // evaluated with the weights reversed
+        let mut points = [v(0, 0, 0); 16];
+        for (i, point) in points.iter_mut().enumerate() {
+            *point = pt(3.0 + (i % 4) as F, 3.0 - (i / 4) as F, 0);
+        }
+        let neighbour = BezierPatch::new(points);
+
+        let mesh = Mesh::from_patches(&[flat(), neighbour], 7);
+
+        assert_eq!(mesh.vertices().len(), 2 * 64 - 8);
+        assert_eq!(mesh.triangles().len(), 2 * 98);
+    }
+
+    #[test]
+    fn collapsed_triangles_are_left_out() {
+        // all of the bottom row meets in a single point, like at the top of
+        // the teapot's lid
+        let mut points = flat().points;
+        for point in &mut points[..4] {
+            *point = pt(1.5, 0, 0);
+        }
+
+        let mesh = Mesh::from_patches(&[BezierPatch::new(points)], 4);
+
+        assert_eq!(mesh.vertices().len(), 21);
+        assert_eq!(mesh.triangles().len(), 28);
+    }
+}
@@ -1,10 +1,21 @@
-use crate::{v, FuzzyEq, Intersection, Props, Ray, Shape, Tuple, EPSILON, F, INFINITY};
-
+use crate::{
+    pt, v, BoundingBox, FuzzyEq, Intersection, Props, Ray, Shape, Tuple, EPSILON, F, INFINITY,
+};
+
+/// A double-napped cone around the y axis with its apex at the origin. A
+/// `thickness` makes it hollow, with an inner wall that much closer to the
+/// axis.
+///
+/// Capping a hollow cone closes it off with a flat disk, which has no
+/// thickness of its own: rays refracted through it never leave the solid
+/// they entered, so leave the ends of glass cones open.
 #[derive(Debug)]
 pub struct Cone {
     minimum: F,
     maximum: F,
-    closed: bool,
+    bottom_cap: bool,
+    top_cap: bool,
+    thickness: F,
     props: Props,
 }
 
@@ -23,64 +34,53 @@ impl Cone {
         self
     }
 
+    /// Closes or opens both ends.
     #[must_use]
-    pub fn closed(mut self, closed: bool) -> Self {
-        self.closed = closed;
-
-        self
+    pub fn closed(self, closed: bool) -> Self {
+        self.bottom_cap(closed).top_cap(closed)
     }
-}
 
-impl Default for Cone {
-    fn default() -> Self {
-        Self {
-            minimum: -INFINITY,
-            maximum: INFINITY,
-            closed: false,
-            props: Props::default(),
-        }
-    }
-}
-
-fn check_cap(ray: Ray, time: F, radius: F) -> bool {
-    let x = ray.origin.x + time * ray.direction.x;
-    let z = ray.origin.z + time * ray.direction.z;
-    x.powi(2) + z.powi(2) <= radius
-}
+    #[must_use]
+    pub fn bottom_cap(mut self, bottom_cap: bool) -> Self {
+        self.bottom_cap = bottom_cap;
 
-fn intersect_caps(cone: &Cone, ray: Ray) -> Vec<F> {
-    let mut xs = vec![];
-    if !cone.closed || ray.direction.y.fuzzy_eq(&0.0) {
-        return xs;
+        self
     }
 
-    // Check for an intersection with the lower end cap by intersecting
-    // the ray with the plane at y=cylinder.minimum
-    let t = (cone.minimum - ray.origin.y) / ray.direction.y;
-    if check_cap(ray, t, cone.minimum.abs()) {
-        xs.push(t);
-    }
+    #[must_use]
+    pub fn top_cap(mut self, top_cap: bool) -> Self {
+        self.top_cap = top_cap;
 
-    // Check for an intersection with the upper end cap by intersecting
-    // the ray with the plane at y=cylinder.maximum
-    let t = (cone.maximum - ray.origin.y) / ray.direction.y;
-    if check_cap(ray, t, cone.maximum.abs()) {
-        xs.push(t);
+        self
     }
 
-    xs
-}
+    /// Hollows the cone out, leaving a wall `thickness` wide measured
+    /// across from the axis. Near the apex, where the cone is narrower than
+    /// that, it stays solid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `thickness` is negative or not finite.
+    #[must_use]
+    pub fn thickness(mut self, thickness: impl Into<F>) -> Self {
+        let thickness = thickness.into();
+        assert!(
+            thickness >= 0.0 && thickness.is_finite(),
+            "the thickness of a wall can't be negative"
+        );
+        self.thickness = thickness;
 
-impl Shape for Cone {
-    fn props(&self) -> &Props {
-        &self.props
+        self
     }
 
-    fn props_mut(&mut self) -> &mut Props {
-        &mut self.props
+    fn is_hollow(&self) -> bool {
+        self.thickness > 0.0
     }
 
-    fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
+    // Intersects the ray with the cone moved up the y axis by `shift`. The
+    // inner wall of a hollow cone is made of the upper half of the cone
+    // moved up and the lower half moved down.
+    fn intersect_wall(&self, ray: Ray, shift: F) -> Vec<F> {
         let Tuple {
             x: ox,
             y: oy,
@@ -93,6 +93,7 @@ impl Shape for Cone {
             z: dz,
             ..
         } = ray.direction;
+        let oy = oy - shift;
 
         let a = dx.powi(2) - dy.powi(2) + dz.powi(2);
         let b = 2.0 * ox * dx - 2.0 * oy * dy + 2.0 * oz * dz;
@@ -105,10 +106,8 @@ impl Shape for Cone {
             return vec![];
         }
 
-        let mut xs = vec![];
-        if a_zero {
-            let t = -c / (2.0 * b);
-            xs.push(self.intersection(t));
+        let ts = if a_zero {
+            vec![-c / (2.0 * b)]
         } else {
             let disc = b.powi(2) - 4.0 * a * c;
             if disc < 0.0 {
@@ -121,37 +120,130 @@ impl Shape for Cone {
                 (t0, t1) = (t1, t0);
             }
 
-            let y0 = ray.origin.y + t0 * ray.direction.y;
-            if self.minimum < y0 && y0 < self.maximum {
-                xs.push(self.intersection(t0));
-            }
+            vec![t0, t1]
+        };
 
-            let y1 = ray.origin.y + t1 * ray.direction.y;
-            if self.minimum < y1 && y1 < self.maximum {
-                xs.push(self.intersection(t1));
-            }
+        ts.into_iter()
+            .filter(|t| {
+                let y = ray.origin.y + t * ray.direction.y;
+                self.minimum < y && y < self.maximum && (y - shift) * shift >= 0.0
+            })
+            .collect()
+    }
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Self {
+            minimum: -INFINITY,
+            maximum: INFINITY,
+            bottom_cap: false,
+            top_cap: false,
+            thickness: 0.0,
+            props: Props::default(),
         }
+    }
+}
 
-        let caps_xs = intersect_caps(self, ray)
-            .into_iter()
-            .map(|t| self.intersection(t));
-        xs.extend(caps_xs);
+fn check_cap(ray: Ray, time: F, hole: F, radius: F) -> bool {
+    let x = ray.origin.x + time * ray.direction.x;
+    let z = ray.origin.z + time * ray.direction.z;
+    let dist = x.powi(2) + z.powi(2);
 
-        xs
+    hole.powi(2) <= dist && dist <= radius.powi(2)
+}
+
+fn intersect_caps(cone: &Cone, ray: Ray) -> Vec<F> {
+    let mut xs = vec![];
+    if ray.direction.y.fuzzy_eq(&0.0) {
+        return xs;
+    }
+
+    for (y, closed) in [
+        (cone.minimum, cone.bottom_cap),
+        (cone.maximum, cone.top_cap),
+    ] {
+        // a closed end is a full disk, while an open end of a hollow cone
+        // still needs a ring between its walls
+        let hole = if closed {
+            0.0
+        } else if cone.is_hollow() {
+            (y.abs() - cone.thickness).max(0.0)
+        } else {
+            continue;
+        };
+
+        // Check for an intersection with the end by intersecting the ray
+        // with the plane at that height
+        let t = (y - ray.origin.y) / ray.direction.y;
+        if check_cap(ray, t, hole, y.abs()) {
+            xs.push(t);
+        }
+    }
+
+    xs
+}
+
+impl Shape for Cone {
+    fn props(&self) -> &Props {
+        &self.props
+    }
+
+    fn props_mut(&mut self) -> &mut Props {
+        &mut self.props
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        let mut xs = self.intersect_wall(ray, 0.0);
+
+        if self.is_hollow() {
+            xs.extend(self.intersect_wall(ray, self.thickness));
+            xs.extend(self.intersect_wall(ray, -self.thickness));
+        }
+
+        xs.extend(intersect_caps(self, ray));
+
+        xs.into_iter().map(|t| self.intersection(t)).collect()
     }
 
     fn local_normal_at(&self, point @ Tuple { x, y, z, .. }: Tuple) -> Tuple {
         let dist = x.powi(2) + z.powi(2);
-        if dist < 1.0 && y > self.maximum - EPSILON {
+        if dist < self.maximum.powi(2) && y > self.maximum - EPSILON {
             v(0, 1, 0)
-        } else if dist < 1.0 && y < self.minimum + EPSILON {
+        } else if dist < self.minimum.powi(2) && y < self.minimum + EPSILON {
             v(0, -1, 0)
         } else {
-            let y = (x.powi(2) + z.powi(2)).sqrt();
-            let y = if point.y > 0.0 { -y } else { y };
-            v(x, y, z)
+            let radius = dist.sqrt();
+            let y = if point.y > 0.0 { -radius } else { radius };
+
+            if self.is_hollow() && radius < point.y.abs() - self.thickness / 2.0 {
+                // the inner wall faces the axis
+                v(-x, -y, -z)
+            } else {
+                v(x, y, z)
+            }
         }
     }
+
+    fn local_tangent_at(&self, point: Tuple) -> Tuple {
+        let normal = self.local_normal_at(point);
+
+        // the caps face straight up or down
+        if normal.x == 0.0 && normal.z == 0.0 {
+            return v(1, 0, 0);
+        }
+
+        v(-point.z, 0, point.x).normalize()
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let radius = self.minimum.abs().max(self.maximum.abs());
+
+        BoundingBox::new(
+            pt(-radius, self.minimum, -radius),
+            pt(radius, self.maximum, radius),
+        )
+    }
 }
 
 #[cfg(test)]
@@ -194,7 +286,8 @@ mod tests {
         let shape = Cone {
             minimum: -0.5,
             maximum: 0.5,
-            closed: true,
+            bottom_cap: true,
+            top_cap: true,
             ..Default::default()
         };
 
@@ -224,4 +317,115 @@ mod tests {
             assert_eq!(n, normal);
         }
     }
+
+    #[test]
+    fn closing_only_one_end_of_a_cone() {
+        let shape = Cone::default().minimum(1).maximum(2).top_cap(true);
+        let r = ray(pt(0, 3, 0), v(0, -1, 0));
+
+        let xs = shape.local_intersect(r);
+        assert_eq!(xs.len(), 1);
+        assert_fuzzy_eq!(xs[0].t, 1.0);
+
+        let shape = shape.top_cap(false).bottom_cap(true);
+        let xs = shape.local_intersect(r);
+        assert_eq!(xs.len(), 1);
+        assert_fuzzy_eq!(xs[0].t, 2.0);
+    }
+
+    #[test]
+    fn the_caps_of_a_cone_grow_with_its_height() {
+        let shape = Cone::default().minimum(1).maximum(2).closed(true);
+
+        let xs = shape.local_intersect(ray(pt(1.5, 3, 0), v(0, -1, 0)));
+
+        let ts = xs.iter().map(|i| i.t).collect::<Vec<_>>();
+        assert_eq!(ts.len(), 2);
+        assert!(ts.iter().any(|t| t.fuzzy_eq(&1.0)));
+        assert!(ts.iter().any(|t| t.fuzzy_eq(&1.5)));
+    }
+
+    #[test]
+    fn intersecting_the_walls_of_a_hollow_cone() {
+        let shape = Cone::default().minimum(0).maximum(2).thickness(0.5);
+
+        let xs = shape.local_intersect(ray(pt(-5, 1, 0), v(1, 0, 0)));
+
+        let mut ts = xs.iter().map(|i| i.t).collect::<Vec<_>>();
+        ts.sort_by(F::total_cmp);
+        assert_eq!(ts.len(), 4);
+        for (t, expected) in ts.into_iter().zip([4.0, 4.5, 5.5, 6.0]) {
+            assert_fuzzy_eq!(t, expected);
+        }
+    }
+
+    #[test]
+    fn the_open_ends_of_a_hollow_cone_are_rings() {
+        let shape = Cone::default().minimum(1).maximum(2).thickness(0.5);
+
+        // down the hole
+        assert!(shape
+            .local_intersect(ray(pt(0, 3, 0), v(0, -1, 0)))
+            .is_empty());
+
+        // through the rim and out of the outer wall
+        let xs = shape.local_intersect(ray(pt(1.75, 3, 0), v(0, -1, 0)));
+        let mut ts = xs.iter().map(|i| i.t).collect::<Vec<_>>();
+        ts.sort_by(F::total_cmp);
+        assert_eq!(ts.len(), 2);
+        assert_fuzzy_eq!(ts[0], 1.0);
+        assert_fuzzy_eq!(ts[1], 1.25);
+    }
+
+    #[test]
+    fn the_normal_on_the_inside_of_a_hollow_cone() {
+        let shape = Cone::default().minimum(0).maximum(2).thickness(0.5);
+
+        assert_eq!(shape.local_normal_at(pt(0.5, 1, 0)), v(-0.5, 0.5, 0));
+        assert_eq!(shape.local_normal_at(pt(1, 1, 0)), v(1, -1, 0));
+        assert_eq!(shape.local_normal_at(pt(1.75, 2, 0)), v(0, 1, 0));
+    }
+
+    #[test]
+    fn the_tangent_on_a_cone() {
+        let shape = Cone::default();
+
+        assert_fuzzy_eq!(shape.local_tangent_at(pt(1, 1, 0)), v(0, 0, 1));
+        assert_fuzzy_eq!(shape.local_tangent_at(pt(0, -1, -1)), v(1, 0, 0));
+        assert_fuzzy_eq!(shape.local_tangent_at(pt(0, 0, 0)), v(1, 0, 0));
+    }
+
+    #[test]
+    fn the_bounds_of_a_cone() {
+        let cone = Cone::default().minimum(-5).maximum(3);
+
+        assert_eq!(cone.bounds(), BoundingBox::new(pt(-5, -5, -5), pt(5, 3, 5)));
+    }
+
+    #[test]
+    fn the_thickness_of_a_hollow_cone_is_not_negative() {
+        for thickness in [-0.1, INFINITY, F::NAN] {
+            assert!(std::panic::catch_unwind(|| Cone::default().thickness(thickness)).is_err());
+        }
+
+        assert_eq!(Cone::default().thickness(1.5).thickness, 1.5);
+    }
+
+    #[test]
+    fn a_hollow_cone_is_solid_where_it_is_narrower_than_its_wall() {
+        let shape = Cone::default().minimum(0).maximum(3).thickness(1.5);
+
+        // only the outer wall at a height of 1
+        let xs = shape.local_intersect(ray(pt(-5, 1, 0), v(1, 0, 0)));
+        assert_eq!(xs.len(), 2);
+
+        // both walls at a height of 2, half a unit apart
+        let xs = shape.local_intersect(ray(pt(-5, 2, 0), v(1, 0, 0)));
+        let mut ts = xs.iter().map(|i| i.t).collect::<Vec<_>>();
+        ts.sort_by(F::total_cmp);
+        assert_eq!(ts.len(), 4);
+        for (t, expected) in ts.into_iter().zip([3.0, 4.5, 5.5, 7.0]) {
+            assert_fuzzy_eq!(t, expected);
+        }
+    }
 }
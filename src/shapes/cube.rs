@@ -1,4 +1,4 @@
-use crate::{v, Intersection, Props, Ray, Shape, Tuple, F};
+use crate::{pt, v, BoundingBox, Intersection, Props, Ray, Shape, Tuple, F};
 
 #[derive(Debug, Default)]
 pub struct Cube {
@@ -28,7 +28,7 @@ impl Shape for Cube {
         &mut self.props
     }
 
-    fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
         let (xtmin, xtmax) = check_axis(ray.origin.x, ray.direction.x);
         let (ytmin, ytmax) = check_axis(ray.origin.y, ray.direction.y);
         let (ztmin, ztmax) = check_axis(ray.origin.z, ray.direction.z);
@@ -57,6 +57,25 @@ impl Shape for Cube {
 
         v(0.0, 0.0, z)
     }
+
+    fn local_tangent_at(&self, point: Tuple) -> Tuple {
+        // follow u of the cube mapping on every face
+        let normal = self.local_normal_at(point);
+
+        if normal.x > 0.0 {
+            v(0, 0, -1)
+        } else if normal.x < 0.0 {
+            v(0, 0, 1)
+        } else if normal.z < 0.0 {
+            v(-1, 0, 0)
+        } else {
+            v(1, 0, 0)
+        }
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(pt(-1, -1, -1), pt(1, 1, 1))
+    }
 }
 
 #[cfg(test)]
@@ -126,4 +145,20 @@ mod tests {
             assert_eq!(normal, n);
         }
     }
+
+    #[test]
+    fn the_tangent_on_a_surface_of_a_cube() {
+        let c = Cube::default();
+
+        for (point, tangent) in [
+            (pt(1, 0.5, -0.8), v(0, 0, -1)),
+            (pt(-1, -0.2, 0.9), v(0, 0, 1)),
+            (pt(-0.4, 1, -0.1), v(1, 0, 0)),
+            (pt(0.3, -1, -0.7), v(1, 0, 0)),
+            (pt(-0.6, 0.3, 1), v(1, 0, 0)),
+            (pt(0.4, 0.4, -1), v(-1, 0, 0)),
+        ] {
+            assert_eq!(c.local_tangent_at(point), tangent);
+        }
+    }
 }
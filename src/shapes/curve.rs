@@ -0,0 +1,252 @@
+use super::bezier::cubic;
+use crate::roots::solve_quadratic;
+use crate::{v, BoundingBox, Intersection, Props, Ray, Shape, Tuple, EPSILON, F};
+
+/// A round strand of constant `radius` swept along a cubic Bézier curve,
+/// for hair, grass and the like. The curve is followed by `segments` straight
+/// pieces joined smoothly at their ends.
+#[derive(Debug)]
+pub struct Curve {
+    points: [Tuple; 4],
+    radius: F,
+    segments: usize,
+    polyline: Vec<Tuple>,
+    props: Props,
+}
+
+impl Curve {
+    #[must_use]
+    pub fn new(points: [Tuple; 4]) -> Self {
+        Self {
+            points,
+            radius: 0.05,
+            segments: 16,
+            polyline: vec![],
+            props: Props::default(),
+        }
+        .with_polyline()
+    }
+
+    #[must_use]
+    pub fn radius(mut self, radius: impl Into<F>) -> Self {
+        self.radius = radius.into();
+
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `segments` is zero.
+    #[must_use]
+    pub fn segments(mut self, segments: usize) -> Self {
+        assert!(segments > 0, "a curve needs at least one segment");
+        self.segments = segments;
+
+        self.with_polyline()
+    }
+
+    fn with_polyline(mut self) -> Self {
+        self.polyline = (0..=self.segments)
+            .map(|i| cubic(self.points, i as F / self.segments as F))
+            .collect();
+
+        self
+    }
+
+    fn closest_point(&self, point: Tuple) -> Tuple {
+        self.polyline
+            .windows(2)
+            .map(|segment| closest_on_segment(point, segment[0], segment[1]))
+            .min_by(|a, b| {
+                (point - *a)
+                    .magnitude()
+                    .total_cmp(&(point - *b).magnitude())
+            })
+            .unwrap_or(self.polyline[0])
+    }
+}
+
+fn closest_on_segment(point: Tuple, a: Tuple, b: Tuple) -> Tuple {
+    let ab = b - a;
+    let length = ab.dot(ab);
+
+    if length == 0.0 {
+        return a;
+    }
+
+    a + ab * ((point - a).dot(ab) / length).clamp(0.0, 1.0)
+}
+
+impl Shape for Curve {
+    fn props(&self) -> &Props {
+        &self.props
+    }
+
+    fn props_mut(&mut self) -> &mut Props {
+        &mut self.props
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        let r2 = self.radius.powi(2);
+        let mut candidates = vec![];
+
+        // the sides of every segment, without their ends
+        for segment in self.polyline.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            let axis = b - a;
+            let length = axis.magnitude();
+            if length == 0.0 {
+                continue;
+            }
+            let axis = axis / length;
+
+            let to_origin = ray.origin - a;
+            let d = ray.direction - axis * ray.direction.dot(axis);
+            let o = to_origin - axis * to_origin.dot(axis);
+
+            candidates.extend(
+                solve_quadratic(d.dot(d), 2.0 * o.dot(d), o.dot(o) - r2)
+                    .into_iter()
+                    .filter(|&t| {
+                        let along = (ray.position(t) - a).dot(axis);
+                        0.0 < along && along < length
+                    }),
+            );
+        }
+
+        // and the spheres rounding off the joints and the two ends
+        for &joint in &self.polyline {
+            let to_origin = ray.origin - joint;
+
+            candidates.extend(solve_quadratic(
+                ray.direction.dot(ray.direction),
+                2.0 * ray.direction.dot(to_origin),
+                to_origin.dot(to_origin) - r2,
+            ));
+        }
+
+        // only keep the hits that aren't buried inside a neighbouring piece
+        candidates.sort_by(F::total_cmp);
+        candidates.dedup_by(|a, b| (*a - *b).abs() < EPSILON);
+
+        candidates
+            .into_iter()
+            .filter(|&t| {
+                let point = ray.position(t);
+                (point - self.closest_point(point)).magnitude() > self.radius - EPSILON
+            })
+            .map(|t| self.intersection(t))
+            .collect()
+    }
+
+    fn local_normal_at(&self, point: Tuple) -> Tuple {
+        point - self.closest_point(point)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        // the curve never leaves the box around its control points
+        let mut bounds = BoundingBox::default();
+        let reach = v(self.radius, self.radius, self.radius);
+
+        for point in self.points {
+            bounds.add_point(point - reach);
+            bounds.add_point(point + reach);
+        }
+
+        bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    fn straight() -> Curve {
+        Curve::new([pt(-1, 0, 0), pt(-0.5, 0, 0), pt(0.5, 0, 0), pt(1, 0, 0)]).radius(0.25)
+    }
+
+    fn arch() -> Curve {
+        Curve::new([pt(-1, 0, 0), pt(-1, 1, 0), pt(1, 1, 0), pt(1, 0, 0)]).radius(0.25)
+    }
+
+    #[test]
+    fn a_curve_follows_its_control_points() {
+        let c = arch().segments(4);
+
+        assert_eq!(c.polyline.len(), 5);
+        assert_fuzzy_eq!(c.polyline[0], pt(-1, 0, 0));
+        assert_fuzzy_eq!(c.polyline[2], pt(0, 0.75, 0));
+        assert_fuzzy_eq!(c.polyline[4], pt(1, 0, 0));
+    }
+
+    #[test]
+    fn a_ray_intersects_the_side_of_a_curve() {
+        let c = straight();
+
+        let xs = c.local_intersect(ray(pt(0.1, 0, -5), v(0, 0, 1)));
+
+        assert_eq!(xs.len(), 2);
+        assert_fuzzy_eq!(xs[0].t, 4.75);
+        assert_fuzzy_eq!(xs[1].t, 5.25);
+    }
+
+    #[test]
+    fn a_ray_intersects_the_ends_of_a_curve() {
+        let c = straight();
+
+        let xs = c.local_intersect(ray(pt(-5, 0, 0), v(1, 0, 0)));
+
+        assert_eq!(xs.len(), 2);
+        assert_fuzzy_eq!(xs[0].t, 3.75);
+        assert_fuzzy_eq!(xs[1].t, 6.25);
+    }
+
+    #[test]
+    fn a_ray_misses_a_curve() {
+        let c = arch();
+
+        for (origin, direction) in [
+            (pt(0, 0.3, -5), v(0, 0, 1)),
+            (pt(1.5, 5, 0), v(0, -1, 0)),
+            (pt(-5, 1.1, 0), v(1, 0, 0)),
+        ] {
+            assert!(c.local_intersect(ray(origin, direction)).is_empty());
+        }
+    }
+
+    #[test]
+    fn a_ray_intersects_a_bent_curve() {
+        let c = arch();
+
+        let xs = c.local_intersect(ray(pt(0, 5, 0), v(0, -1, 0)));
+
+        assert_eq!(xs.len(), 2);
+        assert_fuzzy_eq!(xs[0].t, 4.0);
+        assert!((xs[1].t - 4.5).abs() < 0.01);
+
+        // down through both legs
+        let xs = c.local_intersect(ray(pt(-5, 0.2, 0), v(1, 0, 0)));
+        assert_eq!(xs.len(), 4);
+    }
+
+    #[test]
+    fn the_normal_on_a_curve() {
+        let c = straight();
+
+        assert_fuzzy_eq!(c.local_normal_at(pt(0, 0.25, 0)).normalize(), v(0, 1, 0));
+        assert_fuzzy_eq!(
+            c.local_normal_at(pt(0.3, 0, -0.25)).normalize(),
+            v(0, 0, -1)
+        );
+        assert_fuzzy_eq!(c.local_normal_at(pt(1.25, 0, 0)).normalize(), v(1, 0, 0));
+    }
+
+    #[test]
+    fn the_bounds_of_a_curve() {
+        assert_eq!(
+            arch().bounds(),
+            BoundingBox::new(pt(-1.25, -0.25, -0.25), pt(1.25, 1.25, 0.25))
+        );
+    }
+}
@@ -1,10 +1,20 @@
-use crate::{v, FuzzyEq, Intersection, Props, Ray, Shape, Tuple, EPSILON, F, INFINITY};
-
+use crate::{
+    pt, v, BoundingBox, FuzzyEq, Intersection, Props, Ray, Shape, Tuple, EPSILON, F, INFINITY,
+};
+
+/// A cylinder of radius 1 around the y axis. A `thickness` between 0 and 1
+/// makes it a tube with a hole of radius `1 - thickness` down the middle.
+///
+/// A cap on a tube is a disk without any depth, so the tube's solid isn't
+/// closed at that end and the material it refracts into is never left
+/// again. Tubes of glass should keep their ends open.
 #[derive(Debug)]
 pub struct Cylinder {
     minimum: F,
     maximum: F,
-    closed: bool,
+    bottom_cap: bool,
+    top_cap: bool,
+    thickness: F,
     props: Props,
 }
 
@@ -23,40 +33,111 @@ impl Cylinder {
         self
     }
 
+    /// Closes or opens both ends.
+    #[must_use]
+    pub fn closed(self, closed: bool) -> Self {
+        self.bottom_cap(closed).top_cap(closed)
+    }
+
+    #[must_use]
+    pub fn bottom_cap(mut self, bottom_cap: bool) -> Self {
+        self.bottom_cap = bottom_cap;
+
+        self
+    }
+
+    #[must_use]
+    pub fn top_cap(mut self, top_cap: bool) -> Self {
+        self.top_cap = top_cap;
+
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `thickness` isn't at least 0 and less than 1.
     #[must_use]
-    pub fn closed(mut self, closed: bool) -> Self {
-        self.closed = closed;
+    pub fn thickness(mut self, thickness: impl Into<F>) -> Self {
+        let thickness = thickness.into();
+        assert!(
+            (0.0..1.0).contains(&thickness),
+            "the thickness of a wall must be at least 0 and less than 1"
+        );
+        self.thickness = thickness;
 
         self
     }
+
+    fn is_hollow(&self) -> bool {
+        self.thickness > 0.0
+    }
+
+    fn inner_radius(&self) -> F {
+        1.0 - self.thickness
+    }
+
+    fn intersect_wall(&self, ray: Ray, radius: F) -> Vec<F> {
+        let a = ray.direction.x.powi(2) + ray.direction.z.powi(2);
+        let b = 2.0 * ray.origin.x * ray.direction.x + 2.0 * ray.origin.z * ray.direction.z;
+        let c = ray.origin.x.powi(2) + ray.origin.z.powi(2) - radius.powi(2);
+        let disc = b.powi(2) - 4.0 * a * c;
+
+        // ray does not intersect the cylinder
+        if disc < 0.0 {
+            return vec![];
+        }
+
+        let mut t0 = (-b - F::sqrt(disc)) / (2.0 * a);
+        let mut t1 = (-b + F::sqrt(disc)) / (2.0 * a);
+        if t0 > t1 {
+            (t0, t1) = (t1, t0);
+        }
+
+        [t0, t1]
+            .into_iter()
+            .filter(|t| {
+                let y = ray.origin.y + t * ray.direction.y;
+                self.minimum < y && y < self.maximum
+            })
+            .collect()
+    }
 }
 
-fn check_cap(ray: Ray, time: F) -> bool {
+fn check_cap(ray: Ray, time: F, hole: F) -> bool {
     let x = ray.origin.x + time * ray.direction.x;
     let z = ray.origin.z + time * ray.direction.z;
+    let dist = x.powi(2) + z.powi(2);
 
-    x.powi(2) + z.powi(2) <= 1.0
+    hole.powi(2) <= dist && dist <= 1.0
 }
 
 fn intersect_caps(cylinder: &Cylinder, ray: Ray) -> Vec<F> {
     let mut xs = vec![];
 
-    if !cylinder.closed || ray.direction.y.fuzzy_eq(&0.0) {
+    if ray.direction.y.fuzzy_eq(&0.0) {
         return xs;
     }
 
-    // Check for an intersection with the lower end cap by intersecting
-    // the ray with the plane at y=cylinder.minimum
-    let t = (cylinder.minimum - ray.origin.y) / ray.direction.y;
-    if check_cap(ray, t) {
-        xs.push(t);
-    }
+    for (y, closed) in [
+        (cylinder.minimum, cylinder.bottom_cap),
+        (cylinder.maximum, cylinder.top_cap),
+    ] {
+        // a closed end is a full disk, while an open end of a tube still
+        // needs a ring between its walls
+        let hole = if closed {
+            0.0
+        } else if cylinder.is_hollow() {
+            cylinder.inner_radius()
+        } else {
+            continue;
+        };
 
-    // Check for an intersection with the upper end cap by intersecting
-    // the ray with the plane at y=cylinder.maximum
-    let t = (cylinder.maximum - ray.origin.y) / ray.direction.y;
-    if check_cap(ray, t) {
-        xs.push(t);
+        // Check for an intersection with the end by intersecting the ray
+        // with the plane at that height
+        let t = (y - ray.origin.y) / ray.direction.y;
+        if check_cap(ray, t, hole) {
+            xs.push(t);
+        }
     }
 
     xs
@@ -67,7 +148,9 @@ impl Default for Cylinder {
         Self {
             minimum: -INFINITY,
             maximum: INFINITY,
-            closed: false,
+            bottom_cap: false,
+            top_cap: false,
+            thickness: 0.0,
             props: Props::default(),
         }
     }
@@ -82,42 +165,16 @@ impl Shape for Cylinder {
         &mut self.props
     }
 
-    fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
-        let a = ray.direction.x.powi(2) + ray.direction.z.powi(2);
-        let b = 2.0 * ray.origin.x * ray.direction.x + 2.0 * ray.origin.z * ray.direction.z;
-        let c = ray.origin.x.powi(2) + ray.origin.z.powi(2) - 1.0;
-        let disc = b.powi(2) - 4.0 * a * c;
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        let mut xs = self.intersect_wall(ray, 1.0);
 
-        // ray does not intersect the cylinder
-        if disc < 0.0 {
-            return vec![];
+        if self.is_hollow() {
+            xs.extend(self.intersect_wall(ray, self.inner_radius()));
         }
 
-        let mut t0 = (-b - F::sqrt(disc)) / (2.0 * a);
-        let mut t1 = (-b + F::sqrt(disc)) / (2.0 * a);
-        if t0 > t1 {
-            (t0, t1) = (t1, t0);
-        }
+        xs.extend(intersect_caps(self, ray));
 
-        let mut xs = vec![];
-
-        let y0 = ray.origin.y + t0 * ray.direction.y;
-        if self.minimum < y0 && y0 < self.maximum {
-            xs.push(self.intersection(t0));
-        }
-
-        let y1 = ray.origin.y + t1 * ray.direction.y;
-        if self.minimum < y1 && y1 < self.maximum {
-            xs.push(self.intersection(t1));
-        }
-
-        let cap_xs = intersect_caps(self, ray)
-            .into_iter()
-            .map(|t| self.intersection(t));
-
-        xs.extend(cap_xs);
-
-        xs
+        xs.into_iter().map(|t| self.intersection(t)).collect()
     }
 
     fn local_normal_at(&self, Tuple { x, y, z, .. }: Tuple) -> Tuple {
@@ -127,10 +184,28 @@ impl Shape for Cylinder {
             v(0, 1, 0)
         } else if dist < 1.0 && y < self.minimum + EPSILON {
             v(0, -1, 0)
+        } else if self.is_hollow() && dist.sqrt() < 1.0 - self.thickness / 2.0 {
+            // the inner wall faces the axis
+            v(-x, 0.0, -z)
         } else {
             v(x, 0.0, z)
         }
     }
+
+    fn local_tangent_at(&self, point: Tuple) -> Tuple {
+        let normal = self.local_normal_at(point);
+
+        // the caps face straight up or down
+        if normal.x == 0.0 && normal.z == 0.0 {
+            return v(1, 0, 0);
+        }
+
+        v(-point.z, 0, point.x).normalize()
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(pt(-1, self.minimum, -1), pt(1, self.maximum, 1))
+    }
 }
 
 #[cfg(test)]
@@ -226,7 +301,8 @@ mod tests {
     fn the_default_closed_value() {
         let cyl = Cylinder::default();
 
-        assert!(!cyl.closed);
+        assert!(!cyl.bottom_cap);
+        assert!(!cyl.top_cap);
     }
 
     #[test]
@@ -234,7 +310,8 @@ mod tests {
         let cyl = Cylinder {
             minimum: 1.0,
             maximum: 2.0,
-            closed: true,
+            bottom_cap: true,
+            top_cap: true,
             ..Default::default()
         };
 
@@ -257,7 +334,8 @@ mod tests {
         let cyl = Cylinder {
             minimum: 1.0,
             maximum: 2.0,
-            closed: true,
+            bottom_cap: true,
+            top_cap: true,
             ..Default::default()
         };
 
@@ -274,4 +352,96 @@ mod tests {
             assert_eq!(n, normal);
         }
     }
+
+    #[test]
+    fn closing_only_one_end_of_a_cylinder() {
+        let cyl = Cylinder::default().minimum(1).maximum(2).bottom_cap(true);
+        let r = ray(pt(0, 3, 0), v(0, -1, 0));
+
+        let xs = cyl.local_intersect(r);
+        assert_eq!(xs.len(), 1);
+        assert_fuzzy_eq!(xs[0].t, 2.0);
+
+        let cyl = cyl.bottom_cap(false).top_cap(true);
+        let xs = cyl.local_intersect(r);
+        assert_eq!(xs.len(), 1);
+        assert_fuzzy_eq!(xs[0].t, 1.0);
+    }
+
+    #[test]
+    fn intersecting_the_walls_of_a_tube() {
+        let cyl = Cylinder::default().thickness(0.5);
+
+        let xs = cyl.local_intersect(ray(pt(0, 0, -5), v(0, 0, 1)));
+
+        let mut ts = xs.iter().map(|i| i.t).collect::<Vec<_>>();
+        ts.sort_by(F::total_cmp);
+        assert_eq!(ts, vec![4.0, 4.5, 5.5, 6.0]);
+    }
+
+    #[test]
+    fn the_open_ends_of_a_tube_are_rings() {
+        let cyl = Cylinder::default().minimum(1).maximum(2).thickness(0.5);
+
+        // down the hole
+        assert!(cyl
+            .local_intersect(ray(pt(0, 3, 0), v(0, -1, 0)))
+            .is_empty());
+
+        // through the rim at both ends
+        let xs = cyl.local_intersect(ray(pt(0.75, 3, 0), v(0, -1, 0)));
+        assert_eq!(xs.len(), 2);
+
+        // a closed end covers the hole
+        let cyl = cyl.bottom_cap(true);
+        let xs = cyl.local_intersect(ray(pt(0, 3, 0), v(0, -1, 0)));
+        assert_eq!(xs.len(), 1);
+        assert_fuzzy_eq!(xs[0].t, 2.0);
+    }
+
+    #[test]
+    fn the_normal_on_the_inside_of_a_tube() {
+        let cyl = Cylinder::default().minimum(1).maximum(2).thickness(0.5);
+
+        assert_eq!(cyl.local_normal_at(pt(0.5, 1.5, 0)), v(-0.5, 0, 0));
+        assert_eq!(cyl.local_normal_at(pt(0, 1.5, -1)), v(0, 0, -1));
+        assert_eq!(cyl.local_normal_at(pt(0.75, 2, 0)), v(0, 1, 0));
+    }
+
+    #[test]
+    fn the_tangent_on_a_cylinder() {
+        let cyl = Cylinder {
+            minimum: 1.0,
+            maximum: 2.0,
+            bottom_cap: true,
+            top_cap: true,
+            ..Default::default()
+        };
+
+        for (point, tangent) in [
+            (pt(1, 1.5, 0), v(0, 0, 1)),
+            (pt(0, 1.5, -1), v(1, 0, 0)),
+            (pt(0.5, 2, 0), v(1, 0, 0)),
+            (pt(0, 1, 0.5), v(1, 0, 0)),
+        ] {
+            assert_fuzzy_eq!(cyl.local_tangent_at(point), tangent);
+        }
+    }
+
+    #[test]
+    fn the_bounds_of_a_cylinder() {
+        let cyl = Cylinder::default().minimum(-5).maximum(3);
+
+        assert_eq!(cyl.bounds(), BoundingBox::new(pt(-1, -5, -1), pt(1, 3, 1)));
+        assert!(!Cylinder::default().bounds().is_finite());
+    }
+
+    #[test]
+    fn the_thickness_of_a_tube_is_less_than_1() {
+        for thickness in [-0.1, 1.0, 1.5] {
+            assert!(std::panic::catch_unwind(|| Cylinder::default().thickness(thickness)).is_err());
+        }
+
+        assert_eq!(Cylinder::default().thickness(0.999).thickness, 0.999);
+    }
 }
@@ -0,0 +1,152 @@
+use crate::{pt, v, BoundingBox, Intersection, Props, Ray, Shape, Tuple, EPSILON, F};
+
+/// A flat disk in the xz plane centered on the origin. With an
+/// `inner_radius` it becomes a ring with a hole in the middle.
+#[derive(Debug)]
+pub struct Disk {
+    radius: F,
+    inner_radius: F,
+    props: Props,
+}
+
+impl Disk {
+    #[must_use]
+    pub fn radius(mut self, radius: impl Into<F>) -> Self {
+        self.radius = radius.into();
+
+        self
+    }
+
+    #[must_use]
+    pub fn inner_radius(mut self, inner_radius: impl Into<F>) -> Self {
+        self.inner_radius = inner_radius.into();
+
+        self
+    }
+}
+
+impl Default for Disk {
+    fn default() -> Self {
+        Self {
+            radius: 1.0,
+            inner_radius: 0.0,
+            props: Props::default(),
+        }
+    }
+}
+
+impl Shape for Disk {
+    fn props(&self) -> &Props {
+        &self.props
+    }
+
+    fn props_mut(&mut self) -> &mut Props {
+        &mut self.props
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        if ray.direction.y.abs() < EPSILON {
+            return vec![];
+        }
+
+        let t = -ray.origin.y / ray.direction.y;
+
+        let x = ray.origin.x + t * ray.direction.x;
+        let z = ray.origin.z + t * ray.direction.z;
+        let distance = x.powi(2) + z.powi(2);
+
+        if distance > self.radius.powi(2) || distance < self.inner_radius.powi(2) {
+            return vec![];
+        }
+
+        vec![self.intersection(t)]
+    }
+
+    fn local_normal_at(&self, _point: Tuple) -> Tuple {
+        v(0, 1, 0)
+    }
+
+    fn local_tangent_at(&self, _point: Tuple) -> Tuple {
+        v(1, 0, 0)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            pt(-self.radius, 0, -self.radius),
+            pt(self.radius, 0, self.radius),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn the_default_disk() {
+        let d = Disk::default();
+
+        assert_eq!(d.radius, 1.0);
+        assert_eq!(d.inner_radius, 0.0);
+    }
+
+    #[test]
+    fn a_ray_intersects_a_disk() {
+        let d = Disk::default();
+
+        for (origin, direction, t) in [
+            (pt(0, 1, 0), v(0, -1, 0), 1.0),
+            (pt(0.5, -2, 0.5), v(0, 1, 0), 2.0),
+            (pt(0, 1, -1), v(0, -1, 0.5), 1.0),
+        ] {
+            let xs = d.local_intersect(ray(origin, direction));
+
+            assert_eq!(xs.len(), 1);
+            assert_fuzzy_eq!(xs[0].t, t);
+        }
+    }
+
+    #[test]
+    fn a_ray_misses_a_disk() {
+        let d = Disk::default();
+
+        for (origin, direction) in [
+            // parallel to the disk
+            (pt(0, 1, 0), v(0, 0, 1)),
+            // past its edge
+            (pt(1, 1, 1), v(0, -1, 0)),
+            (pt(0, 1, -3), v(0, -1, 1)),
+        ] {
+            let xs = d.local_intersect(ray(origin, direction));
+
+            assert!(xs.is_empty());
+        }
+    }
+
+    #[test]
+    fn a_ray_passes_through_the_hole_of_an_annulus() {
+        let d = Disk::default().radius(2).inner_radius(0.5);
+
+        assert!(d.local_intersect(ray(pt(0, 1, 0), v(0, -1, 0))).is_empty());
+        assert_eq!(d.local_intersect(ray(pt(1, 1, 0), v(0, -1, 0))).len(), 1);
+        assert!(d
+            .local_intersect(ray(pt(2.5, 1, 0), v(0, -1, 0)))
+            .is_empty());
+    }
+
+    #[test]
+    fn the_normal_of_a_disk_is_constant() {
+        let d = Disk::default();
+
+        assert_eq!(d.local_normal_at(pt(0, 0, 0)), v(0, 1, 0));
+        assert_eq!(d.local_normal_at(pt(0.5, 0, -0.5)), v(0, 1, 0));
+    }
+
+    #[test]
+    fn the_bounds_of_a_disk() {
+        let d = Disk::default().radius(3);
+
+        assert_eq!(d.bounds(), BoundingBox::new(pt(-3, 0, -3), pt(3, 0, 3)));
+    }
+}
@@ -0,0 +1,415 @@
+use super::mesh::intersect_triangle;
+use crate::{
+    pt, v, BoundingBox, Canvas, Intersection, Props, Ray, Shape, Tuple, EPSILON, F, INFINITY,
+};
+
+/// A terrain over the square from -1 to 1 in x and z, with its height given
+/// at the points of a regular grid. Every cell of the grid is split into two
+/// triangles, and rays walk through the cells they cross to find them.
+#[derive(Debug)]
+pub struct Heightfield {
+    columns: usize,
+    rows: usize,
+    heights: Vec<F>,
+    normals: Vec<Tuple>,
+    bounds: BoundingBox,
+    props: Props,
+}
+
+impl Heightfield {
+    /// Samples `height(x, z)` on a grid of `columns` by `rows` points.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer than two columns or rows.
+    #[must_use]
+    pub fn from_fn(columns: usize, rows: usize, height: impl Fn(F, F) -> F) -> Self {
+        assert!(
+            columns >= 2 && rows >= 2,
+            "a heightfield needs at least 2x2 points"
+        );
+
+        let mut heights = Vec::with_capacity(columns * rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                heights.push(height(
+                    grid_to_object(column as F, columns),
+                    grid_to_object(row as F, rows),
+                ));
+            }
+        }
+
+        let lowest = heights.iter().copied().fold(INFINITY, F::min);
+        let highest = heights.iter().copied().fold(-INFINITY, F::max);
+
+        let mut field = Self {
+            columns,
+            rows,
+            heights,
+            normals: vec![],
+            bounds: BoundingBox::new(pt(-1, lowest, -1), pt(1, highest, 1)),
+            props: Props::default(),
+        };
+        field.normals = field.vertex_normals();
+
+        field
+    }
+
+    /// Uses the brightness of every pixel as the height, from 0 for black
+    /// to 1 for white. The first row of pixels lies at z = -1. A canvas a
+    /// single pixel wide or high is stretched across the whole square.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the canvas is empty.
+    #[must_use]
+    pub fn from_canvas(canvas: &Canvas) -> Self {
+        let (width, height) = (canvas.width, canvas.height);
+        assert!(width > 0 && height > 0, "a heightfield needs some pixels");
+
+        // a lone row or column is repeated on both sides of the square
+        Self::from_fn(width.max(2), height.max(2), |x, z| {
+            let column = object_to_grid(x, width).round() as usize;
+            let row = object_to_grid(z, height).round() as usize;
+
+            canvas.pixel_at(column, row).luminance()
+        })
+    }
+
+    fn height(&self, column: usize, row: usize) -> F {
+        self.heights[row * self.columns + column]
+    }
+
+    fn vertex(&self, column: usize, row: usize) -> Tuple {
+        pt(
+            grid_to_object(column as F, self.columns),
+            self.height(column, row),
+            grid_to_object(row as F, self.rows),
+        )
+    }
+
+    // the normal at every grid point, from the slope to its neighbours
+    fn vertex_normals(&self) -> Vec<Tuple> {
+        let mut normals = Vec::with_capacity(self.heights.len());
+
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let (left, right) = (column.saturating_sub(1), (column + 1).min(self.columns - 1));
+                let (near, far) = (row.saturating_sub(1), (row + 1).min(self.rows - 1));
+
+                let dx = (self.height(right, row) - self.height(left, row))
+                    / (self.vertex(right, row).x - self.vertex(left, row).x);
+                let dz = (self.height(column, far) - self.height(column, near))
+                    / (self.vertex(column, far).z - self.vertex(column, near).z);
+
+                normals.push(v(-dx, 1, -dz).normalize());
+            }
+        }
+
+        normals
+    }
+
+    // The corners of the two triangles of a cell. Both start at the corner
+    // nearest to the origin of the grid and share the diagonal.
+    fn triangles(column: usize, row: usize) -> [[(usize, usize); 3]; 2] {
+        let (c0, c1, r0, r1) = (column, column + 1, row, row + 1);
+
+        [
+            [(c0, r0), (c1, r0), (c1, r1)],
+            [(c0, r0), (c1, r1), (c0, r1)],
+        ]
+    }
+
+    fn intersect_cell(&self, ray: Ray, column: usize, row: usize) -> Vec<F> {
+        Self::triangles(column, row)
+            .into_iter()
+            .filter_map(|[a, b, c]| {
+                intersect_triangle(
+                    ray,
+                    self.vertex(a.0, a.1),
+                    self.vertex(b.0, b.1),
+                    self.vertex(c.0, c.1),
+                )
+                .map(|(t, ..)| t)
+            })
+            .collect()
+    }
+}
+
+fn grid_to_object(index: F, count: usize) -> F {
+    -1.0 + 2.0 * index / (count - 1) as F
+}
+
+fn object_to_grid(coordinate: F, count: usize) -> F {
+    (coordinate + 1.0) / 2.0 * (count - 1) as F
+}
+
+impl Shape for Heightfield {
+    fn props(&self) -> &Props {
+        &self.props
+    }
+
+    fn props_mut(&mut self) -> &mut Props {
+        &mut self.props
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        let Some((enter, exit)) = self.bounds.intersection_range(ray) else {
+            return vec![];
+        };
+
+        let last_column = self.columns - 2;
+        let last_row = self.rows - 2;
+        let cell_width = 2.0 / (self.columns - 1) as F;
+        let cell_depth = 2.0 / (self.rows - 1) as F;
+
+        // the cell where the ray enters the grid
+        let start = ray.position(enter);
+        let mut column = object_to_grid(start.x, self.columns)
+            .floor()
+            .clamp(0.0, last_column as F) as usize;
+        let mut row = object_to_grid(start.z, self.rows)
+            .floor()
+            .clamp(0.0, last_row as F) as usize;
+
+        // where the ray crosses into the next column and row, and how far
+        // apart those crossings are
+        let next_crossing = |origin: F, direction: F, index: usize, size: F| {
+            if direction > 0.0 {
+                (-1.0 + (index + 1) as F * size - origin) / direction
+            } else if direction < 0.0 {
+                (-1.0 + index as F * size - origin) / direction
+            } else {
+                INFINITY
+            }
+        };
+        let mut next_x = next_crossing(ray.origin.x, ray.direction.x, column, cell_width);
+        let mut next_z = next_crossing(ray.origin.z, ray.direction.z, row, cell_depth);
+        let step_x = cell_width / ray.direction.x.abs();
+        let step_z = cell_depth / ray.direction.z.abs();
+
+        let mut xs = vec![];
+        let mut t = enter;
+
+        loop {
+            let cell_exit = next_x.min(next_z).min(exit);
+
+            for hit in self.intersect_cell(ray, column, row) {
+                // a hit on an edge is found by the triangles on both sides
+                let in_cell = t - EPSILON <= hit && hit <= cell_exit + EPSILON;
+                let seen = xs.iter().any(|&x: &F| (x - hit).abs() < EPSILON);
+
+                if in_cell && !seen {
+                    xs.push(hit);
+                }
+            }
+
+            if cell_exit >= exit {
+                break;
+            }
+
+            if next_x < next_z {
+                if ray.direction.x > 0.0 && column < last_column {
+                    column += 1;
+                } else if ray.direction.x < 0.0 && column > 0 {
+                    column -= 1;
+                } else {
+                    break;
+                }
+                t = next_x;
+                next_x += step_x;
+            } else {
+                if ray.direction.z > 0.0 && row < last_row {
+                    row += 1;
+                } else if ray.direction.z < 0.0 && row > 0 {
+                    row -= 1;
+                } else {
+                    break;
+                }
+                t = next_z;
+                next_z += step_z;
+            }
+        }
+
+        xs.into_iter().map(|t| self.intersection(t)).collect()
+    }
+
+    fn local_normal_at(&self, point: Tuple) -> Tuple {
+        // interpolate the normals at the corners of the triangle the point
+        // lies on, weighted by how close the point is to each of them
+        let x = object_to_grid(point.x, self.columns);
+        let z = object_to_grid(point.z, self.rows);
+        let column = x.floor().clamp(0.0, (self.columns - 2) as F);
+        let row = z.floor().clamp(0.0, (self.rows - 2) as F);
+        let (fx, fz) = (x - column, z - row);
+
+        let (column, row) = (column as usize, row as usize);
+        let normal = |(c, r): (usize, usize)| self.normals[r * self.columns + c];
+        let [below, above] = Self::triangles(column, row);
+
+        if fx >= fz {
+            let [a, b, c] = below;
+            normal(a) * (1.0 - fx) + normal(b) * (fx - fz) + normal(c) * fz
+        } else {
+            let [a, b, c] = above;
+            normal(a) * (1.0 - fz) + normal(b) * fx + normal(c) * (fz - fx)
+        }
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    fn ramp() -> Heightfield {
+        // rises from 0 at x = -1 to 1 at x = 1
+        Heightfield::from_fn(5, 5, |x, _| (x + 1.0) / 2.0)
+    }
+
+    #[test]
+    fn sampling_heights_from_a_function() {
+        let field = ramp();
+
+        assert_eq!(field.height(0, 0), 0.0);
+        assert_eq!(field.height(2, 3), 0.5);
+        assert_eq!(field.height(4, 1), 1.0);
+        assert_eq!(field.bounds(), BoundingBox::new(pt(-1, 0, -1), pt(1, 1, 1)));
+    }
+
+    #[test]
+    fn sampling_heights_from_a_canvas() {
+        let mut canvas = Canvas::new(3, 2);
+        canvas.write_pixel(2, 0, WHITE);
+        canvas.write_pixel(1, 1, color(0.5, 0.5, 0.5));
+
+        let field = Heightfield::from_canvas(&canvas);
+
+        assert_eq!(field.height(0, 0), 0.0);
+        assert_fuzzy_eq!(field.height(2, 0), 1.0);
+        assert_fuzzy_eq!(field.height(1, 1), 0.5);
+        assert_fuzzy_eq!(field.vertex(2, 1), pt(1, 0, 1));
+    }
+
+    #[test]
+    fn sampling_heights_from_a_single_row_of_pixels() {
+        let mut canvas = Canvas::new(3, 1);
+        canvas.write_pixel(2, 0, WHITE);
+
+        let field = Heightfield::from_canvas(&canvas);
+
+        assert_eq!((field.columns, field.rows), (3, 2));
+        assert_fuzzy_eq!(field.height(2, 0), 1.0);
+        assert_fuzzy_eq!(field.height(2, 1), 1.0);
+
+        let field = Heightfield::from_canvas(&Canvas::new_with_color(1, 1, WHITE));
+        let xs = field.local_intersect(ray(pt(0.3, 5, 0.6), v(0, -1, 0)));
+        assert_eq!(xs.len(), 1);
+        assert_fuzzy_eq!(xs[0].t, 4.0);
+    }
+
+    #[test]
+    fn a_ray_intersects_a_flat_heightfield() {
+        let field = Heightfield::from_fn(4, 4, |_, _| 0.25);
+
+        for (origin, direction, t) in [
+            (pt(0, 1, 0), v(0, -1, 0), 0.75),
+            (pt(0.9, 2.25, -0.9), v(0, -1, 0), 2.0),
+            (pt(-2, 1.25, 0.5), v(1, -1, 0), 1.0),
+            (pt(0.3, -1.75, 0.7), v(0, 2, 0), 1.0),
+        ] {
+            let xs = field.local_intersect(ray(origin, direction));
+
+            assert_eq!(xs.len(), 1);
+            assert_fuzzy_eq!(xs[0].t, t);
+        }
+    }
+
+    #[test]
+    fn a_ray_intersects_a_large_heightfield() {
+        // the cells of a 1024 by 1024 heightmap are only 0.002 across
+        let field = Heightfield::from_fn(1024, 1024, |x, z| 0.1 * (x + z));
+
+        for (origin, direction, t) in [
+            (pt(0.3, 5, -0.2), v(0, -1, 0), 4.99),
+            (pt(-1.5, 1, 0.5), v(1, -1, 0), 1.0),
+        ] {
+            let xs = field.local_intersect(ray(origin, direction));
+
+            assert_eq!(xs.len(), 1);
+            assert_fuzzy_eq!(xs[0].t, t);
+        }
+    }
+
+    #[test]
+    fn a_ray_misses_a_heightfield() {
+        let field = ramp();
+
+        for (origin, direction) in [
+            // outside the grid
+            (pt(1.5, 5, 0), v(0, -1, 0)),
+            // above the terrain
+            (pt(-5, 1.5, 0), v(1, 0, 0)),
+            // parallel to the slope
+            (pt(-3, -0.5, 0), v(2, 1, 0)),
+        ] {
+            let xs = field.local_intersect(ray(origin, direction));
+
+            assert!(xs.is_empty());
+        }
+    }
+
+    #[test]
+    fn a_ray_walks_through_the_grid() {
+        let field = ramp();
+
+        // flat along the x axis at a height halfway up the ramp
+        let xs = field.local_intersect(ray(pt(-5, 0.5, 0.3), v(1, 0, 0)));
+        assert_eq!(xs.len(), 1);
+        assert_fuzzy_eq!(xs[0].t, 5.0);
+
+        // the same, coming from the other side at an angle
+        let xs = field.local_intersect(ray(pt(5, 0.75, 5), v(-1, 0, -1)));
+        assert_eq!(xs.len(), 1);
+        assert_fuzzy_eq!(xs[0].t, 4.5);
+    }
+
+    #[test]
+    fn a_ray_can_hit_a_heightfield_more_than_once() {
+        // a ridge along the z axis
+        let field = Heightfield::from_fn(9, 3, |x, _| 1.0 - x.abs());
+
+        let xs = field.local_intersect(ray(pt(-2, 0.5, 0), v(1, 0, 0)));
+
+        assert_eq!(xs.len(), 2);
+        assert_fuzzy_eq!(xs[0].t, 1.5);
+        assert_fuzzy_eq!(xs[1].t, 2.5);
+    }
+
+    #[test]
+    fn the_normal_of_a_heightfield() {
+        let field = ramp();
+        let slope = v(-0.5, 1, 0).normalize();
+
+        assert_fuzzy_eq!(field.local_normal_at(pt(0.1, 0.55, 0.2)).normalize(), slope);
+        assert_fuzzy_eq!(field.local_normal_at(pt(1, 1, 1)).normalize(), slope);
+    }
+
+    #[test]
+    fn normals_are_interpolated_across_a_heightfield() {
+        // a ridge along the z axis: the normal turns over the top
+        let field = Heightfield::from_fn(3, 3, |x, _| 1.0 - x.abs());
+
+        let top = field.local_normal_at(pt(0, 1, 0)).normalize();
+        let side = field.local_normal_at(pt(-1, 0, 0)).normalize();
+        let between = field.local_normal_at(pt(-0.5, 0.5, 0)).normalize();
+
+        assert_fuzzy_eq!(top, v(0, 1, 0));
+        assert_fuzzy_eq!(side, v(-1, 1, 0).normalize());
+        assert_fuzzy_eq!(between, (top + side).normalize());
+    }
+}
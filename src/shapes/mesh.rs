@@ -0,0 +1,563 @@
+use crate::{v, BoundingBox, Intersection, Props, Ray, Shape, Tuple, EPSILON, F};
+use std::ops::Range;
+
+// triangles in a leaf of the bounding volume hierarchy
+const LEAF_SIZE: usize = 4;
+
+/// A surface made of triangles sharing a list of vertices. The triangles
+/// are kept in a bounding volume hierarchy so that rays only test the few
+/// that are near them.
+#[derive(Debug)]
+pub struct Mesh {
+    vertices: Vec<Tuple>,
+    normals: Vec<Tuple>,
+    triangles: Vec<[usize; 3]>,
+    nodes: Vec<Node>,
+    props: Props,
+}
+
+#[derive(Debug)]
+enum Node {
+    Leaf(BoundingBox, Range<usize>),
+    Branch(BoundingBox, usize, usize),
+}
+
+impl Node {
+    fn bounds(&self) -> BoundingBox {
+        match self {
+            Self::Leaf(bounds, _) | Self::Branch(bounds, ..) => *bounds,
+        }
+    }
+}
+
+impl Mesh {
+    /// A mesh of `triangles`, each given by the indices of its three
+    /// corners in `vertices`, counter-clockwise when seen from outside.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a triangle refers to a vertex that doesn't exist.
+    #[must_use]
+    pub fn new(vertices: Vec<Tuple>, triangles: Vec<[usize; 3]>) -> Self {
+        assert!(
+            triangles.iter().flatten().all(|&i| i < vertices.len()),
+            "a triangle refers to a missing vertex"
+        );
+
+        let mut mesh = Self {
+            vertices,
+            normals: vec![],
+            triangles,
+            nodes: vec![],
+            props: Props::default(),
+        };
+
+        if !mesh.triangles.is_empty() {
+            mesh.build(0..mesh.triangles.len());
+        }
+
+        mesh
+    }
+
+    /// A triangle fan: a flat polygon, or anything shaped like one, made of
+    /// triangles that all share the first of `vertices` and run around the
+    /// rest in order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer than three vertices.
+    #[must_use]
+    pub fn fan(vertices: Vec<Tuple>) -> Self {
+        assert!(vertices.len() >= 3, "a fan needs at least three vertices");
+
+        let triangles = (1..vertices.len() - 1).map(|i| [0, i, i + 1]).collect();
+
+        Self::new(vertices, triangles)
+    }
+
+    /// Gives every vertex a normal, which is interpolated across the
+    /// triangles to make the mesh look smooth.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there isn't one normal per vertex.
+    #[must_use]
+    pub fn normals(mut self, normals: Vec<Tuple>) -> Self {
+        assert_eq!(
+            normals.len(),
+            self.vertices.len(),
+            "a mesh needs one normal per vertex"
+        );
+        self.normals = normals;
+
+        self
+    }
+
+    #[must_use]
+    pub fn vertices(&self) -> &[Tuple] {
+        &self.vertices
+    }
+
+    #[must_use]
+    pub fn triangles(&self) -> &[[usize; 3]] {
+        &self.triangles
+    }
+
+    fn corners(&self, [a, b, c]: [usize; 3]) -> [Tuple; 3] {
+        [self.vertices[a], self.vertices[b], self.vertices[c]]
+    }
+
+    fn bounds_of(&self, triangles: &[[usize; 3]]) -> BoundingBox {
+        let mut bounds = BoundingBox::default();
+
+        for &triangle in triangles {
+            for corner in self.corners(triangle) {
+                bounds.add_point(corner);
+            }
+        }
+
+        bounds
+    }
+
+    // Adds the node for a range of triangles, splitting it in half along the
+    // longest side of its bounds, and returns its index.
+    fn build(&mut self, range: Range<usize>) -> usize {
+        let bounds = self.bounds_of(&self.triangles[range.clone()]);
+        let index = self.nodes.len();
+
+        if range.len() <= LEAF_SIZE {
+            self.nodes.push(Node::Leaf(bounds, range));
+            return index;
+        }
+
+        let size = bounds.max - bounds.min;
+        let axis = if size.x >= size.y && size.x >= size.z {
+            0
+        } else if size.y >= size.z {
+            1
+        } else {
+            2
+        };
+
+        let vertices = &self.vertices;
+        let centroid = |&[a, b, c]: &[usize; 3]| {
+            (vertices[a][axis] + vertices[b][axis] + vertices[c][axis]) / 3.0
+        };
+        self.triangles[range.clone()].sort_by(|a, b| centroid(a).total_cmp(&centroid(b)));
+
+        // reserve the place of this node before its children
+        self.nodes.push(Node::Leaf(bounds, range.clone()));
+
+        let middle = range.start + range.len() / 2;
+        let left = self.build(range.start..middle);
+        let right = self.build(middle..range.end);
+        self.nodes[index] = Node::Branch(bounds, left, right);
+
+        index
+    }
+
+    // The nodes whose bounds pass the test, found from the root down, and
+    // the indices of the triangles in their leaves.
+    fn candidates(&self, test: impl Fn(&BoundingBox) -> bool) -> Vec<usize> {
+        let mut found = vec![];
+        let mut stack = vec![0];
+
+        if self.nodes.is_empty() {
+            return found;
+        }
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+
+            if !test(&node.bounds()) {
+                continue;
+            }
+
+            match node {
+                Node::Leaf(_, range) => found.extend(range.clone()),
+                Node::Branch(_, left, right) => stack.extend([*left, *right]),
+            }
+        }
+
+        found
+    }
+
+    // the normal at the point of `triangle` with the given barycentric
+    // weights of its corners
+    fn normal_on(&self, triangle: [usize; 3], [w1, w2, w3]: [F; 3]) -> Tuple {
+        let [a, b, c] = triangle;
+
+        if self.normals.is_empty() {
+            let [p1, p2, p3] = self.corners(triangle);
+            (p2 - p1).cross(p3 - p1)
+        } else {
+            self.normals[a] * w1 + self.normals[b] * w2 + self.normals[c] * w3
+        }
+    }
+}
+
+impl Shape for Mesh {
+    fn props(&self) -> &Props {
+        &self.props
+    }
+
+    fn props_mut(&mut self) -> &mut Props {
+        &mut self.props
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        self.candidates(|bounds| bounds.intersects(ray))
+            .into_iter()
+            .filter_map(|index| {
+                let [p1, p2, p3] = self.corners(self.triangles[index]);
+                let (t, u, v) = intersect_triangle(ray, p1, p2, p3)?;
+
+                Some(Intersection::with_uv(t, self, index, u, v))
+            })
+            .collect()
+    }
+
+    fn local_normal_at_hit(&self, point: Tuple, hit: &Intersection) -> Tuple {
+        match hit.triangle {
+            Some(index) => {
+                self.normal_on(self.triangles[index], [1.0 - hit.u - hit.v, hit.u, hit.v])
+            }
+            None => self.local_normal_at(point),
+        }
+    }
+
+    fn local_normal_at(&self, point: Tuple) -> Tuple {
+        // without a hit to say which triangle the point lies on, find the
+        // one it's closest to among those whose bounds contain it, whether
+        // off its plane or past its edges
+        let reach = v(EPSILON, EPSILON, EPSILON);
+        let near = self.candidates(|bounds| {
+            BoundingBox::new(bounds.min - reach, bounds.max + reach).contains_point(point)
+        });
+
+        let Some((triangle, weights, _)) = near
+            .into_iter()
+            .map(|index| {
+                let triangle = self.triangles[index];
+                let corners = self.corners(triangle);
+                let weights = barycentric(point, corners);
+                let [p1, p2, p3] = corners;
+                let off_plane = (point - p1).dot((p2 - p1).cross(p3 - p1).normalize());
+
+                (
+                    triangle,
+                    weights,
+                    off_plane.abs() + distance_outside(weights),
+                )
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+        else {
+            return v(0, 1, 0);
+        };
+
+        self.normal_on(triangle, weights)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.nodes
+            .first()
+            .map_or_else(BoundingBox::default, Node::bounds)
+    }
+}
+
+/// Where `ray` meets the triangle with the given corners, by the
+/// Möller–Trumbore algorithm: the distance along the ray and the barycentric
+/// weights `u` and `v` of the second and third corners.
+pub(crate) fn intersect_triangle(ray: Ray, p1: Tuple, p2: Tuple, p3: Tuple) -> Option<(F, F, F)> {
+    let e1 = p2 - p1;
+    let e2 = p3 - p1;
+
+    let dir_cross_e2 = ray.direction.cross(e2);
+    let det = e1.dot(dir_cross_e2);
+
+    // the determinant grows with the size of the triangle and the length of
+    // the ray's direction, so only compare it with those to find rays
+    // parallel to the triangle
+    if det.abs() <= F::EPSILON * e1.magnitude() * e2.magnitude() * ray.direction.magnitude() {
+        return None;
+    }
+
+    let f = 1.0 / det;
+    let p1_to_origin = ray.origin - p1;
+    let u = f * p1_to_origin.dot(dir_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let origin_cross_e1 = p1_to_origin.cross(e1);
+    let v = f * ray.direction.dot(origin_cross_e1);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    Some((f * e2.dot(origin_cross_e1), u, v))
+}
+
+// the weights of the corners of a triangle that give `point`, once it's
+// projected onto the plane of the triangle
+fn barycentric(point: Tuple, [p1, p2, p3]: [Tuple; 3]) -> [F; 3] {
+    let (e1, e2, to_point) = (p2 - p1, p3 - p1, point - p1);
+
+    let d11 = e1.dot(e1);
+    let d12 = e1.dot(e2);
+    let d22 = e2.dot(e2);
+    let d1p = e1.dot(to_point);
+    let d2p = e2.dot(to_point);
+    let denominator = d11 * d22 - d12 * d12;
+
+    if denominator.abs() < F::EPSILON {
+        return [1.0, 0.0, 0.0];
+    }
+
+    let w2 = (d22 * d1p - d12 * d2p) / denominator;
+    let w3 = (d11 * d2p - d12 * d1p) / denominator;
+
+    [1.0 - w2 - w3, w2, w3]
+}
+
+// how far outside the triangle weights are, 0 for points inside it
+fn distance_outside(weights: [F; 3]) -> F {
+    weights.iter().map(|w| (-w).max(0.0)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    // a unit square in the xy plane, split along its diagonal
+    fn square() -> Mesh {
+        Mesh::new(
+            vec![pt(0, 0, 0), pt(1, 0, 0), pt(1, 1, 0), pt(0, 1, 0)],
+            vec![[0, 1, 2], [0, 2, 3]],
+        )
+    }
+
+    // a grid of n by n squares over the unit square in the xz plane
+    fn grid(n: usize) -> Mesh {
+        let mut vertices = vec![];
+        for z in 0..=n {
+            for x in 0..=n {
+                vertices.push(pt(x as F / n as F, 0, z as F / n as F));
+            }
+        }
+
+        let mut triangles = vec![];
+        for z in 0..n {
+            for x in 0..n {
+                let i = z * (n + 1) + x;
+                triangles.push([i, i + n + 2, i + 1]);
+                triangles.push([i, i + n + 1, i + n + 2]);
+            }
+        }
+
+        Mesh::new(vertices, triangles)
+    }
+
+    #[test]
+    fn intersecting_a_ray_with_a_triangle() {
+        let (p1, p2, p3) = (pt(0, 1, 0), pt(-1, 0, 0), pt(1, 0, 0));
+
+        for (origin, t) in [
+            (pt(0, 0.5, -2), Some(2.0)),
+            // past each of the edges
+            (pt(1, 1, -2), None),
+            (pt(-1, 1, -2), None),
+            (pt(0, -1, -2), None),
+        ] {
+            let xs = intersect_triangle(ray(origin, v(0, 0, 1)), p1, p2, p3);
+
+            assert_eq!(xs.map(|(t, ..)| t), t);
+        }
+
+        // parallel to the triangle
+        let r = ray(pt(0, -1, -2), v(0, 1, 0));
+        assert_eq!(intersect_triangle(r, p1, p2, p3), None);
+    }
+
+    #[test]
+    fn an_intersection_with_a_triangle_stores_u_and_v() {
+        let (p1, p2, p3) = (pt(0, 1, 0), pt(-1, 0, 0), pt(1, 0, 0));
+        let r = ray(pt(-0.2, 0.3, -2), v(0, 0, 1));
+
+        let (_, u, v) = intersect_triangle(r, p1, p2, p3).unwrap();
+
+        assert_fuzzy_eq!(u, 0.45);
+        assert_fuzzy_eq!(v, 0.25);
+    }
+
+    #[test]
+    fn intersecting_a_ray_with_a_tiny_triangle() {
+        // two millimetres across, in a mesh measured in metres
+        let (p1, p2, p3) = (pt(0, 0, 0), pt(0.002, 0, 0), pt(0, 0.002, 0));
+
+        let r = ray(pt(0.0005, 0.0005, -1), v(0, 0, 1));
+        assert_eq!(
+            intersect_triangle(r, p1, p2, p3).map(|(t, ..)| t),
+            Some(1.0)
+        );
+
+        // and the same mesh scaled up, which shortens the ray in object space
+        let mesh = Mesh::new(vec![p1, p2, p3], vec![[0, 1, 2]])
+            .transform(Matrix::scaling(1000, 1000, 1000));
+        let xs = mesh.intersect(ray(pt(0.5, 0.5, -5), v(0, 0, 1)));
+        assert_eq!(xs.len(), 1);
+        assert_fuzzy_eq!(xs[0].t, 5.0);
+    }
+
+    #[test]
+    fn a_ray_intersects_a_mesh() {
+        let mesh = square();
+
+        for (origin, count) in [
+            (pt(0.75, 0.25, -1), 1),
+            (pt(0.25, 0.75, -1), 1),
+            (pt(1.5, 0.5, -1), 0),
+        ] {
+            let xs = mesh.local_intersect(ray(origin, v(0, 0, 1)));
+
+            assert_eq!(xs.len(), count);
+            if count > 0 {
+                assert_fuzzy_eq!(xs[0].t, 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn a_triangle_fan_shares_its_first_vertex() {
+        // a regular hexagon around the origin in the xz plane
+        let vertices = (0..6)
+            .map(|i| {
+                let angle = i as F * PI / 3.0;
+                pt(angle.cos(), 0, angle.sin())
+            })
+            .collect();
+
+        let mesh = Mesh::fan(vertices);
+
+        assert_eq!(
+            mesh.triangles(),
+            [[0, 1, 2], [0, 2, 3], [0, 3, 4], [0, 4, 5]]
+        );
+
+        for (x, z, count) in [(0.1, 0.2, 1), (-0.8, 0.1, 1), (0.1, -0.8, 1), (0.9, 0.9, 0)] {
+            let xs = mesh.local_intersect(ray(pt(x, 1, z), v(0, -1, 0)));
+
+            assert_eq!(xs.len(), count);
+        }
+
+        assert!(std::panic::catch_unwind(|| Mesh::fan(vec![pt(0, 0, 0), pt(1, 0, 0)])).is_err());
+    }
+
+    #[test]
+    fn a_large_mesh_is_split_into_a_hierarchy() {
+        let mesh = grid(8);
+
+        assert_eq!(mesh.triangles().len(), 128);
+        assert!(mesh.nodes.len() > 1);
+        assert_eq!(mesh.bounds(), BoundingBox::new(pt(0, 0, 0), pt(1, 0, 1)));
+
+        // every triangle is still found
+        for i in 0..8 {
+            let x = (i as F + 0.3) / 8.0;
+            let xs = mesh.local_intersect(ray(pt(x, 1, 0.55), v(0, -1, 0)));
+
+            assert_eq!(xs.len(), 1);
+            assert_fuzzy_eq!(xs[0].t, 1.0);
+        }
+    }
+
+    #[test]
+    fn the_normal_of_a_flat_mesh() {
+        let mesh = square();
+
+        assert_fuzzy_eq!(
+            mesh.local_normal_at(pt(0.75, 0.25, 0)).normalize(),
+            v(0, 0, 1)
+        );
+        assert_fuzzy_eq!(
+            mesh.local_normal_at(pt(0.25, 0.75, 0)).normalize(),
+            v(0, 0, 1)
+        );
+    }
+
+    #[test]
+    fn the_normal_comes_from_the_triangle_under_the_point() {
+        // two faces of a wedge whose bounds overlap
+        let mesh = Mesh::new(
+            vec![
+                pt(0, 0, 0),
+                pt(1, 0, 0),
+                pt(1, 1, 0),
+                pt(0, 0, 1),
+                pt(1, 0, 1),
+            ],
+            vec![[0, 1, 2], [0, 3, 4]],
+        );
+
+        assert_fuzzy_eq!(
+            mesh.local_normal_at(pt(0.6, 0.2, 0)).normalize(),
+            v(0, 0, 1)
+        );
+        assert_fuzzy_eq!(
+            mesh.local_normal_at(pt(0.2, 0, 0.6)).normalize(),
+            v(0, 1, 0)
+        );
+    }
+
+    #[test]
+    fn normals_are_interpolated_across_a_mesh() {
+        let mesh = square().normals(vec![v(0, 0, 1), v(1, 0, 0), v(1, 0, 0), v(0, 0, 1)]);
+
+        let n = mesh.local_normal_at(pt(0.5, 0.25, 0));
+
+        assert_fuzzy_eq!(n, v(0.5, 0, 0.5));
+    }
+
+    #[test]
+    fn normals_are_interpolated_from_where_the_ray_hit() {
+        let mesh = square().normals(vec![v(0, 0, 1), v(1, 0, 0), v(1, 0, 0), v(0, 0, 1)]);
+        let r = ray(pt(0.5, 0.25, -1), v(0, 0, 1));
+
+        let xs = mesh.local_intersect(r);
+        let n = mesh.local_normal_at_hit(r.position(xs[0].t), &xs[0]);
+
+        assert_fuzzy_eq!(n, v(0.5, 0, 0.5));
+    }
+
+    #[test]
+    fn each_side_of_a_sheet_gets_the_normal_of_its_own_triangle() {
+        // the same triangle twice, wound both ways, so that both are hit at
+        // the same point
+        let mesh = Mesh::new(
+            vec![pt(0, 0, 0), pt(1, 0, 0), pt(0, 1, 0)],
+            vec![[0, 1, 2], [0, 2, 1]],
+        );
+        let r = ray(pt(0.25, 0.25, -1), v(0, 0, 1));
+
+        let normals = mesh
+            .local_intersect(r)
+            .iter()
+            .map(|i| mesh.local_normal_at_hit(r.position(i.t), i).normalize())
+            .collect::<Vec<_>>();
+
+        assert_eq!(normals.len(), 2);
+        assert!(normals.contains(&v(0, 0, 1)));
+        assert!(normals.contains(&v(0, 0, -1)));
+    }
+
+    #[test]
+    fn finding_barycentric_weights() {
+        let corners = [pt(0, 0, 0), pt(2, 0, 0), pt(0, 2, 0)];
+
+        assert_eq!(barycentric(pt(0, 0, 0), corners), [1.0, 0.0, 0.0]);
+        assert_eq!(barycentric(pt(1, 1, 0), corners), [0.0, 0.5, 0.5]);
+        assert_eq!(barycentric(pt(0.5, 0.5, 3), corners), [0.5, 0.25, 0.25]);
+        assert!(distance_outside(barycentric(pt(3, 0, 0), corners)) > 0.0);
+    }
+}
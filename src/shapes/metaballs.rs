@@ -0,0 +1,335 @@
+use crate::roots::solve_quadratic;
+use crate::{v, BoundingBox, Intersection, Props, Ray, Shape, Tuple, F};
+
+// how many times a stretch of the ray where the surface might be is halved
+// looking for crossings, and how many more times a crossing is halved after
+// that
+const MAX_DEPTH: usize = 20;
+const BISECTIONS: usize = 30;
+
+#[derive(Debug, Clone, Copy)]
+struct Ball {
+    center: Tuple,
+    radius: F,
+    weight: F,
+}
+
+impl Ball {
+    // the share of the field coming from this ball, falling smoothly from
+    // `weight` at the center to nothing at `radius`
+    fn field_at(&self, point: Tuple) -> F {
+        self.field_at_distance((point - self.center).magnitude())
+    }
+
+    fn field_at_distance(&self, distance: F) -> F {
+        let falloff = 1.0 - distance.powi(2) / self.radius.powi(2);
+
+        if falloff > 0.0 {
+            self.weight * falloff.powi(3)
+        } else {
+            0.0
+        }
+    }
+
+    // the least and the most of this ball's field anywhere on the segment
+    // from `a` to `b`. The field only depends on the distance to the center,
+    // so it's found at the nearest and farthest points.
+    fn field_range(&self, a: Tuple, b: Tuple) -> (F, F) {
+        let segment = b - a;
+        let length2 = segment.dot(segment);
+        let along = if length2 > 0.0 {
+            ((self.center - a).dot(segment) / length2).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let nearest = (a + segment * along - self.center).magnitude();
+        let farthest = (a - self.center)
+            .magnitude()
+            .max((b - self.center).magnitude());
+
+        let (near, far) = (
+            self.field_at_distance(nearest),
+            self.field_at_distance(farthest),
+        );
+
+        (near.min(far), near.max(far))
+    }
+}
+
+/// Blobs that melt into each other: the surface where the sum of the fields
+/// of all the balls reaches `threshold`.
+#[derive(Debug)]
+pub struct Metaballs {
+    balls: Vec<Ball>,
+    threshold: F,
+    props: Props,
+}
+
+impl Metaballs {
+    /// Adds a ball whose field reaches `weight` at `center` and fades out
+    /// at `radius` from it.
+    #[must_use]
+    pub fn ball(mut self, center: Tuple, radius: impl Into<F>, weight: impl Into<F>) -> Self {
+        self.balls.push(Ball {
+            center,
+            radius: radius.into(),
+            weight: weight.into(),
+        });
+
+        self
+    }
+
+    #[must_use]
+    pub fn threshold(mut self, threshold: impl Into<F>) -> Self {
+        self.threshold = threshold.into();
+
+        self
+    }
+
+    fn field_at(&self, point: Tuple) -> F {
+        self.balls.iter().map(|ball| ball.field_at(point)).sum()
+    }
+
+    // The parts of the ray inside at least one ball, in order. The field is
+    // zero everywhere else, so that's where the surface has to be.
+    fn spans(&self, ray: Ray) -> Vec<(F, F)> {
+        let mut spans = self
+            .balls
+            .iter()
+            .filter_map(|ball| {
+                let to_ray = ray.origin - ball.center;
+                let roots = solve_quadratic(
+                    ray.direction.dot(ray.direction),
+                    2.0 * ray.direction.dot(to_ray),
+                    to_ray.dot(to_ray) - ball.radius.powi(2),
+                );
+
+                (roots.len() == 2).then(|| (roots[0], roots[1]))
+            })
+            .collect::<Vec<_>>();
+
+        spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut merged: Vec<(F, F)> = vec![];
+        for (start, end) in spans {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        merged
+    }
+
+    // Adds the times the ray crosses the surface between `a` and `b` to `xs`,
+    // in order. Halves of the stretch where the field can't reach the
+    // threshold, or can't fall below it, are skipped, so the search only
+    // closes in on where the surface might be.
+    fn crossings(&self, ray: Ray, (a, b): (F, F), depth: usize, xs: &mut Vec<F>) {
+        let (pa, pb) = (ray.position(a), ray.position(b));
+        let (low, high) = self
+            .balls
+            .iter()
+            .map(|ball| ball.field_range(pa, pb))
+            .fold((0.0, 0.0), |(low, high), (l, h)| (low + l, high + h));
+
+        if self.threshold < low || self.threshold > high {
+            return;
+        }
+
+        if depth < MAX_DEPTH {
+            let middle = (a + b) / 2.0;
+            self.crossings(ray, (a, middle), depth + 1, xs);
+            self.crossings(ray, (middle, b), depth + 1, xs);
+            return;
+        }
+
+        let inside = |t: F| self.field_at(ray.position(t)) >= self.threshold;
+        let (mut a, mut b) = (a, b);
+        let side = inside(a);
+
+        if side == inside(b) {
+            return;
+        }
+
+        // narrow down the crossing by bisection
+        for _ in 0..BISECTIONS {
+            let middle = (a + b) / 2.0;
+
+            if inside(middle) == side {
+                a = middle;
+            } else {
+                b = middle;
+            }
+        }
+
+        xs.push((a + b) / 2.0);
+    }
+}
+
+impl Default for Metaballs {
+    fn default() -> Self {
+        Self {
+            balls: vec![],
+            threshold: 0.5,
+            props: Props::default(),
+        }
+    }
+}
+
+impl Shape for Metaballs {
+    fn props(&self) -> &Props {
+        &self.props
+    }
+
+    fn props_mut(&mut self) -> &mut Props {
+        &mut self.props
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        let mut xs = vec![];
+
+        for span in self.spans(ray) {
+            self.crossings(ray, span, 0, &mut xs);
+        }
+
+        xs.into_iter().map(|t| self.intersection(t)).collect()
+    }
+
+    fn local_normal_at(&self, point: Tuple) -> Tuple {
+        // against the gradient of the field, which grows towards the centers
+        self.balls
+            .iter()
+            .map(|ball| {
+                let offset = point - ball.center;
+                let falloff = 1.0 - offset.magnitude().powi(2) / ball.radius.powi(2);
+
+                if falloff > 0.0 {
+                    offset * (6.0 * ball.weight * falloff.powi(2) / ball.radius.powi(2))
+                } else {
+                    v(0, 0, 0)
+                }
+            })
+            .fold(v(0, 0, 0), |sum, n| sum + n)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::default();
+
+        for ball in &self.balls {
+            let reach = v(ball.radius, ball.radius, ball.radius);
+            bounds.add_point(ball.center - reach);
+            bounds.add_point(ball.center + reach);
+        }
+
+        bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    // the distance from the center of a lone ball of radius 1 and weight 1
+    // to its surface at the default threshold
+    fn lone_radius() -> F {
+        F::sqrt(1.0 - F::cbrt(0.5))
+    }
+
+    #[test]
+    fn the_field_of_a_ball() {
+        let m = Metaballs::default().ball(pt(1, 0, 0), 2, 3);
+
+        assert_fuzzy_eq!(m.field_at(pt(1, 0, 0)), 3.0);
+        assert_fuzzy_eq!(m.field_at(pt(2, 0, 0)), 3.0 * 0.75_f64.powi(3));
+        assert_eq!(m.field_at(pt(3, 0, 0)), 0.0);
+        assert_eq!(m.field_at(pt(-5, 0, 0)), 0.0);
+    }
+
+    #[test]
+    fn a_ray_intersects_a_lone_ball() {
+        let m = Metaballs::default().ball(pt(0, 0, 0), 1, 1);
+
+        let xs = m.local_intersect(ray(pt(0, 0, -5), v(0, 0, 1)));
+
+        assert_eq!(xs.len(), 2);
+        assert_fuzzy_eq!(xs[0].t, 5.0 - lone_radius());
+        assert_fuzzy_eq!(xs[1].t, 5.0 + lone_radius());
+    }
+
+    #[test]
+    fn a_ray_misses_metaballs() {
+        let m = Metaballs::default().ball(pt(0, 0, 0), 1, 1);
+
+        // through the field, but where it's too weak
+        assert!(m
+            .local_intersect(ray(pt(0, 0.6, -5), v(0, 0, 1)))
+            .is_empty());
+        // past it entirely
+        assert!(m.local_intersect(ray(pt(0, 2, -5), v(0, 0, 1))).is_empty());
+        // with nothing to hit
+        assert!(Metaballs::default()
+            .local_intersect(ray(pt(0, 0, -5), v(0, 0, 1)))
+            .is_empty());
+    }
+
+    #[test]
+    fn a_ray_grazing_a_ball_finds_both_crossings() {
+        // the small ball only stretches the part of the ray that's searched
+        let m = Metaballs::default()
+            .ball(pt(0, 0, 0), 1, 1)
+            .ball(pt(0.7, 0.5, 0), 0.5, 0.01);
+
+        // the ray only spends a thousandth of a unit inside the surface, far
+        // less than the stretch it spends inside the balls
+        let y = lone_radius() - 1e-7;
+        let xs = m.local_intersect(ray(pt(-5, y, 0), v(1, 0, 0)));
+
+        assert_eq!(xs.len(), 2);
+        assert!(xs[1].t - xs[0].t < 0.001);
+        assert_fuzzy_eq!(xs[0].t + xs[1].t, 10.0);
+    }
+
+    #[test]
+    fn nearby_balls_melt_together() {
+        let m = Metaballs::default()
+            .ball(pt(-0.6, 0, 0), 1, 1)
+            .ball(pt(0.6, 0, 0), 1, 1);
+
+        // neither ball reaches the middle on its own
+        assert!(0.6 > lone_radius());
+
+        let xs = m.local_intersect(ray(pt(-5, 0, 0), v(1, 0, 0)));
+        assert_eq!(xs.len(), 2);
+
+        let xs = m.local_intersect(ray(pt(0, 5, 0), v(0, -1, 0)));
+        assert_eq!(xs.len(), 2);
+        assert_fuzzy_eq!(xs[0].t + xs[1].t, 10.0);
+    }
+
+    #[test]
+    fn the_normal_on_metaballs() {
+        let m = Metaballs::default().ball(pt(0, 0, 0), 1, 1);
+        let r = lone_radius();
+
+        assert_fuzzy_eq!(m.local_normal_at(pt(r, 0, 0)).normalize(), v(1, 0, 0));
+        assert_fuzzy_eq!(m.local_normal_at(pt(0, 0, -r)).normalize(), v(0, 0, -1));
+
+        // between two balls the normal points away from both
+        let m = Metaballs::default()
+            .ball(pt(0, 0, 0), 1.5, 1)
+            .ball(pt(2, 0, 0), 1.5, 1);
+        assert_fuzzy_eq!(m.local_normal_at(pt(1, 0.2, 0)).normalize(), v(0, 1, 0));
+    }
+
+    #[test]
+    fn the_bounds_of_metaballs() {
+        let m = Metaballs::default()
+            .ball(pt(0, 0, 0), 1, 1)
+            .ball(pt(3, 1, 0), 2, 1);
+
+        assert_eq!(m.bounds(), BoundingBox::new(pt(-1, -1, -2), pt(5, 3, 2)));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{v, Intersection, Props, Ray, Shape, Tuple, EPSILON};
+use crate::{pt, v, BoundingBox, Intersection, Props, Ray, Shape, Tuple, EPSILON, INFINITY};
 
 #[must_use]
 pub fn glass() -> Plane {
@@ -22,7 +22,7 @@ impl Shape for Plane {
         &mut self.props
     }
 
-    fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
         if ray.direction.y.abs() < EPSILON {
             return vec![];
         }
@@ -37,6 +37,14 @@ impl Shape for Plane {
     fn local_normal_at(&self, _point: Tuple) -> Tuple {
         v(0.0, 1.0, 0.0)
     }
+
+    fn local_tangent_at(&self, _point: Tuple) -> Tuple {
+        v(1, 0, 0)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(pt(-INFINITY, 0, -INFINITY), pt(INFINITY, 0, INFINITY))
+    }
 }
 
 #[cfg(test)]
@@ -96,4 +104,12 @@ mod tests {
         assert_eq!(xs[0].t, 1.0);
         assert_eq!(xs[0].object, &p as &dyn Shape);
     }
+
+    #[test]
+    fn the_tangent_of_a_plane_is_constant_everywhere() {
+        let p = Plane::default();
+
+        assert_eq!(p.local_tangent_at(pt(0, 0, 0)), v(1, 0, 0));
+        assert_eq!(p.local_tangent_at(pt(10, 0, -10)), v(1, 0, 0));
+    }
 }
@@ -0,0 +1,519 @@
+use crate::{pt, v, Mesh, Tuple, F};
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> IoResult<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return Err(invalid_data(&format!("unknown PLY type `{name}`"))),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar(String, Scalar),
+    List(String, Scalar, Scalar),
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn find(&self, name: &str) -> Option<usize> {
+        self.properties
+            .iter()
+            .position(|p| matches!(p, Property::Scalar(n, _) if n == name))
+    }
+}
+
+// what's left of the file, read line by line in the header and value by
+// value after it
+struct Reader<'a> {
+    rest: &'a [u8],
+    format: Format,
+}
+
+impl<'a> Reader<'a> {
+    fn line(&mut self) -> IoResult<&'a str> {
+        let end = self
+            .rest
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(truncated)?;
+        let line = &self.rest[..end];
+        self.rest = &self.rest[end + 1..];
+
+        std::str::from_utf8(line)
+            .map(str::trim)
+            .map_err(|_| invalid_data("the PLY header is not text"))
+    }
+
+    fn take(&mut self, len: usize) -> IoResult<&'a [u8]> {
+        if self.rest.len() < len {
+            return Err(truncated());
+        }
+        let (taken, rest) = self.rest.split_at(len);
+        self.rest = rest;
+
+        Ok(taken)
+    }
+
+    // the fewest bytes a value of `property` can take up: a digit in ASCII
+    // files, or the size of the value or list count in binary ones
+    fn min_size(&self, property: &Property) -> usize {
+        match (self.format, property) {
+            (Format::Ascii, _) => 1,
+            (_, Property::Scalar(_, scalar) | Property::List(_, scalar, _)) => scalar.size(),
+        }
+    }
+
+    fn value(&mut self, scalar: Scalar) -> IoResult<F> {
+        if self.format == Format::Ascii {
+            let start = self
+                .rest
+                .iter()
+                .position(|b| !b.is_ascii_whitespace())
+                .ok_or_else(truncated)?;
+            let len = self.rest[start..]
+                .iter()
+                .position(u8::is_ascii_whitespace)
+                .unwrap_or(self.rest.len() - start);
+            let token = &self.take(start + len)?[start..];
+
+            return std::str::from_utf8(token)
+                .ok()
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(|| invalid_data("malformed number in PLY file"));
+        }
+
+        let bytes = self.take(scalar.size())?;
+        let big = self.format == Format::BigEndian;
+
+        macro_rules! read {
+            ($t:ty) => {{
+                let bytes = bytes.try_into().unwrap();
+                F::from(if big {
+                    <$t>::from_be_bytes(bytes)
+                } else {
+                    <$t>::from_le_bytes(bytes)
+                })
+            }};
+        }
+
+        Ok(match scalar {
+            Scalar::I8 => read!(i8),
+            Scalar::U8 => read!(u8),
+            Scalar::I16 => read!(i16),
+            Scalar::U16 => read!(u16),
+            Scalar::I32 => read!(i32),
+            Scalar::U32 => read!(u32),
+            Scalar::F32 => read!(f32),
+            Scalar::F64 => read!(f64),
+        })
+    }
+}
+
+fn invalid_data(message: &str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, message)
+}
+
+fn truncated() -> IoError {
+    IoError::new(ErrorKind::UnexpectedEof, "the PLY file is truncated")
+}
+
+fn count(n: F) -> IoResult<usize> {
+    if n >= 0.0 && n.fract() == 0.0 {
+        Ok(n as usize)
+    } else {
+        Err(invalid_data("expected a whole number in PLY file"))
+    }
+}
+
+fn read_header(reader: &mut Reader) -> IoResult<Vec<Element>> {
+    if reader.line()? != "ply" {
+        return Err(invalid_data("not a PLY file"));
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+
+    loop {
+        let line = reader.line()?;
+        let words = line.split_ascii_whitespace().collect::<Vec<_>>();
+
+        match words.as_slice() {
+            ["end_header"] => break,
+            [] | ["comment" | "obj_info", ..] => {}
+            ["format", name, "1.0"] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::LittleEndian,
+                    "binary_big_endian" => Format::BigEndian,
+                    _ => return Err(invalid_data(&format!("unknown PLY format `{name}`"))),
+                });
+            }
+            ["element", name, n] => elements.push(Element {
+                name: (*name).to_string(),
+                count: n
+                    .parse()
+                    .map_err(|_| invalid_data("malformed element count in PLY header"))?,
+                properties: vec![],
+            }),
+            ["property", "list", count_type, item_type, name] => elements
+                .last_mut()
+                .ok_or_else(|| invalid_data("a PLY property comes before any element"))?
+                .properties
+                .push(Property::List(
+                    (*name).to_string(),
+                    Scalar::parse(count_type)?,
+                    Scalar::parse(item_type)?,
+                )),
+            ["property", scalar, name] => elements
+                .last_mut()
+                .ok_or_else(|| invalid_data("a PLY property comes before any element"))?
+                .properties
+                .push(Property::Scalar(
+                    (*name).to_string(),
+                    Scalar::parse(scalar)?,
+                )),
+            _ => return Err(invalid_data(&format!("malformed PLY header line `{line}`"))),
+        }
+    }
+
+    reader.format = format.ok_or_else(|| invalid_data("the PLY header has no format"))?;
+
+    Ok(elements)
+}
+
+impl Mesh {
+    /// Reads a PLY file, in ASCII or either binary format. Faces with more
+    /// than three corners are split into fans of triangles, and the vertex
+    /// normals are used when the file has them. Elements other than
+    /// vertices and faces are skipped.
+    pub fn from_ply(reader: &mut impl Read) -> IoResult<Self> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+
+        let mut reader = Reader {
+            rest: &bytes,
+            format: Format::Ascii,
+        };
+        let elements = read_header(&mut reader)?;
+
+        let mut vertices: Vec<Tuple> = vec![];
+        let mut normals = vec![];
+        let mut triangles = vec![];
+
+        for element in &elements {
+            let position = [element.find("x"), element.find("y"), element.find("z")];
+            let normal = [element.find("nx"), element.find("ny"), element.find("nz")];
+
+            let position = match (element.name.as_str(), position) {
+                ("vertex", [Some(x), Some(y), Some(z)]) => Some([x, y, z]),
+                ("vertex", _) => return Err(invalid_data("PLY vertices need x, y and z")),
+                _ => None,
+            };
+            let normal = match normal {
+                [Some(x), Some(y), Some(z)] => Some([x, y, z]),
+                _ => None,
+            };
+
+            // the count comes from the file, so make sure what's left of it
+            // could hold that many rows before reading them one by one
+            let row_size = element
+                .properties
+                .iter()
+                .map(|p| reader.min_size(p))
+                .sum::<usize>();
+
+            if element
+                .count
+                .checked_mul(row_size)
+                .is_none_or(|size| size > reader.rest.len())
+            {
+                return Err(truncated());
+            }
+
+            // rows without properties take up no room and say nothing
+            if row_size == 0 && element.name != "face" {
+                continue;
+            }
+
+            let mut values = vec![0.0; element.properties.len()];
+            let mut corners = vec![];
+
+            for _ in 0..element.count {
+                for (property, value) in element.properties.iter().zip(&mut values) {
+                    match property {
+                        Property::Scalar(_, scalar) => *value = reader.value(*scalar)?,
+                        Property::List(name, count_type, item_type) => {
+                            let len = count(reader.value(*count_type)?)?;
+                            let is_face = element.name == "face"
+                                && (name == "vertex_indices" || name == "vertex_index");
+
+                            if is_face {
+                                corners.clear();
+                            }
+                            for _ in 0..len {
+                                let item = reader.value(*item_type)?;
+                                if is_face {
+                                    corners.push(count(item)?);
+                                }
+                            }
+                        }
+                    }
+                }
+
+                if let Some([x, y, z]) = position {
+                    vertices.push(pt(values[x], values[y], values[z]));
+
+                    if let Some([x, y, z]) = normal {
+                        normals.push(v(values[x], values[y], values[z]));
+                    }
+                }
+
+                if element.name == "face" {
+                    if corners.len() < 3 {
+                        return Err(invalid_data("a PLY face needs at least three corners"));
+                    }
+
+                    for pair in corners[1..].windows(2) {
+                        triangles.push([corners[0], pair[0], pair[1]]);
+                    }
+                }
+            }
+        }
+
+        if triangles.iter().flatten().any(|&i| i >= vertices.len()) {
+            return Err(invalid_data("a PLY face refers to a missing vertex"));
+        }
+
+        let mesh = Mesh::new(vertices, triangles);
+
+        Ok(if normals.is_empty() {
+            mesh
+        } else {
+            mesh.normals(normals)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    // a square split in two, and a triangle standing up from its edge
+    const SQUARE: &str = "ply
+format ascii 1.0
+comment made by hand
+element vertex 5
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+element face 2
+property list uchar int vertex_indices
+element material 1
+property uchar red
+end_header
+0 0 0 1 0 0
+1 0 0 1 0 0
+1 0 1 1 0 0
+0 0 1 1 0 0
+0 1 0 1 0 0
+4 0 1 2 3
+3 0 3 4
+255
+";
+
+    fn binary(format: &str, bytes: fn(F) -> Vec<u8>) -> Vec<u8> {
+        let mut data = format!(
+            "ply
+format {format} 1.0
+element vertex 4
+property double x
+property double y
+property double z
+element face 2
+property list uchar ushort vertex_indices
+end_header
+"
+        )
+        .into_bytes();
+
+        for corner in [[0, 0, 0], [1, 0, 0], [0, 0, 1], [0, 2, 0]] {
+            for c in corner {
+                data.extend(bytes(F::from(c)));
+            }
+        }
+        for face in [[0, 1, 2], [1, 2, 3]] {
+            data.push(3);
+            for i in face {
+                let i = i as u16;
+                data.extend(if format.contains("little") {
+                    i.to_le_bytes()
+                } else {
+                    i.to_be_bytes()
+                });
+            }
+        }
+
+        data
+    }
+
+    #[test]
+    fn reading_an_ascii_ply_file() {
+        let mesh = Mesh::from_ply(&mut SQUARE.as_bytes()).unwrap();
+
+        assert_eq!(mesh.vertices().len(), 5);
+        assert_eq!(mesh.vertices()[2], pt(1, 0, 1));
+        assert_eq!(mesh.triangles(), [[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+
+        let xs = mesh.local_intersect(ray(pt(0.7, 5, 0.2), v(0, -1, 0)));
+        assert_eq!(xs.len(), 1);
+        assert_fuzzy_eq!(xs[0].t, 5.0);
+    }
+
+    #[test]
+    fn a_ply_file_with_normals_gives_a_smooth_mesh() {
+        let mesh = Mesh::from_ply(&mut SQUARE.as_bytes()).unwrap();
+
+        let n = mesh.local_normal_at(pt(0.5, 0, 0.5)).normalize();
+
+        assert_fuzzy_eq!(n, v(1, 0, 0));
+    }
+
+    #[test]
+    fn reading_binary_ply_files() {
+        for data in [
+            binary("binary_little_endian", |c| c.to_le_bytes().to_vec()),
+            binary("binary_big_endian", |c| c.to_be_bytes().to_vec()),
+        ] {
+            let mesh = Mesh::from_ply(&mut data.as_slice()).unwrap();
+
+            assert_eq!(
+                mesh.vertices(),
+                [pt(0, 0, 0), pt(1, 0, 0), pt(0, 0, 1), pt(0, 2, 0)]
+            );
+            assert_eq!(mesh.triangles(), [[0, 1, 2], [1, 2, 3]]);
+        }
+    }
+
+    #[test]
+    fn elements_without_properties_are_skipped() {
+        let ply = SQUARE.replace(
+            "element material 1",
+            "element nothing 1000000000000\nelement material 1",
+        );
+
+        let mesh = Mesh::from_ply(&mut ply.as_bytes()).unwrap();
+
+        assert_eq!(mesh.triangles().len(), 3);
+    }
+
+    #[test]
+    fn reading_a_broken_ply_file() {
+        let whole = binary("binary_little_endian", |c| c.to_le_bytes().to_vec());
+
+        for (bytes, kind) in [
+            (b"".to_vec(), ErrorKind::UnexpectedEof),
+            (b"solid\n".to_vec(), ErrorKind::InvalidData),
+            (SQUARE.as_bytes()[..40].to_vec(), ErrorKind::UnexpectedEof),
+            (whole[..whole.len() - 1].to_vec(), ErrorKind::UnexpectedEof),
+            (
+                SQUARE.replace("255\n", "").into_bytes(),
+                ErrorKind::UnexpectedEof,
+            ),
+            (
+                SQUARE
+                    .replace("ascii 1.0", "binary_middle_endian 1.0")
+                    .into_bytes(),
+                ErrorKind::InvalidData,
+            ),
+            (
+                SQUARE.replace("float nz", "float128 nz").into_bytes(),
+                ErrorKind::InvalidData,
+            ),
+            (
+                SQUARE.replace("property float y\n", "").into_bytes(),
+                ErrorKind::InvalidData,
+            ),
+            (
+                SQUARE.replace("element vertex 5\n", "").into_bytes(),
+                ErrorKind::InvalidData,
+            ),
+            (
+                SQUARE.replace("0 3 4", "0 3 5").into_bytes(),
+                ErrorKind::InvalidData,
+            ),
+            (
+                SQUARE.replace("3 0 3 4", "2 0 3").into_bytes(),
+                ErrorKind::InvalidData,
+            ),
+            (
+                SQUARE.replace("0 1 0 1", "0 l 0 1").into_bytes(),
+                ErrorKind::InvalidData,
+            ),
+            (
+                SQUARE
+                    .replace("element face 2", "element face 1000000000000")
+                    .into_bytes(),
+                ErrorKind::UnexpectedEof,
+            ),
+            (
+                SQUARE
+                    .replace("ascii 1.0", "binary_little_endian 1.0")
+                    .replace("vertex 5", "vertex 4000000000000000000")
+                    .into_bytes(),
+                ErrorKind::UnexpectedEof,
+            ),
+        ] {
+            let error = Mesh::from_ply(&mut bytes.as_slice()).unwrap_err();
+
+            assert_eq!(error.kind(), kind);
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use crate::roots::solve_quadratic;
+use crate::{pt, v, BoundingBox, Intersection, Matrix, Props, Ray, Shape, Tuple, F};
+
+/// The surface where
+/// `a x² + b y² + c z² + d xy + e xz + f yz + g x + h y + i z + j = 0`,
+/// with the coefficients given in that order. Paraboloids, hyperboloids and
+/// the like reach out forever, so they can be clipped to a box.
+#[derive(Debug)]
+pub struct Quadric {
+    coefficients: [F; 10],
+    clip: Option<BoundingBox>,
+    props: Props,
+}
+
+impl Quadric {
+    #[must_use]
+    pub fn coefficients(mut self, coefficients: [F; 10]) -> Self {
+        self.coefficients = coefficients;
+
+        self
+    }
+
+    /// Only keep the part of the surface inside `clip`.
+    #[must_use]
+    pub fn clip(mut self, clip: BoundingBox) -> Self {
+        self.clip = Some(clip);
+
+        self
+    }
+}
+
+/// The unit sphere.
+impl Default for Quadric {
+    fn default() -> Self {
+        Self {
+            coefficients: [1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0],
+            clip: None,
+            props: Props::default(),
+        }
+    }
+}
+
+impl Shape for Quadric {
+    fn props(&self) -> &Props {
+        &self.props
+    }
+
+    fn props_mut(&mut self) -> &mut Props {
+        &mut self.props
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        let [a, b, c, d, e, f, g, h, i, j] = self.coefficients;
+        let Tuple {
+            x: ox,
+            y: oy,
+            z: oz,
+            ..
+        } = ray.origin;
+        let Tuple {
+            x: dx,
+            y: dy,
+            z: dz,
+            ..
+        } = ray.direction;
+
+        // substitute the ray into the equation and collect the powers of t
+        let t2 = a * dx * dx + b * dy * dy + c * dz * dz + d * dx * dy + e * dx * dz + f * dy * dz;
+        let t1 = 2.0 * (a * ox * dx + b * oy * dy + c * oz * dz)
+            + d * (ox * dy + oy * dx)
+            + e * (ox * dz + oz * dx)
+            + f * (oy * dz + oz * dy)
+            + g * dx
+            + h * dy
+            + i * dz;
+        let t0 = a * ox * ox
+            + b * oy * oy
+            + c * oz * oz
+            + d * ox * oy
+            + e * ox * oz
+            + f * oy * oz
+            + g * ox
+            + h * oy
+            + i * oz
+            + j;
+
+        solve_quadratic(t2, t1, t0)
+            .into_iter()
+            .filter(|&t| {
+                self.clip
+                    .is_none_or(|clip| clip.contains_point(ray.position(t)))
+            })
+            .map(|t| self.intersection(t))
+            .collect()
+    }
+
+    fn local_normal_at(&self, Tuple { x, y, z, .. }: Tuple) -> Tuple {
+        // the gradient of the equation
+        let [a, b, c, d, e, f, g, h, i, _] = self.coefficients;
+
+        v(
+            2.0 * a * x + d * y + e * z + g,
+            2.0 * b * y + d * x + f * z + h,
+            2.0 * c * z + e * x + f * y + i,
+        )
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.clip
+            .or_else(|| self.ellipsoid_bounds())
+            .unwrap_or_else(BoundingBox::infinite)
+    }
+}
+
+impl Quadric {
+    // the box around the surface when it's an ellipsoid, the only kind of
+    // quadric that doesn't reach out forever
+    fn ellipsoid_bounds(&self) -> Option<BoundingBox> {
+        // flipping every sign leaves the surface the same
+        let sign = if self.coefficients[0] < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        let [a, b, c, d, e, f, g, h, i, j] = self.coefficients.map(|k| k * sign);
+
+        // the quadratic part as a symmetric matrix, which has to be
+        // positive definite for the surface to close
+        let m = Matrix::from([
+            [a, d / 2.0, e / 2.0],
+            [d / 2.0, b, f / 2.0],
+            [e / 2.0, f / 2.0, c],
+        ]);
+        let det = m.determinant();
+
+        if a <= 0.0 || a * b - d * d / 4.0 <= 0.0 || det <= 0.0 {
+            return None;
+        }
+
+        let inverse = |row, col| m.cofactor(col, row) / det;
+
+        // moving the origin to the center leaves `pᵀ m p = k`, which reaches
+        // `sqrt(k m⁻¹ᵢᵢ)` along each axis
+        let linear = [g, h, i];
+        let [x, y, z] = [0, 1, 2]
+            .map(|row| -(0..3).map(|col| inverse(row, col) * linear[col]).sum::<F>() / 2.0);
+        let k = -(x * g + y * h + z * i) / 2.0 - j;
+
+        if k < 0.0 {
+            return Some(BoundingBox::default());
+        }
+
+        let center = pt(x, y, z);
+        let [x, y, z] = [0, 1, 2].map(|n| (k * inverse(n, n)).sqrt());
+        let reach = v(x, y, z);
+
+        Some(BoundingBox::new(center - reach, center + reach))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    // y = x² + z²
+    const PARABOLOID: [F; 10] = [1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0];
+
+    #[test]
+    fn the_default_quadric_is_a_unit_sphere() {
+        let q = Quadric::default();
+        let s = Sphere::default();
+
+        for (origin, direction) in [
+            (pt(0, 0, -5), v(0, 0, 1)),
+            (pt(0, 2, -5), v(0, 0, 1)),
+            (pt(0, 0, 0), v(0, 0, 1)),
+            (pt(-3, -2, -5), v(1, 0.5, 2)),
+        ] {
+            let r = ray(origin, direction);
+            let expected = s.local_intersect(r);
+            let xs = q.local_intersect(r);
+
+            assert_eq!(xs.len(), expected.len());
+            for (i, e) in xs.iter().zip(expected) {
+                assert_fuzzy_eq!(i.t, e.t);
+            }
+        }
+
+        assert_fuzzy_eq!(q.local_normal_at(pt(0, 0, -1)).normalize(), v(0, 0, -1));
+    }
+
+    #[test]
+    fn intersecting_a_paraboloid() {
+        let q = Quadric::default().coefficients(PARABOLOID);
+
+        // straight down the axis the equation is only linear in t
+        let xs = q.local_intersect(ray(pt(0, 5, 0), v(0, -1, 0)));
+        assert_eq!(xs.len(), 1);
+        assert_fuzzy_eq!(xs[0].t, 5.0);
+
+        let xs = q.local_intersect(ray(pt(-5, 1, 0), v(1, 0, 0)));
+        assert_eq!(xs.len(), 2);
+        assert_fuzzy_eq!(xs[0].t, 4.0);
+        assert_fuzzy_eq!(xs[1].t, 6.0);
+
+        assert!(q.local_intersect(ray(pt(-5, -1, 0), v(1, 0, 0))).is_empty());
+    }
+
+    #[test]
+    fn the_normal_on_a_paraboloid() {
+        let q = Quadric::default().coefficients(PARABOLOID);
+
+        let n = q.local_normal_at(pt(1, 1, 0)).normalize();
+
+        assert_fuzzy_eq!(n, v(2, -1, 0).normalize());
+    }
+
+    #[test]
+    fn a_clipped_quadric_ignores_hits_outside_the_box() {
+        let q = Quadric::default()
+            .coefficients(PARABOLOID)
+            .clip(BoundingBox::new(pt(-2, 0, -2), pt(2, 4, 2)));
+
+        assert!(q.local_intersect(ray(pt(-5, 9, 0), v(1, 0, 0))).is_empty());
+
+        let xs = q.local_intersect(ray(pt(-5, 1, 0), v(1, 0, 0)));
+        assert_eq!(xs.len(), 2);
+
+        // only one half of the paraboloid is left
+        let q = q.clip(BoundingBox::new(pt(-2, 0, 0), pt(2, 4, 2)));
+        let xs = q.local_intersect(ray(pt(-1, 4, -5), v(0, 0, 1)));
+        assert_eq!(xs.len(), 1);
+        assert_fuzzy_eq!(xs[0].t, 5.0 + F::sqrt(3.0));
+    }
+
+    #[test]
+    fn the_bounds_of_a_quadric() {
+        let clip = BoundingBox::new(pt(-2, 0, -2), pt(2, 4, 2));
+
+        assert!(!Quadric::default()
+            .coefficients(PARABOLOID)
+            .bounds()
+            .is_finite());
+        assert_eq!(Quadric::default().clip(clip).bounds(), clip);
+    }
+
+    #[test]
+    fn an_ellipsoid_is_bounded_without_a_clip() {
+        let check = |coefficients, min, max| {
+            let bounds = Quadric::default().coefficients(coefficients).bounds();
+
+            assert_fuzzy_eq!(bounds.min, min);
+            assert_fuzzy_eq!(bounds.max, max);
+        };
+
+        check(Quadric::default().coefficients, pt(-1, -1, -1), pt(1, 1, 1));
+
+        // (x - 1)² / 4 + y² + z² / 9 = 1, with the signs either way round
+        let ellipsoid = [0.25, 1.0, 1.0 / 9.0, 0.0, 0.0, 0.0, -0.5, 0.0, 0.0, -0.75];
+        check(ellipsoid, pt(-1, -1, -3), pt(3, 1, 3));
+        check(ellipsoid.map(|k| -k), pt(-1, -1, -3), pt(3, 1, 3));
+
+        // the xy term squashes the sphere into an ellipsoid tilted between
+        // the x and y axes
+        let reach = F::sqrt(4.0 / 3.0);
+        check(
+            [1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0],
+            pt(-reach, -reach, -1),
+            pt(reach, reach, 1),
+        );
+    }
+}
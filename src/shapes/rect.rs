@@ -0,0 +1,144 @@
+use crate::{pt, v, BoundingBox, Intersection, Props, Ray, Shape, Tuple, EPSILON, F};
+
+/// A flat rectangle in the xz plane centered on the origin, `width` along
+/// the x axis and `depth` along the z axis.
+#[derive(Debug)]
+pub struct Rect {
+    width: F,
+    depth: F,
+    props: Props,
+}
+
+impl Rect {
+    #[must_use]
+    pub fn width(mut self, width: impl Into<F>) -> Self {
+        self.width = width.into();
+
+        self
+    }
+
+    #[must_use]
+    pub fn depth(mut self, depth: impl Into<F>) -> Self {
+        self.depth = depth.into();
+
+        self
+    }
+}
+
+impl Default for Rect {
+    fn default() -> Self {
+        Self {
+            width: 2.0,
+            depth: 2.0,
+            props: Props::default(),
+        }
+    }
+}
+
+impl Shape for Rect {
+    fn props(&self) -> &Props {
+        &self.props
+    }
+
+    fn props_mut(&mut self) -> &mut Props {
+        &mut self.props
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        if ray.direction.y.abs() < EPSILON {
+            return vec![];
+        }
+
+        let t = -ray.origin.y / ray.direction.y;
+
+        let x = ray.origin.x + t * ray.direction.x;
+        let z = ray.origin.z + t * ray.direction.z;
+
+        if x.abs() > self.width / 2.0 || z.abs() > self.depth / 2.0 {
+            return vec![];
+        }
+
+        vec![self.intersection(t)]
+    }
+
+    fn local_normal_at(&self, _point: Tuple) -> Tuple {
+        v(0, 1, 0)
+    }
+
+    fn local_tangent_at(&self, _point: Tuple) -> Tuple {
+        v(1, 0, 0)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let (x, z) = (self.width / 2.0, self.depth / 2.0);
+
+        BoundingBox::new(pt(-x, 0, -z), pt(x, 0, z))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn the_default_rect() {
+        let r = Rect::default();
+
+        assert_eq!(r.width, 2.0);
+        assert_eq!(r.depth, 2.0);
+    }
+
+    #[test]
+    fn a_ray_intersects_a_rect() {
+        let r = Rect::default();
+
+        for (origin, direction, t) in [
+            (pt(0, 1, 0), v(0, -1, 0), 1.0),
+            (pt(0.9, -2, -0.9), v(0, 1, 0), 2.0),
+            (pt(-1, 2, 0), v(0, -1, 0.25), 2.0),
+        ] {
+            let xs = r.local_intersect(ray(origin, direction));
+
+            assert_eq!(xs.len(), 1);
+            assert_fuzzy_eq!(xs[0].t, t);
+        }
+    }
+
+    #[test]
+    fn a_ray_misses_a_rect() {
+        let r = Rect::default().width(4).depth(1);
+
+        for (origin, direction) in [
+            // parallel to the rect
+            (pt(0, 1, 0), v(1, 0, 0)),
+            // past its edges
+            (pt(2.5, 1, 0), v(0, -1, 0)),
+            (pt(0, 1, 0.75), v(0, -1, 0)),
+        ] {
+            let xs = r.local_intersect(ray(origin, direction));
+
+            assert!(xs.is_empty());
+        }
+
+        assert_eq!(
+            r.local_intersect(ray(pt(1.5, 1, 0.25), v(0, -1, 0))).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn the_normal_of_a_rect_is_constant() {
+        let r = Rect::default();
+
+        assert_eq!(r.local_normal_at(pt(0, 0, 0)), v(0, 1, 0));
+        assert_eq!(r.local_normal_at(pt(-1, 0, 1)), v(0, 1, 0));
+    }
+
+    #[test]
+    fn the_bounds_of_a_rect() {
+        let r = Rect::default().width(4).depth(1);
+
+        assert_eq!(r.bounds(), BoundingBox::new(pt(-2, 0, -0.5), pt(2, 0, 0.5)));
+    }
+}
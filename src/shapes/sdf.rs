@@ -0,0 +1,410 @@
+use crate::{pt, v, Intersection, Props, Ray, Shape, Tuple, EPSILON, F};
+use std::fmt;
+use std::sync::Arc;
+
+// how close to the surface sphere tracing has to get to count as a hit, well
+// below the offset used for over and under points
+const HIT_DISTANCE: F = EPSILON / 10.0;
+
+/// A shape given by a signed distance function: negative inside the shape,
+/// positive outside, and never more than the distance to the surface.
+/// Rays are intersected with it by sphere tracing.
+#[derive(Debug)]
+pub struct Sdf {
+    node: SdfNode,
+    max_steps: usize,
+    max_distance: F,
+    props: Props,
+}
+
+impl Sdf {
+    #[must_use]
+    pub fn new(node: SdfNode) -> Self {
+        Self {
+            node,
+            ..Default::default()
+        }
+    }
+
+    /// How many steps a ray may take before giving up on it.
+    #[must_use]
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+
+        self
+    }
+
+    /// How far from its origin a ray is followed.
+    #[must_use]
+    pub fn max_distance(mut self, max_distance: impl Into<F>) -> Self {
+        self.max_distance = max_distance.into();
+
+        self
+    }
+
+    // Every distance along `direction` (a unit vector) at which the surface
+    // is crossed.
+    fn march(&self, origin: Tuple, direction: Tuple) -> Vec<F> {
+        let mut hits = vec![];
+        let mut s = 0.0;
+        let mut on_surface = false;
+
+        for _ in 0..self.max_steps {
+            if s > self.max_distance {
+                break;
+            }
+
+            let distance = self.node.distance(origin + direction * s).abs();
+
+            if distance < HIT_DISTANCE {
+                // don't count the same crossing again until the ray has left
+                // the surface behind
+                if !on_surface {
+                    hits.push(s);
+                    on_surface = true;
+                }
+            } else {
+                on_surface = false;
+            }
+
+            // inside the shape the distance is just as safe a step
+            s += distance.max(HIT_DISTANCE);
+        }
+
+        hits
+    }
+}
+
+/// The unit sphere.
+impl Default for Sdf {
+    fn default() -> Self {
+        Self {
+            node: SdfNode::sphere(1),
+            max_steps: 256,
+            max_distance: 1000.0,
+            props: Props::default(),
+        }
+    }
+}
+
+impl Shape for Sdf {
+    fn props(&self) -> &Props {
+        &self.props
+    }
+
+    fn props_mut(&mut self) -> &mut Props {
+        &mut self.props
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        // march over unit distances, then scale back to the ray's own
+        let length = ray.direction.magnitude();
+        let direction = ray.direction / length;
+
+        // the hits behind the origin are needed to know whether the ray
+        // starts inside the shape
+        let behind = self.march(ray.origin, -direction);
+        let ahead = self.march(ray.origin, direction);
+
+        behind
+            .into_iter()
+            .rev()
+            .map(|s| -s)
+            .chain(ahead)
+            .map(|s| self.intersection(s / length))
+            .collect()
+    }
+
+    fn local_normal_at(&self, point: Tuple) -> Tuple {
+        // the gradient of the distance, by central differences
+        let h = EPSILON;
+        let d =
+            |offset: Tuple| self.node.distance(point + offset) - self.node.distance(point - offset);
+
+        v(d(v(h, 0, 0)), d(v(0, h, 0)), d(v(0, 0, h)))
+    }
+}
+
+/// A distance function that can be built from simple pieces.
+#[derive(Debug, Clone)]
+pub enum SdfNode {
+    Sphere(F),
+    /// A box given by its half extents.
+    Cuboid(Tuple),
+    /// A box given by its half extents, with edges rounded by the radius.
+    RoundCuboid(Tuple, F),
+    Translate(Box<SdfNode>, Tuple),
+    Union(Box<SdfNode>, Box<SdfNode>),
+    /// Two shapes melted together, the blend reaching as far as the factor.
+    /// A factor of 0 or less leaves them a plain union.
+    SmoothUnion(Box<SdfNode>, Box<SdfNode>, F),
+    /// A shape twisted around the y axis by the given angle per unit of height.
+    Twist(Box<SdfNode>, F),
+    Function(DistanceFn),
+}
+
+/// A distance function written as a closure.
+#[derive(Clone)]
+pub struct DistanceFn(Arc<dyn Fn(Tuple) -> F + Send + Sync>);
+
+impl fmt::Debug for DistanceFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DistanceFn")
+    }
+}
+
+impl SdfNode {
+    #[must_use]
+    pub fn sphere(radius: impl Into<F>) -> Self {
+        Self::Sphere(radius.into())
+    }
+
+    #[must_use]
+    pub fn cuboid(x: impl Into<F>, y: impl Into<F>, z: impl Into<F>) -> Self {
+        Self::Cuboid(v(x, y, z))
+    }
+
+    #[must_use]
+    pub fn round_cuboid(
+        x: impl Into<F>,
+        y: impl Into<F>,
+        z: impl Into<F>,
+        radius: impl Into<F>,
+    ) -> Self {
+        Self::RoundCuboid(v(x, y, z), radius.into())
+    }
+
+    #[must_use]
+    pub fn function(f: impl Fn(Tuple) -> F + Send + Sync + 'static) -> Self {
+        Self::Function(DistanceFn(Arc::new(f)))
+    }
+
+    #[must_use]
+    pub fn translate(self, x: impl Into<F>, y: impl Into<F>, z: impl Into<F>) -> Self {
+        Self::Translate(Box::new(self), v(x, y, z))
+    }
+
+    #[must_use]
+    pub fn union(self, other: SdfNode) -> Self {
+        Self::Union(Box::new(self), Box::new(other))
+    }
+
+    #[must_use]
+    pub fn smooth_union(self, other: SdfNode, smoothness: impl Into<F>) -> Self {
+        Self::SmoothUnion(Box::new(self), Box::new(other), smoothness.into())
+    }
+
+    #[must_use]
+    pub fn twist(self, rate: impl Into<F>) -> Self {
+        Self::Twist(Box::new(self), rate.into())
+    }
+
+    /// The signed distance from `point` to the surface.
+    #[must_use]
+    pub fn distance(&self, point: Tuple) -> F {
+        match self {
+            Self::Sphere(radius) => length(point.x, point.y, point.z) - radius,
+            Self::Cuboid(half) => cuboid_distance(point, *half),
+            Self::RoundCuboid(half, radius) => {
+                cuboid_distance(point, *half - v(*radius, *radius, *radius)) - radius
+            }
+            Self::Translate(node, offset) => node.distance(point - *offset),
+            Self::Union(a, b) => a.distance(point).min(b.distance(point)),
+            Self::SmoothUnion(a, b, k) => {
+                let (da, db) = (a.distance(point), b.distance(point));
+
+                // without any smoothness there's nothing to blend
+                if *k <= 0.0 {
+                    return da.min(db);
+                }
+
+                let h = (0.5 + 0.5 * (db - da) / k).clamp(0.0, 1.0);
+
+                db + (da - db) * h - k * h * (1.0 - h)
+            }
+            Self::Twist(node, rate) => {
+                let Tuple { x, y, z, .. } = point;
+                let (sin, cos) = (rate * y).sin_cos();
+                let twisted = pt(cos * x - sin * z, y, sin * x + cos * z);
+
+                // twisting stretches space, so scale the distance down to
+                // keep the steps safe
+                let stretch = (1.0 + (rate * length(x, 0.0, z)).powi(2)).sqrt();
+
+                node.distance(twisted) / stretch
+            }
+            Self::Function(DistanceFn(f)) => f(point),
+        }
+    }
+}
+
+fn length(x: F, y: F, z: F) -> F {
+    (x * x + y * y + z * z).sqrt()
+}
+
+fn cuboid_distance(point: Tuple, half: Tuple) -> F {
+    let q = [
+        point.x.abs() - half.x,
+        point.y.abs() - half.y,
+        point.z.abs() - half.z,
+    ];
+
+    let outside = length(q[0].max(0.0), q[1].max(0.0), q[2].max(0.0));
+    let inside = q[0].max(q[1]).max(q[2]).min(0.0);
+
+    outside + inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn distances_to_simple_shapes() {
+        let sphere = SdfNode::sphere(2);
+        assert_fuzzy_eq!(sphere.distance(pt(0, 0, 5)), 3.0);
+        assert_fuzzy_eq!(sphere.distance(pt(1, 0, 0)), -1.0);
+
+        let cuboid = SdfNode::cuboid(1, 2, 3);
+        assert_fuzzy_eq!(cuboid.distance(pt(3, 0, 0)), 2.0);
+        assert_fuzzy_eq!(cuboid.distance(pt(0, 0, 0)), -1.0);
+        assert_fuzzy_eq!(cuboid.distance(pt(4, 6, 3)), 5.0);
+
+        let rounded = SdfNode::round_cuboid(1, 1, 1, 0.5);
+        assert_fuzzy_eq!(rounded.distance(pt(3, 0, 0)), 2.0);
+        assert_fuzzy_eq!(rounded.distance(pt(2, 2, 0)), 1.5 * F::sqrt(2.0) - 0.5);
+    }
+
+    #[test]
+    fn combining_distances() {
+        let a = SdfNode::sphere(1).translate(-1, 0, 0);
+        let b = SdfNode::sphere(1).translate(1.5, 0, 0);
+
+        let union = a.clone().union(b.clone());
+        assert_fuzzy_eq!(union.distance(pt(-3, 0, 0)), 1.0);
+        assert_fuzzy_eq!(union.distance(pt(4, 0, 0)), 1.5);
+
+        // the smooth union bulges out where the shapes meet
+        let smooth = a.smooth_union(b, 0.5);
+        assert!(smooth.distance(pt(0.25, 0, 0)) < union.distance(pt(0.25, 0, 0)));
+        assert_fuzzy_eq!(smooth.distance(pt(-3, 0, 0)), 1.0);
+
+        // and without any smoothness it's the plain union
+        let sharp = SdfNode::sphere(1)
+            .translate(-1, 0, 0)
+            .smooth_union(SdfNode::sphere(1).translate(1.5, 0, 0), 0);
+        assert_eq!(
+            sharp.distance(pt(0.25, 0, 0)),
+            union.distance(pt(0.25, 0, 0))
+        );
+    }
+
+    #[test]
+    fn twisting_a_distance() {
+        let twisted = SdfNode::cuboid(1, 5, 0.25).twist(PI / 2.0);
+
+        // unchanged on the axis
+        assert_fuzzy_eq!(twisted.distance(pt(0, 0, 0)), -0.25);
+        assert!(twisted.distance(pt(0, 0, 0.5)) > 0.0);
+        assert!(twisted.distance(pt(0.5, 0, 0)) < 0.0);
+
+        // a quarter turn at y = 1
+        assert!(twisted.distance(pt(0, 1, 0.5)) < 0.0);
+        assert!(twisted.distance(pt(0.5, 1, 0)) > 0.0);
+    }
+
+    #[test]
+    fn a_distance_from_a_closure() {
+        let plane = SdfNode::function(|p| p.y);
+
+        assert_eq!(plane.distance(pt(3, 2, 1)), 2.0);
+    }
+
+    #[test]
+    fn a_ray_intersects_an_sdf() {
+        let s = Sdf::default();
+
+        let xs = s.local_intersect(ray(pt(0, 0, -5), v(0, 0, 1)));
+
+        assert_eq!(xs.len(), 2);
+        assert_fuzzy_eq!(xs[0].t, 4.0);
+        assert_fuzzy_eq!(xs[1].t, 6.0);
+    }
+
+    #[test]
+    fn a_ray_misses_an_sdf() {
+        let s = Sdf::default();
+
+        let xs = s.local_intersect(ray(pt(0, 2, -5), v(0, 0, 1)));
+
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn an_sdf_is_intersected_behind_the_ray_too() {
+        let s = Sdf::new(SdfNode::cuboid(1, 1, 1));
+
+        let xs = s.local_intersect(ray(pt(0, 0, 0), v(0, 0, 2)));
+
+        assert_eq!(xs.len(), 2);
+        assert_fuzzy_eq!(xs[0].t, -0.5);
+        assert_fuzzy_eq!(xs[1].t, 0.5);
+    }
+
+    #[test]
+    fn sphere_tracing_gives_up_after_the_step_limit() {
+        let s = Sdf::default().max_steps(3);
+
+        // the ray crawls along the surface without ever reaching it
+        let xs = s.local_intersect(ray(pt(-5, 1.0001, 0), v(1, 0, 0)));
+
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn sphere_tracing_stops_at_the_maximum_distance() {
+        let s = Sdf::default().max_distance(3);
+
+        let xs = s.local_intersect(ray(pt(0, 0, -5), v(0, 0, 1)));
+
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn the_normal_on_an_sdf() {
+        let s = Sdf::new(SdfNode::cuboid(1, 1, 1));
+        let h = F::sqrt(3.0) / 3.0;
+
+        for (point, normal) in [
+            (pt(1, 0.5, 0), v(1, 0, 0)),
+            (pt(0.2, -1, 0.3), v(0, -1, 0)),
+            (pt(0, 0.5, -1), v(0, 0, -1)),
+        ] {
+            assert_fuzzy_eq!(s.local_normal_at(point).normalize(), normal);
+        }
+
+        let s = Sdf::default();
+        assert_fuzzy_eq!(s.local_normal_at(pt(h, h, h)).normalize(), v(h, h, h));
+    }
+
+    #[test]
+    fn an_sdf_in_a_world() {
+        let mut w = World::new();
+        w.objects.push(
+            Sdf::default()
+                .transform(Matrix::translation(0, 0, 1))
+                .into(),
+        );
+        w.lights.push(point_light(pt(-10, 10, -10), WHITE));
+        let r = ray(pt(0, 0, -5), v(0, 0, 1));
+
+        let xs = w.intersect(r);
+        let comps = xs[0].prepare_computations(r, &xs);
+
+        assert_eq!(xs.len(), 2);
+        assert_fuzzy_eq!(xs[0].t, 5.0);
+        assert_fuzzy_eq!(xs[1].t, 7.0);
+        assert_fuzzy_eq!(comps.normalv, v(0, 0, -1));
+    }
+}
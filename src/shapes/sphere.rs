@@ -1,4 +1,4 @@
-use crate::{pt, Intersection, Props, Ray, Shape, Tuple};
+use crate::{pt, v, BoundingBox, Intersection, Props, Ray, Shape, Tuple, EPSILON};
 
 #[must_use]
 pub fn glass() -> Sphere {
@@ -22,7 +22,7 @@ impl Shape for Sphere {
         &mut self.props
     }
 
-    fn local_intersect(&self, ray: Ray) -> Vec<Intersection> {
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
         let sphere_to_ray = ray.origin - pt(0, 0, 0);
 
         let a = ray.direction.dot(ray.direction);
@@ -47,6 +47,20 @@ impl Shape for Sphere {
     fn local_normal_at(&self, point: Tuple) -> Tuple {
         point - pt(0, 0, 0)
     }
+
+    fn local_tangent_at(&self, Tuple { x, z, .. }: Tuple) -> Tuple {
+        // u runs counterclockwise around the y axis, and is undefined at
+        // the poles
+        if x.abs() < EPSILON && z.abs() < EPSILON {
+            return v(1, 0, 0);
+        }
+
+        v(-z, 0, x).normalize()
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(pt(-1, -1, -1), pt(1, 1, 1))
+    }
 }
 
 #[cfg(test)]
@@ -237,8 +251,23 @@ mod tests {
             ..Default::default()
         };
 
-        let s = Sphere::default().material(m);
+        let s = Sphere::default().material(m.clone());
 
         assert_eq!(s.props.material, m);
     }
+
+    #[test]
+    fn the_tangent_on_a_sphere_follows_the_equator() {
+        let s = Sphere::default();
+
+        for (point, tangent) in [
+            (pt(0, 0, -1), v(1, 0, 0)),
+            (pt(1, 0, 0), v(0, 0, 1)),
+            (pt(0, 0, 1), v(-1, 0, 0)),
+            (pt(0.6, 0.8, 0), v(0, 0, 1)),
+            (pt(0, 1, 0), v(1, 0, 0)),
+        ] {
+            assert_fuzzy_eq!(s.local_tangent_at(point), tangent);
+        }
+    }
 }
@@ -0,0 +1,238 @@
+use crate::{pt, Mesh, Tuple, F};
+use std::collections::HashMap;
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult};
+
+impl Mesh {
+    /// Reads an ASCII or binary STL file. STL stores every triangle with
+    /// its own copy of its corners, so corners at the same position are
+    /// merged into one vertex.
+    pub fn from_stl(reader: &mut impl Read) -> IoResult<Self> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+
+        // binary files may start with "solid" too, but only they have
+        // exactly the size their triangle count calls for
+        let binary_size = bytes.get(80..84).map(|count| {
+            84 + 50 * u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize
+        });
+
+        let corners = if bytes.starts_with(b"solid") && binary_size != Some(bytes.len()) {
+            read_ascii(&bytes)?
+        } else {
+            read_binary(&bytes)?
+        };
+
+        let mut vertices = vec![];
+        let mut seen = HashMap::new();
+        let indices = corners
+            .into_iter()
+            .map(|corner| {
+                // adding zero turns -0.0 into 0.0 so that both are merged
+                let key = [corner.x, corner.y, corner.z].map(|c| (c + 0.0).to_bits());
+
+                *seen.entry(key).or_insert_with(|| {
+                    vertices.push(corner);
+                    vertices.len() - 1
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let triangles = indices
+            .chunks_exact(3)
+            .map(|c| [c[0], c[1], c[2]])
+            .collect();
+
+        Ok(Mesh::new(vertices, triangles))
+    }
+}
+
+fn invalid_data(message: &str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, message)
+}
+
+fn truncated() -> IoError {
+    IoError::new(ErrorKind::UnexpectedEof, "the STL file is truncated")
+}
+
+// the corners of every triangle, three by three
+fn read_binary(bytes: &[u8]) -> IoResult<Vec<Tuple>> {
+    let count = bytes.get(80..84).ok_or_else(truncated)?;
+    let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize;
+
+    let data = bytes.get(84..84 + 50 * count).ok_or_else(truncated)?;
+
+    let float = |b: &[u8]| F::from(f32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+
+    // every triangle is a normal, three corners and two unused bytes
+    Ok(data
+        .chunks_exact(50)
+        .flat_map(|triangle| {
+            triangle[12..48]
+                .chunks_exact(12)
+                .map(|c| pt(float(&c[0..4]), float(&c[4..8]), float(&c[8..12])))
+                .collect::<Vec<_>>()
+        })
+        .collect())
+}
+
+fn read_ascii(bytes: &[u8]) -> IoResult<Vec<Tuple>> {
+    let text = std::str::from_utf8(bytes).map_err(|_| invalid_data("the STL file is not text"))?;
+    let mut tokens = text.split_ascii_whitespace();
+    let mut corners = vec![];
+
+    expect_all(&mut tokens, &["solid"])?;
+
+    // skip the name of the solid
+    let mut token = tokens.find(|&t| t == "facet" || t == "endsolid");
+
+    loop {
+        match token {
+            Some("endsolid") => return Ok(corners),
+            Some("facet") => {}
+            Some(_) => return Err(invalid_data("expected `facet` in STL file")),
+            None => return Err(truncated()),
+        }
+
+        // the normal of the facet follows from the order of its corners
+        expect_all(&mut tokens, &["normal"])?;
+        for _ in 0..3 {
+            number(tokens.next())?;
+        }
+
+        expect_all(&mut tokens, &["outer", "loop"])?;
+        for _ in 0..3 {
+            expect_all(&mut tokens, &["vertex"])?;
+            corners.push(pt(
+                number(tokens.next())?,
+                number(tokens.next())?,
+                number(tokens.next())?,
+            ));
+        }
+        expect_all(&mut tokens, &["endloop", "endfacet"])?;
+
+        token = tokens.next();
+    }
+}
+
+fn expect_all<'a>(tokens: &mut impl Iterator<Item = &'a str>, words: &[&str]) -> IoResult<()> {
+    for word in words {
+        match tokens.next() {
+            Some(token) if token == *word => {}
+            Some(_) => return Err(invalid_data(&format!("expected `{word}` in STL file"))),
+            None => return Err(truncated()),
+        }
+    }
+
+    Ok(())
+}
+
+fn number(token: Option<&str>) -> IoResult<F> {
+    token
+        .ok_or_else(truncated)?
+        .parse()
+        .map_err(|_| invalid_data("malformed number in STL file"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    // two triangles sharing an edge
+    const SQUARE: &str = "solid a square
+facet normal 0 0 1
+  outer loop
+    vertex 0 0 0
+    vertex 1 0 0
+    vertex 1 1 0
+  endloop
+endfacet
+facet normal 0 0 1
+  outer loop
+    vertex 0 0 0
+    vertex 1 1 0
+    vertex 0 1 -0
+  endloop
+endfacet
+endsolid a square
+";
+
+    fn binary(triangles: &[[[f32; 3]; 3]]) -> Vec<u8> {
+        let mut bytes = b"solid binary, despite the name".to_vec();
+        bytes.resize(80, 0);
+        bytes.extend((triangles.len() as u32).to_le_bytes());
+
+        for triangle in triangles {
+            bytes.extend([0.0_f32; 3].iter().flat_map(|c| c.to_le_bytes()));
+            for corner in triangle {
+                bytes.extend(corner.iter().flat_map(|c| c.to_le_bytes()));
+            }
+            bytes.extend([0, 0]);
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn reading_an_ascii_stl_file() {
+        let mesh = Mesh::from_stl(&mut SQUARE.as_bytes()).unwrap();
+
+        assert_eq!(
+            mesh.vertices(),
+            [pt(0, 0, 0), pt(1, 0, 0), pt(1, 1, 0), pt(0, 1, 0)]
+        );
+        assert_eq!(mesh.triangles().len(), 2);
+        assert!(mesh.triangles().contains(&[0, 1, 2]));
+        assert!(mesh.triangles().contains(&[0, 2, 3]));
+    }
+
+    #[test]
+    fn reading_a_binary_stl_file() {
+        let bytes = binary(&[
+            [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+            [[1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 2.0, 0.0]],
+        ]);
+
+        let mesh = Mesh::from_stl(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(mesh.vertices().len(), 4);
+        assert_eq!(mesh.vertices()[3], pt(0, 2, 0));
+        assert_eq!(mesh.triangles().len(), 2);
+
+        let xs = mesh.local_intersect(ray(pt(0.2, 5, 0.2), v(0, -1, 0)));
+        assert_eq!(xs.len(), 2);
+    }
+
+    #[test]
+    fn reading_a_broken_stl_file() {
+        let whole = binary(&[[[0.0; 3]; 3]]);
+
+        for (bytes, kind) in [
+            (b"".to_vec(), ErrorKind::UnexpectedEof),
+            (whole[..100].to_vec(), ErrorKind::UnexpectedEof),
+            (SQUARE.as_bytes()[..60].to_vec(), ErrorKind::UnexpectedEof),
+            (
+                SQUARE.replace("endsolid a square\n", "").into_bytes(),
+                ErrorKind::UnexpectedEof,
+            ),
+            (
+                SQUARE.replacen("outer", "inner", 1).into_bytes(),
+                ErrorKind::InvalidData,
+            ),
+            (
+                SQUARE
+                    .replacen("vertex 1 0 0", "vertex 1 O 0", 1)
+                    .into_bytes(),
+                ErrorKind::InvalidData,
+            ),
+            (
+                SQUARE.replacen("endfacet", "facet", 1).into_bytes(),
+                ErrorKind::InvalidData,
+            ),
+        ] {
+            let error = Mesh::from_stl(&mut bytes.as_slice()).unwrap_err();
+
+            assert_eq!(error.kind(), kind);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use crate::roots::solve_quartic;
+use crate::{pt, v, BoundingBox, Intersection, Props, Ray, Shape, Tuple, EPSILON, F};
+
+/// A ring around the y axis, `major_radius` being the distance from the
+/// origin to the middle of the tube and `minor_radius` the radius of the tube.
+#[derive(Debug)]
+pub struct Torus {
+    major_radius: F,
+    minor_radius: F,
+    props: Props,
+}
+
+impl Torus {
+    #[must_use]
+    pub fn major_radius(mut self, major_radius: impl Into<F>) -> Self {
+        self.major_radius = major_radius.into();
+
+        self
+    }
+
+    #[must_use]
+    pub fn minor_radius(mut self, minor_radius: impl Into<F>) -> Self {
+        self.minor_radius = minor_radius.into();
+
+        self
+    }
+}
+
+impl Default for Torus {
+    fn default() -> Self {
+        Self {
+            major_radius: 1.0,
+            minor_radius: 0.25,
+            props: Props::default(),
+        }
+    }
+}
+
+impl Shape for Torus {
+    fn props(&self) -> &Props {
+        &self.props
+    }
+
+    fn props_mut(&mut self) -> &mut Props {
+        &mut self.props
+    }
+
+    fn local_intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        let r2 = self.major_radius.powi(2);
+        let bound = self.major_radius + self.minor_radius;
+
+        // the quartic loses precision when the ray starts far away, so move
+        // the origin up to the bounding sphere first
+        let origin = ray.origin - pt(0, 0, 0);
+        let dd = ray.direction.dot(ray.direction);
+        let start = (-origin.dot(ray.direction) / dd - bound / dd.sqrt()).max(0.0);
+        let o = origin + ray.direction * start;
+        let d = ray.direction;
+
+        // substitute the ray into (x² + y² + z² + R² - r²)² = 4R²(x² + z²)
+        let e = o.dot(o) + r2 - self.minor_radius.powi(2);
+        let f = o.dot(d);
+
+        let roots = solve_quartic(
+            dd * dd,
+            4.0 * dd * f,
+            2.0 * dd * e + 4.0 * f * f - 4.0 * r2 * (d.x * d.x + d.z * d.z),
+            4.0 * f * e - 8.0 * r2 * (o.x * d.x + o.z * d.z),
+            e * e - 4.0 * r2 * (o.x * o.x + o.z * o.z),
+        );
+
+        roots
+            .into_iter()
+            .map(|t| self.intersection(t + start))
+            .collect()
+    }
+
+    fn local_normal_at(&self, point: Tuple) -> Tuple {
+        // point away from the nearest point on the circle in the middle of
+        // the tube
+        let distance = (point.x.powi(2) + point.z.powi(2)).sqrt();
+
+        if distance < EPSILON {
+            return v(0, point.y.signum(), 0);
+        }
+
+        let scale = self.major_radius / distance;
+        let center = pt(point.x * scale, 0, point.z * scale);
+
+        point - center
+    }
+
+    fn local_tangent_at(&self, Tuple { x, z, .. }: Tuple) -> Tuple {
+        // around the y axis, like the equator of a sphere, and undefined on
+        // the axis itself where a fat torus closes up
+        if x.abs() < EPSILON && z.abs() < EPSILON {
+            return v(1, 0, 0);
+        }
+
+        v(-z, 0, x).normalize()
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let outer = self.major_radius + self.minor_radius;
+
+        BoundingBox::new(
+            pt(-outer, -self.minor_radius, -outer),
+            pt(outer, self.minor_radius, outer),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn the_default_torus() {
+        let t = Torus::default();
+
+        assert_eq!(t.major_radius, 1.0);
+        assert_eq!(t.minor_radius, 0.25);
+    }
+
+    #[test]
+    fn a_ray_intersects_a_torus() {
+        let t = Torus::default();
+
+        for (origin, direction, expected) in [
+            // through the middle, crossing the tube twice
+            (pt(-5, 0, 0), v(1, 0, 0), vec![3.75, 4.25, 5.75, 6.25]),
+            (pt(0, 0, 5), v(0, 0, -1), vec![3.75, 4.25, 5.75, 6.25]),
+            // down through the tube
+            (pt(1, 5, 0), v(0, -1, 0), vec![4.75, 5.25]),
+            // a direction that isn't normalized
+            (pt(0, -5, -1), v(0, 2, 0), vec![2.375, 2.625]),
+            // from inside the tube
+            (pt(1, 0, 0), v(0, 1, 0), vec![-0.25, 0.25]),
+        ] {
+            let r = ray(origin, direction);
+            let xs = t.local_intersect(r);
+
+            assert_eq!(xs.len(), expected.len());
+            for (i, t) in xs.iter().zip(expected) {
+                assert_fuzzy_eq!(i.t, t);
+            }
+        }
+    }
+
+    #[test]
+    fn a_ray_misses_a_torus() {
+        let t = Torus::default();
+
+        for (origin, direction) in [
+            // through the hole
+            (pt(0, 5, 0), v(0, -1, 0)),
+            // above the tube
+            (pt(-5, 0.3, 0), v(1, 0, 0)),
+            // outside of it
+            (pt(2, 0, -5), v(0, 0, 1)),
+        ] {
+            let xs = t.local_intersect(ray(origin, direction));
+
+            assert!(xs.is_empty());
+        }
+    }
+
+    #[test]
+    fn intersecting_a_torus_with_other_radii() {
+        let t = Torus::default().major_radius(2).minor_radius(1);
+
+        let xs = t.local_intersect(ray(pt(-10, 0, 0), v(1, 0, 0)));
+
+        let ts = xs.iter().map(|i| i.t).collect::<Vec<_>>();
+        assert_eq!(ts.len(), 4);
+        for (t, expected) in ts.into_iter().zip([7.0, 9.0, 11.0, 13.0]) {
+            assert_fuzzy_eq!(t, expected);
+        }
+    }
+
+    #[test]
+    fn a_ray_from_far_away_still_hits_precisely() {
+        let t = Torus::default();
+
+        let xs = t.local_intersect(ray(pt(-10000, 0, 0), v(1, 0, 0)));
+
+        assert_eq!(xs.len(), 4);
+        assert_fuzzy_eq!(xs[0].t, 9998.75);
+        assert_fuzzy_eq!(xs[3].t, 10001.25);
+    }
+
+    #[test]
+    fn the_normal_on_a_torus() {
+        let t = Torus::default();
+
+        for (point, normal) in [
+            (pt(1.25, 0, 0), v(1, 0, 0)),
+            (pt(0.75, 0, 0), v(-1, 0, 0)),
+            (pt(1, 0.25, 0), v(0, 1, 0)),
+            (pt(0, -0.25, -1), v(0, -1, 0)),
+            (pt(0, 0, 1.25), v(0, 0, 1)),
+        ] {
+            assert_fuzzy_eq!(t.local_normal_at(point).normalize(), normal);
+        }
+    }
+
+    #[test]
+    fn the_tangent_on_a_torus() {
+        let t = Torus::default();
+
+        assert_fuzzy_eq!(t.local_tangent_at(pt(1.25, 0, 0)), v(0, 0, 1));
+        assert_fuzzy_eq!(t.local_tangent_at(pt(0, 0.25, -1)), v(1, 0, 0));
+    }
+
+    #[test]
+    fn the_bounds_of_a_torus() {
+        let t = Torus::default();
+
+        assert_eq!(
+            t.bounds(),
+            BoundingBox::new(pt(-1.25, -0.25, -1.25), pt(1.25, 0.25, 1.25))
+        );
+    }
+
+    #[test]
+    fn the_tangent_on_the_axis_of_a_fat_torus() {
+        let t = Torus::default().major_radius(0.5).minor_radius(1);
+
+        assert_eq!(t.local_tangent_at(pt(0, 0.866_025, 0)), v(1, 0, 0));
+    }
+}
@@ -28,7 +28,7 @@ macro_rules! assert_fuzzy_ne {
     ($left:expr, $right:expr) => {{
         match (&$left, &$right) {
             (left_val, right_val) => {
-                if left_val.fuzzy_eq(*right_val) {
+                if left_val.fuzzy_eq(right_val) {
                     panic!("assertion failed: {left_val:?} is fuzzy equal to {right_val:?}");
                 }
             }
@@ -1,11 +1,13 @@
+use crate::sampling::glossy_direction;
 use crate::{
-    color, pt, ray, Color, Comps, Intersection, Intersections, Material, Matrix, PointLight, Ray,
-    Shape, Sphere, Transforms, Tuple, BLACK,
+    color, pt, ray, Background, Color, Comps, Intersection, Intersections, Material, Matrix,
+    PointLight, Ray, RayBudget, Rng, Shape, Sphere, Transforms, Tuple, BLACK, F, INFINITY, WHITE,
 };
 
 pub struct World {
     pub objects: Vec<Box<dyn Shape>>,
     pub lights: Vec<PointLight>,
+    pub background: Background,
 }
 
 impl World {
@@ -14,104 +16,282 @@ impl World {
         World {
             objects: vec![],
             lights: vec![],
+            background: Background::default(),
         }
     }
 
     #[must_use]
-    pub fn intersect(&self, ray: Ray) -> Vec<Intersection> {
+    pub fn intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
         self.objects.iter().flat_map(|o| o.intersect(ray)).collect()
     }
 
-    pub fn shade_hit(&self, comps: &Comps, remaining: u8) -> Color {
-        let surface = self
+    pub fn shade_hit(&self, comps: &Comps, remaining: impl Into<RayBudget>) -> Color {
+        let remaining = remaining.into();
+        let lit = self
             .lights
             .iter()
             .map(|&l| {
-                comps.object.props().material.lighting(
+                comps.object.props().material.illuminate(
                     comps.object,
                     l,
                     comps.over_point,
                     comps.eyev,
                     comps.normalv,
-                    self.is_shadowed(l, comps.over_point),
+                    self.shadow_attenuation(l, comps.over_point),
                 )
             })
             .sum::<Color>();
 
+        let material = &comps.object.props().material;
+
+        // diffuse light from a precomputed environment, which isn't shadowed
+        let environment = self
+            .background
+            .irradiance_at(comps.normalv)
+            .map_or(BLACK, |e| {
+                let color = material.color_at(comps.object, comps.over_point);
+                material.diffuse_albedo(color, comps.eyev, comps.normalv) * e
+            });
+
+        let surface = material.emissive + lit + environment;
+
         let reflected = self.reflected_color(comps, remaining);
         let refracted = self.refracted_color(comps, remaining);
 
-        let material = comps.object.props().material;
-
         if material.reflective > 0.0 && material.transparency > 0.0 {
             let reflectance = comps.schlick();
             return surface + reflected * reflectance + refracted * (1.0 - reflectance);
         }
 
+        if material.reflective > 0.0 && material.fresnel {
+            // whatever light is reflected off the varnish doesn't reach the
+            // surface underneath, though the surface's own glow still gets out
+            let reflectance = comps.schlick();
+            return material.emissive
+                + (lit + environment) * (1.0 - material.reflective * reflectance)
+                + reflected * reflectance;
+        }
+
         surface + reflected + refracted
     }
 
-    pub fn color_at(&self, ray: Ray, remaining: u8) -> Color {
+    pub fn color_at(&self, ray: Ray, remaining: impl Into<RayBudget>) -> Color {
+        let remaining = remaining.into();
         let xs = self.intersect(ray);
 
-        xs.hit().map_or(BLACK, |&h| {
-            let comps = h.prepare_computations(ray, &xs);
-            self.shade_hit(&comps, remaining)
-        })
+        xs.hit().map_or_else(
+            || self.background.color_at(ray.direction),
+            |&h| {
+                let comps = h.prepare_computations(ray, &xs);
+                self.shade_hit(&comps, remaining)
+            },
+        )
     }
 
     #[must_use]
     pub fn is_shadowed(&self, light: PointLight, point: Tuple) -> bool {
+        self.shadow_attenuation(light, point) == BLACK
+    }
+
+    /// The fraction of the light's intensity that reaches `point`.
+    pub fn shadow_attenuation(&self, light: PointLight, point: Tuple) -> Color {
         let v = light.position - point;
-        let distance = v.magnitude();
-        let direction = v.normalize();
 
+        self.transmittance(point, v.normalize(), v.magnitude())
+    }
+
+    /// How much of the light travelling from `point` along `direction` makes
+    /// it past the objects within `distance`. Transparent objects let part of
+    /// it through at every surface it crosses, while objects that don't cast
+    /// shadows are ignored altogether.
+    pub fn transmittance(&self, point: Tuple, direction: Tuple, distance: F) -> Color {
         let r = ray(point, direction);
+        let mut attenuation = WHITE;
+
+        for i in self.intersect(r) {
+            let props = i.object.props();
 
-        self.intersect(r).hit().is_some_and(|&h| h.t < distance)
+            if i.t < 0.0 || i.t >= distance || !props.casts_shadow {
+                continue;
+            }
+
+            let material = &props.material;
+            let filter = if material.tinted_shadow {
+                material.color_at(i.object, r.position(i.t))
+            } else {
+                WHITE
+            };
+
+            attenuation = attenuation * filter * material.transparency;
+
+            if attenuation == BLACK {
+                break;
+            }
+        }
+
+        attenuation
     }
 
-    pub fn reflected_color(&self, comps: &Comps, remaining: u8) -> Color {
-        if comps.object.props().material.reflective == 0.0 || remaining == 0 {
+    pub fn reflected_color(&self, comps: &Comps, remaining: impl Into<RayBudget>) -> Color {
+        let reflective = comps.object.props().material.reflective;
+
+        if reflective == 0.0 {
             return BLACK;
         }
 
-        let reflect_ray = ray(comps.over_point, comps.reflectv);
-        let color = self.color_at(reflect_ray, remaining - 1);
+        let Some(remaining) = remaining.into().reflect(reflective * reflectance(comps)) else {
+            return BLACK;
+        };
+
+        // the reflected ray stays in the medium the ray came through
+        let color = self.glossy_color_at(
+            comps,
+            comps.over_point,
+            comps.reflectv,
+            comps.from_object,
+            remaining,
+        );
 
-        color * comps.object.props().material.reflective
+        color * reflective
     }
 
-    pub fn refracted_color(&self, comps: &Comps, remaining: u8) -> Color {
-        if comps.object.props().material.transparency == 0.0 || remaining == 0 {
+    pub fn refracted_color(&self, comps: &Comps, remaining: impl Into<RayBudget>) -> Color {
+        let transparency = comps.object.props().material.transparency;
+
+        if transparency == 0.0 {
             return BLACK;
         }
 
-        // find the ratio of the first index of refraction to the second
-        // inverted from the definition of Snell's Law
-        let n_ratio = comps.n1 / comps.n2;
+        let Some(remaining) = remaining
+            .into()
+            .refract(transparency * transmittance(comps))
+        else {
+            return BLACK;
+        };
+
+        // trace every channel separately when the index depends on the
+        // wavelength, keeping only that channel of the color found
+        if let Some(indices) = comps.dispersed_indices() {
+            let [red, green, blue] = indices.map(|(n1, n2)| {
+                comps
+                    .refracted_direction_between(n1, n2)
+                    .map_or(BLACK, |direction| {
+                        self.glossy_color_at(
+                            comps,
+                            comps.under_point,
+                            direction,
+                            comps.to_object,
+                            remaining,
+                        )
+                    })
+            });
+
+            return color(red.red, green.green, blue.blue) * transparency;
+        }
+
+        let Some(direction) = comps.refracted_direction() else {
+            return BLACK;
+        };
 
-        // cos(theta_i) is the same as the dot product of the two vectors
-        let cos_i = comps.eyev.dot(comps.normalv);
+        // find the color of the refracted ray, making sure to multiply
+        // by the transparency value to account for any opacity
+        let color = self.glossy_color_at(
+            comps,
+            comps.under_point,
+            direction,
+            comps.to_object,
+            remaining,
+        );
 
-        // find sin(theta_t) ^ 2 via trigonometric identity
-        let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
-        if sin2_t > 1.0 {
-            return Color::new(0.0, 0.0, 0.0);
+        color * transparency
+    }
+
+    /// The color seen from `origin` along `direction`, averaged over rays
+    /// jittered by the blur of the material that was hit. Only the first
+    /// blurry hit along a path is sampled more than once.
+    fn glossy_color_at(
+        &self,
+        comps: &Comps,
+        origin: Tuple,
+        direction: Tuple,
+        medium: Option<&dyn Shape>,
+        remaining: RayBudget,
+    ) -> Color {
+        let material = &comps.object.props().material;
+
+        if material.blur == 0.0 || material.blur_samples == 0 {
+            return self.absorbed_color_at(ray(origin, direction), medium, remaining);
         }
 
-        // cos(theta_t) via trigonometric identity
-        let cos_t = (1.0 - sin2_t).sqrt();
+        // seeded by the hit so that renders are reproducible
+        let mut rng = Rng::for_point(comps.point);
 
-        // compute the direction of the refracted ray
-        let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
+        // a ray that was already split at a blurry hit above follows a single
+        // jittered ray, so the number of rays doesn't grow with every bounce
+        if remaining.blurred {
+            let d = glossy_direction(direction, comps.normalv, material.blur, &mut rng);
+            return self.absorbed_color_at(ray(origin, d), medium, remaining);
+        }
 
-        // create the refracted ray
-        let refract_ray = Ray::new(comps.under_point, direction);
+        let remaining = remaining.split(material.blur_samples);
 
-        // find the color of the refracted ray, making sure to multiply
-        // by the transparency value to account for any opacity
-        self.color_at(refract_ray, remaining - 1) * comps.object.props().material.transparency
+        let total = (0..material.blur_samples)
+            .map(|_| {
+                let d = glossy_direction(direction, comps.normalv, material.blur, &mut rng);
+                self.absorbed_color_at(ray(origin, d), medium, remaining)
+            })
+            .sum::<Color>();
+
+        total * (1.0 / material.blur_samples as F)
+    }
+
+    /// Like `World::color_at` for a ray travelling through `medium`, which
+    /// absorbs part of the light on its way to the next hit.
+    fn absorbed_color_at(
+        &self,
+        ray: Ray,
+        medium: Option<&dyn Shape>,
+        remaining: RayBudget,
+    ) -> Color {
+        let Some(material) = medium.map(|o| &o.props().material) else {
+            return self.color_at(ray, remaining);
+        };
+
+        if material.absorption == BLACK {
+            return self.color_at(ray, remaining);
+        }
+
+        let xs = self.intersect(ray);
+
+        xs.hit().map_or_else(
+            || self.background.color_at(ray.direction) * material.transmission(INFINITY),
+            |&h| {
+                let comps = h.prepare_computations(ray, &xs);
+                self.shade_hit(&comps, remaining) * material.transmission(h.t)
+            },
+        )
+    }
+}
+
+/// The share of the reflected light `World::shade_hit` keeps for the
+/// surface, which the Fresnel effect dims when looking straight at it.
+fn reflectance(comps: &Comps) -> F {
+    let material = &comps.object.props().material;
+
+    if material.transparency > 0.0 || material.fresnel {
+        comps.schlick()
+    } else {
+        1.0
+    }
+}
+
+/// The share of the refracted light `World::shade_hit` keeps for the
+/// surface, whatever isn't reflected when it's also reflective.
+fn transmittance(comps: &Comps) -> F {
+    if comps.object.props().material.reflective > 0.0 {
+        1.0 - comps.schlick()
+    } else {
+        1.0
     }
 }
 
@@ -131,6 +311,7 @@ impl Default for World {
         Self {
             objects: vec![s1.into(), s2.into()],
             lights: vec![light],
+            background: Background::default(),
         }
     }
 }
@@ -229,6 +410,19 @@ mod tests {
         assert_eq!(c, color(0, 0, 0));
     }
 
+    #[test]
+    fn color_when_a_ray_misses_with_a_background() {
+        let w = World {
+            background: Background::Solid(color(0.1, 0.2, 0.3)),
+            ..Default::default()
+        };
+
+        let r = Ray::new(pt(0, 0, -5), v(0, 1, 0));
+        let c = w.color_at(r, 1);
+
+        assert_eq!(c, color(0.1, 0.2, 0.3));
+    }
+
     #[test]
     fn color_when_a_ray_hits() {
         let w = World::default();
@@ -295,6 +489,75 @@ mod tests {
         assert!(!w.is_shadowed(l, p));
     }
 
+    #[test]
+    fn an_opaque_object_blocks_all_light() {
+        let w = World::default();
+        let p = pt(10, -10, 10);
+        let l = w.lights[0];
+
+        assert_eq!(w.shadow_attenuation(l, p), BLACK);
+    }
+
+    #[test]
+    fn a_glass_sphere_lets_the_light_through() {
+        let w = World {
+            objects: vec![glass_sphere().into()],
+            ..Default::default()
+        };
+
+        let p = pt(10, -10, 10);
+        let l = w.lights[0];
+
+        assert!(!w.is_shadowed(l, p));
+        assert_fuzzy_eq!(w.shadow_attenuation(l, p), WHITE);
+    }
+
+    #[test]
+    fn every_surface_of_a_transparent_object_attenuates_the_light() {
+        let sphere = Sphere::default().material(Material::default().transparency(0.5));
+        let w = World {
+            objects: vec![sphere.into()],
+            ..Default::default()
+        };
+
+        let p = pt(10, -10, 10);
+        let l = w.lights[0];
+
+        assert_fuzzy_eq!(w.shadow_attenuation(l, p), color(0.25, 0.25, 0.25));
+    }
+
+    #[test]
+    fn a_tinted_shadow_takes_the_color_of_the_object() {
+        let sphere = Sphere::default().material(
+            Material::default()
+                .transparency(1)
+                .rgb(1, 0.5, 0)
+                .tinted_shadow(true),
+        );
+        let w = World {
+            objects: vec![sphere.into()],
+            ..Default::default()
+        };
+
+        let p = pt(10, -10, 10);
+        let l = w.lights[0];
+
+        assert_fuzzy_eq!(w.shadow_attenuation(l, p), color(1, 0.25, 0));
+    }
+
+    #[test]
+    fn an_object_that_casts_no_shadow() {
+        let mut w = World::default();
+        for o in &mut w.objects {
+            o.props_mut().casts_shadow = false;
+        }
+
+        let p = pt(10, -10, 10);
+        let l = w.lights[0];
+
+        assert!(!w.is_shadowed(l, p));
+    }
+
     #[test]
     fn shade_hit_is_given_an_intersection_in_shadow() {
         let s1 = Sphere::default();
@@ -304,6 +567,7 @@ mod tests {
         let w = World {
             objects: vec![s1.into(), s2.into()],
             lights: vec![light],
+            ..Default::default()
         };
 
         let r = ray(pt(0, 0, 5), v(0, 0, 1));
@@ -315,6 +579,27 @@ mod tests {
         assert_eq!(c, color(0.1, 0.1, 0.1));
     }
 
+    #[test]
+    fn shade_hit_behind_a_glass_sphere() {
+        let s1 = glass_sphere();
+        let s2 = Sphere::default().transform(Matrix::translation(0, 0, 10));
+        let light = point_light(pt(0, 0, -10), color(1, 1, 1));
+
+        let w = World {
+            objects: vec![s1.into(), s2.into()],
+            lights: vec![light],
+            ..Default::default()
+        };
+
+        let r = ray(pt(0, 0, 5), v(0, 0, 1));
+        let s = w.objects.get(1).unwrap();
+        let i = s.intersection(4.0);
+
+        let comps = i.prepare_computations(r, &[i]);
+        let c = w.shade_hit(&comps, 1);
+        assert_fuzzy_eq!(c, color(1.9, 1.9, 1.9));
+    }
+
     #[test]
     fn reflected_color_for_a_nonreflective_material() {
         let r = ray(pt(0, 0, 0), v(0, 0, 1));
@@ -535,7 +820,9 @@ mod tests {
         let comps = xs[0].prepare_computations(r, &xs);
         let c = w.shade_hit(&comps, 5);
 
-        assert_fuzzy_eq!(c, color(0.93642, 0.68642, 0.68642));
+        // the ball under the floor is lit through the transparent floor, so
+        // it's brighter than it would be behind an opaque one
+        assert_fuzzy_eq!(c, color(1.12547, 0.68642, 0.68642));
     }
 
     #[test]
@@ -569,6 +856,445 @@ mod tests {
 
         let c = w.shade_hit(&comps, 5);
 
-        assert_fuzzy_eq!(c, color(0.93391, 0.69643, 0.69243));
+        // the ball is lit through the floor, as above
+        assert_fuzzy_eq!(c, color(1.11500, 0.69643, 0.69243));
+    }
+
+    #[test]
+    fn shade_hit_adds_the_emission_once() {
+        let mut w = World::default();
+        w.lights.push(point_light(pt(10, 10, -10), color(1, 1, 1)));
+
+        let lamp = Sphere::default().material(
+            Material::default()
+                .emissive(color(1, 0.5, 0))
+                .ambient(0)
+                .diffuse(0)
+                .specular(0),
+        );
+
+        let r = ray(pt(0, 0, -5), v(0, 0, 1));
+        let i = lamp.intersection(4.0);
+        let comps = i.prepare_computations(r, &[i]);
+        let c = w.shade_hit(&comps, 5);
+
+        assert_fuzzy_eq!(c, color(1, 0.5, 0));
+    }
+
+    #[test]
+    fn an_emissive_object_glows_without_lights() {
+        let mut w = World::new();
+        let lamp = Sphere::default().material(Material::default().emissive(color(1, 1, 0.8)));
+        w.objects.push(lamp.into());
+
+        let c = w.color_at(ray(pt(0, 0, -5), v(0, 0, 1)), 5);
+
+        assert_fuzzy_eq!(c, color(1, 1, 0.8));
+    }
+
+    #[test]
+    fn reflections_show_the_background() {
+        let mirror = Plane::default().material(
+            Material::default()
+                .reflective(1)
+                .ambient(0)
+                .diffuse(0)
+                .specular(0),
+        );
+
+        let w = World {
+            objects: vec![mirror.into()],
+            lights: vec![],
+            background: Background::Gradient {
+                bottom: BLACK,
+                top: WHITE,
+            },
+        };
+
+        let r = ray(pt(0, 1, 0), v(0, -1, 0));
+        let c = w.color_at(r, 5);
+
+        assert_fuzzy_eq!(c, WHITE);
+    }
+
+    #[test]
+    fn shade_hit_with_environment_irradiance() {
+        let environment = Canvas::new_with_color(8, 4, color(0.5, 0.5, 0.5));
+
+        let w = World {
+            lights: vec![],
+            background: Background::Image(EnvironmentMap::new(environment).irradiance(8, 4)),
+            ..Default::default()
+        };
+
+        let r = Ray::new(pt(0, 0, -5), v(0, 0, 1));
+        let shape = w.objects.first().unwrap();
+        let i = shape.intersection(4.0);
+        let comps = i.prepare_computations(r, &[i]);
+        let c = w.shade_hit(&comps, 1);
+
+        // the outer sphere has a diffuse of 0.7
+        let expected = color(0.8, 1.0, 0.6) * 0.35;
+        assert!((c.red - expected.red).abs() < 0.01);
+        assert!((c.green - expected.green).abs() < 0.01);
+        assert!((c.blue - expected.blue).abs() < 0.01);
+    }
+
+    #[test]
+    fn metals_get_no_diffuse_light_from_the_environment() {
+        let environment = Canvas::new_with_color(8, 4, color(0.5, 0.5, 0.5));
+        let metal = Sphere::default().material(
+            Material::default()
+                .brdf(Brdf::CookTorrance)
+                .metallic(1)
+                .roughness(0.8),
+        );
+
+        let w = World {
+            objects: vec![metal.into()],
+            lights: vec![],
+            background: Background::Image(EnvironmentMap::new(environment).irradiance(8, 4)),
+        };
+
+        let r = Ray::new(pt(0, 0, -5), v(0, 0, 1));
+        let shape = w.objects.first().unwrap();
+        let i = shape.intersection(4.0);
+        let comps = i.prepare_computations(r, &[i]);
+
+        assert_eq!(w.shade_hit(&comps, 1), BLACK);
+    }
+
+    #[test]
+    fn light_is_absorbed_by_the_distance_travelled_through_glass() {
+        // a thick ball and a thin lens made of the same tinted glass
+        for (depth, expected) in [
+            (1.0, color(1, 0.13534, 0.01832)),
+            (0.1, color(1, 0.81874, 0.67033)),
+        ] {
+            let glass = Sphere::default()
+                .transform(Matrix::scaling(1, 1, depth))
+                .material(
+                    Material::default()
+                        .transparency(1)
+                        .absorption(color(0, 1, 2)),
+                );
+
+            let w = World {
+                objects: vec![glass.into()],
+                lights: vec![],
+                background: Background::Solid(WHITE),
+            };
+
+            let c = w.color_at(ray(pt(0, 0, -5), v(0, 0, 1)), 5);
+
+            assert_fuzzy_eq!(c, expected);
+        }
+    }
+
+    fn dispersive_world(dispersion: Dispersion) -> World {
+        let mut w = World::default();
+
+        {
+            let a = w.objects[0].as_mut();
+            a.props_mut().material.ambient = 1.0;
+            a.props_mut().material.pattern = Some(test());
+
+            let b = w.objects[1].as_mut();
+            b.props_mut().material.transparency = 1.0;
+            b.props_mut().material.refractive_index = 1.5;
+            b.props_mut().material.dispersion = Some(dispersion);
+        }
+
+        w
+    }
+
+    #[test]
+    fn dispersion_without_spread_refracts_like_a_single_index() {
+        let w = dispersive_world(Dispersion::Cauchy { a: 1.5, b: 0.0 });
+        let a = w.objects[0].as_ref();
+        let b = w.objects[1].as_ref();
+
+        let r = ray(pt(0, 0, 0.1), v(0, 1, 0));
+        let xs = [
+            a.intersection(-0.9899),
+            b.intersection(-0.4899),
+            b.intersection(0.4899),
+            a.intersection(0.9899),
+        ];
+
+        let comps = xs[2].prepare_computations(r, &xs);
+        let c = w.refracted_color(&comps, 5);
+
+        assert_fuzzy_eq!(c, color(0, 0.99888, 0.04721));
+    }
+
+    #[test]
+    fn dispersion_splits_the_refracted_ray_by_channel() {
+        let w = dispersive_world(Dispersion::Abbe(20.0));
+        let a = w.objects[0].as_ref();
+        let b = w.objects[1].as_ref();
+
+        let r = ray(pt(0, 0, 0.1), v(0, 1, 0));
+        let xs = [
+            a.intersection(-0.9899),
+            b.intersection(-0.4899),
+            b.intersection(0.4899),
+            a.intersection(0.9899),
+        ];
+
+        let comps = xs[2].prepare_computations(r, &xs);
+        let c = w.refracted_color(&comps, 5);
+
+        // green sees the same index as before, while blue is bent more and
+        // ends up somewhere else on the test pattern
+        assert_fuzzy_eq!(c.green, 0.99888);
+        assert!((c.blue - 0.04721).abs() > 0.001, "{c:?}");
+    }
+
+    fn mirror_world(mirror: Material) -> World {
+        // a dark ball floating over a mirror, against a white sky
+        let floor = Plane::default().material(mirror);
+        let ball = Sphere::default().transform(Matrix::translation(0, 5, 5));
+
+        World {
+            objects: vec![floor.into(), ball.into()],
+            lights: vec![],
+            background: Background::Solid(WHITE),
+        }
+    }
+
+    #[test]
+    fn a_sharp_mirror_reflects_a_single_ray() {
+        let w = mirror_world(Material::default().rgb(0, 0, 0).reflective(1));
+        let r = ray(pt(0, 1, -1), v(0, -1, 1).normalize());
+
+        assert_fuzzy_eq!(w.color_at(r, 5), BLACK);
+    }
+
+    #[test]
+    fn a_blurry_mirror_averages_several_rays() {
+        let w = mirror_world(
+            Material::default()
+                .rgb(0, 0, 0)
+                .reflective(1)
+                .blur(0.2)
+                .blur_samples(64),
+        );
+        let r = ray(pt(0, 1, -1), v(0, -1, 1).normalize());
+
+        let c = w.color_at(r, 5);
+
+        // some of the rays miss the ball and see the sky
+        assert!(c.red > 0.1 && c.red < 0.9, "{c:?}");
+        // but the result is the same every time
+        assert_eq!(c, w.color_at(r, 5));
+    }
+
+    #[test]
+    fn frosted_glass_blurs_what_is_behind_it() {
+        let glass = Plane::default().material(
+            Material::default()
+                .rgb(0, 0, 0)
+                .transparency(1)
+                .blur(0.2)
+                .blur_samples(64),
+        );
+        let ball = Sphere::default().transform(Matrix::translation(0, -6, 0));
+
+        let w = World {
+            objects: vec![glass.into(), ball.into()],
+            lights: vec![],
+            background: Background::Solid(WHITE),
+        };
+
+        let c = w.color_at(ray(pt(0, 1, 0), v(0, -1, 0)), 5);
+
+        assert!(c.red > 0.1 && c.red < 0.9, "{c:?}");
+    }
+
+    #[test]
+    fn blurry_mirrors_facing_each_other_only_split_the_first_bounce() {
+        let mirror = Material::default()
+            .reflective(0.9)
+            .blur(0.1)
+            .blur_samples(64);
+        let lower = Plane::default()
+            .material(mirror.clone())
+            .transform(Matrix::translation(0, -1, 0));
+        let upper = Plane::default()
+            .material(mirror)
+            .transform(Matrix::translation(0, 1, 0));
+
+        let w = World {
+            objects: vec![lower.into(), upper.into()],
+            lights: vec![PointLight::new(pt(0, 0, 0), WHITE)],
+            background: Background::Solid(BLACK),
+        };
+
+        // splitting every bounce would trace 64^5 rays here
+        let c = w.color_at(ray(pt(0, 0, 0), v(0, -1, 1).normalize()), 5);
+
+        assert!(c.red > 0.0, "{c:?}");
+    }
+
+    #[test]
+    fn faint_reflections_are_skipped_below_the_minimum_contribution() {
+        let mut w = World::default();
+        let shape = Plane::default()
+            .material(Material::default().reflective(0.5))
+            .transform(Matrix::translation(0, -1, 0));
+
+        w.objects.push(shape.into());
+
+        let r = ray(pt(0, 0, -3), v(0, -F::sqrt(2.0) / 2.0, F::sqrt(2.0) / 2.0));
+        let shape = w.objects.last().unwrap();
+
+        let i = shape.intersection(F::sqrt(2.0));
+        let comps = i.prepare_computations(r, &[i]);
+
+        let bright = RenderSettings::default().min_contribution(0.5).budget();
+        let faint = RenderSettings::default().min_contribution(0.6).budget();
+
+        assert_fuzzy_eq!(
+            w.reflected_color(&comps, bright),
+            color(0.19033, 0.23791, 0.14274)
+        );
+        assert_fuzzy_eq!(w.reflected_color(&comps, faint), BLACK);
+    }
+
+    #[test]
+    fn reflections_off_glass_are_weighted_by_their_reflectance() {
+        let mut w = World::default();
+        let shape = glass_sphere().material(
+            Material::default()
+                .reflective(1)
+                .transparency(1)
+                .refractive_index(1.5),
+        );
+
+        w.objects = vec![shape.into()];
+        w.background = Background::Solid(WHITE);
+
+        // looking straight at glass, only about 4% of the light is reflected
+        let r = ray(pt(0, 0, -5), v(0, 0, 1));
+        let shape = &w.objects[0];
+
+        let i = shape.intersection(4.0);
+        let comps = i.prepare_computations(r, &[i]);
+
+        let bright = RenderSettings::default().min_contribution(0.01).budget();
+        let faint = RenderSettings::default().min_contribution(0.1).budget();
+
+        assert_ne!(w.reflected_color(&comps, bright), BLACK);
+        assert_eq!(w.reflected_color(&comps, faint), BLACK);
+    }
+
+    #[test]
+    fn reflections_and_refractions_have_separate_limits() {
+        let mut w = World::default();
+        let shape = Plane::default()
+            .material(Material::default().reflective(0.5))
+            .transform(Matrix::translation(0, -1, 0));
+
+        w.objects.push(shape.into());
+
+        let r = ray(pt(0, 0, -3), v(0, -F::sqrt(2.0) / 2.0, F::sqrt(2.0) / 2.0));
+        let shape = w.objects.last().unwrap();
+
+        let i = shape.intersection(F::sqrt(2.0));
+        let comps = i.prepare_computations(r, &[i]);
+
+        let no_refractions = RenderSettings::default().refraction_depth(0).budget();
+        let no_reflections = RenderSettings::default().reflection_depth(0).budget();
+
+        assert_fuzzy_eq!(
+            w.reflected_color(&comps, no_refractions),
+            color(0.19033, 0.23791, 0.14274)
+        );
+        assert_fuzzy_eq!(w.reflected_color(&comps, no_reflections), BLACK);
+    }
+
+    fn varnished_floor(material: Material) -> World {
+        let floor = Plane::default().material(material);
+
+        World {
+            objects: vec![floor.into()],
+            lights: vec![],
+            background: Background::Solid(WHITE),
+        }
+    }
+
+    #[test]
+    fn fresnel_reflections_are_weak_head_on() {
+        let w = varnished_floor(
+            Material::default()
+                .reflective(1)
+                .refractive_index(1.5)
+                .fresnel(true),
+        );
+
+        let c = w.color_at(ray(pt(0, 1, 0), v(0, -1, 0)), 5);
+
+        assert_fuzzy_eq!(c, color(0.04, 0.04, 0.04));
+    }
+
+    #[test]
+    fn fresnel_reflections_strengthen_at_grazing_angles() {
+        let w = varnished_floor(
+            Material::default()
+                .reflective(1)
+                .refractive_index(1.5)
+                .fresnel(true),
+        );
+
+        let head_on = w.color_at(ray(pt(0, 1, 0), v(0, -1, 0)), 5);
+        let grazing = w.color_at(ray(pt(0, 1, -10), v(0, -0.1, 1).normalize()), 5);
+
+        assert!(grazing.red > 0.5);
+        assert!(grazing.red > head_on.red);
+    }
+
+    #[test]
+    fn fresnel_reflections_take_light_away_from_the_surface() {
+        let mut w = varnished_floor(
+            Material::default()
+                .reflective(0.5)
+                .refractive_index(1.5)
+                .specular(0)
+                .fresnel(true),
+        );
+        w.background = Background::Solid(BLACK);
+        w.lights = vec![PointLight::new(pt(0, 10, 0), WHITE)];
+
+        let c = w.color_at(ray(pt(0, 1, 0), v(0, -1, 0)), 5);
+
+        assert_fuzzy_eq!(c, color(0.98, 0.98, 0.98));
+    }
+
+    #[test]
+    fn fresnel_reflections_leave_emission_alone() {
+        let mut w = varnished_floor(
+            Material::default()
+                .reflective(0.5)
+                .refractive_index(1.5)
+                .emissive(WHITE)
+                .fresnel(true),
+        );
+        w.background = Background::Solid(BLACK);
+
+        let c = w.color_at(ray(pt(0, 1, 0), v(0, -1, 0)), 5);
+
+        assert_fuzzy_eq!(c, WHITE);
+    }
+
+    #[test]
+    fn without_fresnel_reflections_are_constant() {
+        let w = varnished_floor(Material::default().reflective(0.5).refractive_index(1.5));
+
+        let head_on = w.color_at(ray(pt(0, 1, 0), v(0, -1, 0)), 5);
+        let grazing = w.color_at(ray(pt(0, 1, -10), v(0, -0.1, 1).normalize()), 5);
+
+        assert_fuzzy_eq!(head_on, color(0.5, 0.5, 0.5));
+        assert_fuzzy_eq!(grazing, color(0.5, 0.5, 0.5));
     }
 }